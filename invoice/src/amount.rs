@@ -32,6 +32,11 @@ use strict_types::StrictVal;
 
 use crate::LIB_NAME_RGB_CONTRACT;
 
+/// Amount of a fungible state.
+///
+/// The amount is 64-bit since this is the only width of fungible state
+/// ([`FungibleState::Bits64`]) defined by the RGB consensus; wider amounts
+/// require a new fungible state type to be supported by the consensus first.
 #[derive(
     Wrapper, WrapperMut, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default, From
 )]
@@ -147,6 +152,16 @@ impl Amount {
         self.0.checked_sub(other.into().0).map(Self)
     }
 
+    /// Sums up the amounts, returning `None` on overflow.
+    ///
+    /// Unlike the [`Sum`] implementation, which saturates at the maximal amount
+    /// value, this detects the overflow and must be used for balances and
+    /// supplies.
+    pub fn checked_sum(iter: impl IntoIterator<Item = impl Into<Self>>) -> Option<Self> {
+        iter.into_iter()
+            .try_fold(Amount::ZERO, |sum, value| sum.checked_add(value))
+    }
+
     #[must_use]
     pub fn checked_add_assign(&mut self, other: impl Into<Self>) -> Option<()> {
         *self = self.0.checked_add(other.into().0).map(Self)?;
@@ -159,12 +174,16 @@ impl Amount {
     }
 }
 
+/// Saturates at the maximal amount value; use [`Amount::checked_sum`] where an
+/// overflow must be detected.
 impl Sum<u64> for Amount {
     fn sum<I: Iterator<Item = u64>>(iter: I) -> Self {
         iter.fold(Amount::ZERO, |sum, value| sum.saturating_add(value))
     }
 }
 
+/// Saturates at the maximal amount value; use [`Amount::checked_sum`] where an
+/// overflow must be detected.
 impl Sum for Amount {
     fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
         iter.fold(Amount::ZERO, |sum, value| sum.saturating_add(value))
@@ -279,6 +298,14 @@ impl CoinAmount {
             precision,
         }
     }
+
+    /// Converts the coin amount back into atomic amount, returning `None` if
+    /// it exceeds the maximal amount value.
+    pub fn to_amount_checked(&self) -> Option<Amount> {
+        self.precision
+            .checked_convert(self.int)?
+            .checked_add(self.fract)
+    }
 }
 
 impl Display for CoinAmount {
@@ -335,4 +362,23 @@ mod test {
         assert_eq!(format!("{amount}"), "10000.43608195");
         assert_eq!(format!("{amount:#.10}"), "10`000.436`081`950`0");
     }
+
+    #[test]
+    fn checked_sum() {
+        assert_eq!(Amount::checked_sum([1u64, 2, 3]), Some(Amount::from(6u64)));
+        assert_eq!(Amount::checked_sum([u64::MAX, 1]), None);
+        assert_eq!([u64::MAX, 1].into_iter().sum::<Amount>(), Amount::from(u64::MAX));
+    }
+
+    #[test]
+    fn coin_amount_checked() {
+        let amount = CoinAmount::with(u64::MAX, Precision::Atto);
+        assert_eq!(amount.to_amount_checked(), Some(Amount::from(u64::MAX)));
+        let amount = CoinAmount {
+            int: 19,
+            fract: 0,
+            precision: Precision::Atto,
+        };
+        assert_eq!(amount.to_amount_checked(), None);
+    }
 }
@@ -0,0 +1,768 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test schemata implementing the standard interfaces, used by the unit tests
+//! which need real contracts issued and imported into a stock.
+
+use std::collections::BTreeMap;
+
+use amplify::confinement::Confined;
+use bp::dbc::opret::OpretProof;
+use bp::dbc::{Anchor, Method};
+use bp::{Outpoint, Txid, Vout};
+use commit_verify::{mpc, TryCommitVerify};
+use invoice::{Amount, Precision};
+use rgb::vm::AluScript;
+use rgb::{
+    AnchorSet, AssignmentType, ContractId, ExtensionSchema, ExtensionType, FungibleType,
+    GenesisSchema, GenesisSeal, GlobalStateSchema, GlobalStateType, GraphSeal, InputMap,
+    MediaType, Occurrences, OpId, Operation, OutputSeal, Schema, Script, StateSchema, SubSchema,
+    Transition, TransitionBundle, TransitionSchema, TransitionType, Types, ValencyType, WitnessId,
+    XAnchor, XChain, XOutpoint, XOutputSeal,
+};
+use strict_encoding::FieldName;
+use strict_types::{SemId, Ty};
+
+use crate::containers::{BuilderSeal, Contract, Fascia, TransitionInfo};
+use crate::interface::resolver::DumbResolver;
+use crate::interface::rgb21::{rgb21_stl, Allocation, TokenData, TokenIndex};
+use crate::interface::{
+    AssignIface, ContractBuilder, ExtensionIface, GenesisIface, Iface, IfaceClass, IfaceImpl,
+    IssuerClass, Modifier, NamedField, OwnedIface, Req, Rgb20, Rgb21, TransitionIface,
+    ValencyIface, VerNo,
+};
+use crate::persistence::{AcceptPolicy, Inventory, Stash, Stock};
+use crate::stl::{rgb_contract_stl, AssetSpec, AssetTerms, RicardianContract, StandardTypes};
+
+const GS_SPEC: GlobalStateType = GlobalStateType::with(2000);
+const GS_TERMS: GlobalStateType = GlobalStateType::with(2001);
+const GS_ISSUED_SUPPLY: GlobalStateType = GlobalStateType::with(2002);
+const GS_BURNED_SUPPLY: GlobalStateType = GlobalStateType::with(2003);
+const GS_REPLACED_SUPPLY: GlobalStateType = GlobalStateType::with(2004);
+const GS_TOKENS: GlobalStateType = GlobalStateType::with(2102);
+const GS_ENGRAVINGS: GlobalStateType = GlobalStateType::with(2103);
+const GS_ATTACHMENT_TYPES: GlobalStateType = GlobalStateType::with(2104);
+
+const OS_ASSET: AssignmentType = AssignmentType::with(4000);
+const OS_INFLATION: AssignmentType = AssignmentType::with(4001);
+const OS_BURN_RIGHT: AssignmentType = AssignmentType::with(4002);
+const OS_CLAIM_RIGHT: AssignmentType = AssignmentType::with(4003);
+const OS_RIGHT: AssignmentType = AssignmentType::with(4004);
+const OS_DOCUMENT: AssignmentType = AssignmentType::with(4005);
+
+const VS_CLAIM: ValencyType = ValencyType::with(1);
+const VS_BONUS: ValencyType = ValencyType::with(2);
+
+const TS_TRANSFER: TransitionType = TransitionType::with(10000);
+const TS_ISSUE: TransitionType = TransitionType::with(10001);
+const TS_BURN: TransitionType = TransitionType::with(10002);
+const TS_REPLACE: TransitionType = TransitionType::with(10003);
+const TS_ENGRAVE: TransitionType = TransitionType::with(10004);
+
+const ES_CLAIM: ExtensionType = ExtensionType::with(20000);
+
+/// Inflatable and burnable fungible asset schema implementing RGB20
+/// interface, without any validation scripts.
+pub struct TestRgb20;
+
+impl IssuerClass for TestRgb20 {
+    type IssuingIface = Rgb20;
+
+    fn schema() -> SubSchema {
+        let types = StandardTypes::with(rgb_contract_stl());
+        let unit = Ty::<SemId>::UNIT.sem_id_unnamed();
+        let issue_meta = types.get("RGBContract.IssueMeta");
+        let burn_meta = types.get("RGBContract.BurnMeta");
+        let amount = types.get("RGBContract.Amount");
+
+        Schema {
+            ffv: zero!(),
+            flags: none!(),
+            subset_of: None,
+            global_types: tiny_bmap! {
+                GS_SPEC => GlobalStateSchema::once(types.get("RGBContract.AssetSpec")),
+                GS_TERMS => GlobalStateSchema::once(types.get("RGBContract.AssetTerms")),
+                GS_ISSUED_SUPPLY => GlobalStateSchema::many(amount),
+                GS_BURNED_SUPPLY => GlobalStateSchema::many(amount),
+                GS_REPLACED_SUPPLY => GlobalStateSchema::many(amount),
+            },
+            owned_types: tiny_bmap! {
+                OS_ASSET => StateSchema::Fungible(FungibleType::Unsigned64Bit),
+                OS_INFLATION => StateSchema::Fungible(FungibleType::Unsigned64Bit),
+                OS_BURN_RIGHT => StateSchema::Declarative,
+            },
+            valency_types: none!(),
+            genesis: GenesisSchema {
+                metadata: unit,
+                globals: tiny_bmap! {
+                    GS_SPEC => Occurrences::Once,
+                    GS_TERMS => Occurrences::Once,
+                    GS_ISSUED_SUPPLY => Occurrences::Once,
+                },
+                assignments: tiny_bmap! {
+                    OS_ASSET => Occurrences::NoneOrMore,
+                    OS_INFLATION => Occurrences::NoneOrMore,
+                    OS_BURN_RIGHT => Occurrences::NoneOrMore,
+                },
+                valencies: none!(),
+            },
+            extensions: none!(),
+            transitions: tiny_bmap! {
+                TS_TRANSFER => TransitionSchema {
+                    metadata: unit,
+                    globals: none!(),
+                    inputs: tiny_bmap! { OS_ASSET => Occurrences::OnceOrMore },
+                    assignments: tiny_bmap! { OS_ASSET => Occurrences::OnceOrMore },
+                    valencies: none!(),
+                },
+                TS_ISSUE => TransitionSchema {
+                    metadata: issue_meta,
+                    globals: tiny_bmap! { GS_ISSUED_SUPPLY => Occurrences::Once },
+                    inputs: tiny_bmap! { OS_INFLATION => Occurrences::OnceOrMore },
+                    assignments: tiny_bmap! {
+                        OS_ASSET => Occurrences::NoneOrMore,
+                        OS_INFLATION => Occurrences::NoneOrMore,
+                    },
+                    valencies: none!(),
+                },
+                TS_BURN => TransitionSchema {
+                    metadata: burn_meta,
+                    globals: tiny_bmap! { GS_BURNED_SUPPLY => Occurrences::Once },
+                    inputs: tiny_bmap! { OS_BURN_RIGHT => Occurrences::Once },
+                    assignments: tiny_bmap! { OS_BURN_RIGHT => Occurrences::NoneOrOnce },
+                    valencies: none!(),
+                },
+                TS_REPLACE => TransitionSchema {
+                    metadata: burn_meta,
+                    globals: tiny_bmap! { GS_REPLACED_SUPPLY => Occurrences::Once },
+                    inputs: tiny_bmap! { OS_BURN_RIGHT => Occurrences::Once },
+                    assignments: tiny_bmap! {
+                        OS_ASSET => Occurrences::NoneOrMore,
+                        OS_BURN_RIGHT => Occurrences::NoneOrOnce,
+                    },
+                    valencies: none!(),
+                },
+            },
+            types: types.type_system().into(),
+            script: Script::AluVM(AluScript {
+                libs: none!(),
+                entry_points: none!(),
+            }),
+        }
+    }
+
+    fn issue_impl() -> IfaceImpl {
+        let schema = Self::schema();
+
+        IfaceImpl {
+            version: VerNo::V1,
+            schema_id: schema.schema_id(),
+            iface_id: Rgb20::iface().iface_id(),
+            global_state: tiny_bset! {
+                NamedField::with(GS_SPEC, fname!("spec")),
+                NamedField::with(GS_TERMS, fname!("terms")),
+                NamedField::with(GS_ISSUED_SUPPLY, fname!("issuedSupply")),
+                NamedField::with(GS_BURNED_SUPPLY, fname!("burnedSupply")),
+                NamedField::with(GS_REPLACED_SUPPLY, fname!("replacedSupply")),
+            },
+            assignments: tiny_bset! {
+                NamedField::with(OS_ASSET, fname!("assetOwner")),
+                NamedField::with(OS_INFLATION, fname!("inflationAllowance")),
+                NamedField::with(OS_BURN_RIGHT, fname!("burnRight")),
+            },
+            valencies: none!(),
+            transitions: tiny_bset! {
+                NamedField::with(TS_TRANSFER, fname!("transfer")),
+                NamedField::with(TS_ISSUE, fname!("issue")),
+                NamedField::with(TS_BURN, fname!("burn")),
+                NamedField::with(TS_REPLACE, fname!("replace")),
+            },
+            extensions: none!(),
+            script: Script::AluVM(AluScript {
+                libs: none!(),
+                entry_points: none!(),
+            }),
+        }
+    }
+}
+
+/// Engravable and inflatable collectible schema implementing RGB21
+/// interface, without any validation scripts.
+pub struct TestRgb21;
+
+impl IssuerClass for TestRgb21 {
+    type IssuingIface = Rgb21;
+
+    fn schema() -> SubSchema { rgb21_schema(true) }
+
+    fn issue_impl() -> IfaceImpl { rgb21_impl(true) }
+}
+
+/// Collectible schema implementing RGB21 interface, which defines neither
+/// engravings nor attachment types.
+pub struct MinimalRgb21;
+
+impl IssuerClass for MinimalRgb21 {
+    type IssuingIface = Rgb21;
+
+    fn schema() -> SubSchema { rgb21_schema(false) }
+
+    fn issue_impl() -> IfaceImpl { rgb21_impl(false) }
+}
+
+fn rgb21_schema(engravable: bool) -> SubSchema {
+    let types = StandardTypes::with(rgb21_stl());
+    let unit = Ty::<SemId>::UNIT.sem_id_unnamed();
+
+    let mut global_types = tiny_bmap! {
+        GS_SPEC => GlobalStateSchema::once(types.get("RGBContract.AssetSpec")),
+        GS_TERMS => GlobalStateSchema::once(types.get("RGBContract.AssetTerms")),
+        GS_TOKENS => GlobalStateSchema::many(types.get("RGB21.TokenData")),
+    };
+    let mut genesis_globals = tiny_bmap! {
+        GS_SPEC => Occurrences::Once,
+        GS_TERMS => Occurrences::Once,
+        GS_TOKENS => Occurrences::NoneOrMore,
+    };
+    let mut owned_types = tiny_bmap! {
+        OS_ASSET => StateSchema::Structured(types.get("RGB21.Allocation")),
+    };
+    let mut genesis_assignments = tiny_bmap! { OS_ASSET => Occurrences::NoneOrMore };
+    let mut transitions = tiny_bmap! {
+        TS_TRANSFER => TransitionSchema {
+            metadata: unit,
+            globals: none!(),
+            inputs: tiny_bmap! { OS_ASSET => Occurrences::OnceOrMore },
+            assignments: tiny_bmap! { OS_ASSET => Occurrences::OnceOrMore },
+            valencies: none!(),
+        },
+    };
+    if engravable {
+        let engraving = GlobalStateSchema::many(types.get("RGB21.EngravingData"));
+        let attachment_type = GlobalStateSchema::many(types.get("RGB21.AttachmentType"));
+        global_types.insert(GS_ENGRAVINGS, engraving).unwrap();
+        global_types
+            .insert(GS_ATTACHMENT_TYPES, attachment_type)
+            .unwrap();
+        genesis_globals
+            .insert(GS_ATTACHMENT_TYPES, Occurrences::NoneOrMore)
+            .unwrap();
+        let engrave = TransitionSchema {
+            metadata: unit,
+            globals: tiny_bmap! { GS_ENGRAVINGS => Occurrences::Once },
+            inputs: tiny_bmap! { OS_ASSET => Occurrences::OnceOrMore },
+            assignments: tiny_bmap! { OS_ASSET => Occurrences::OnceOrMore },
+            valencies: none!(),
+        };
+        transitions.insert(TS_ENGRAVE, engrave).unwrap();
+        owned_types
+            .insert(OS_INFLATION, StateSchema::Structured(types.get("RGB21.ItemsCount")))
+            .unwrap();
+        genesis_assignments
+            .insert(OS_INFLATION, Occurrences::NoneOrMore)
+            .unwrap();
+    }
+
+    Schema {
+        ffv: zero!(),
+        flags: none!(),
+        subset_of: None,
+        global_types,
+        owned_types,
+        valency_types: none!(),
+        genesis: GenesisSchema {
+            metadata: unit,
+            globals: genesis_globals,
+            assignments: genesis_assignments,
+            valencies: none!(),
+        },
+        extensions: none!(),
+        transitions,
+        types: types.type_system().into(),
+        script: Script::AluVM(AluScript {
+            libs: none!(),
+            entry_points: none!(),
+        }),
+    }
+}
+
+fn rgb21_impl(engravable: bool) -> IfaceImpl {
+    let schema = rgb21_schema(engravable);
+
+    let mut global_state = tiny_bset! {
+        NamedField::with(GS_SPEC, fname!("spec")),
+        NamedField::with(GS_TERMS, fname!("terms")),
+        NamedField::with(GS_TOKENS, fname!("tokens")),
+    };
+    let mut assignments = tiny_bset! {
+        NamedField::with(OS_ASSET, fname!("assetOwner")),
+    };
+    let mut transitions = tiny_bset! {
+        NamedField::with(TS_TRANSFER, fname!("transfer")),
+    };
+    if engravable {
+        assignments
+            .push(NamedField::with(OS_INFLATION, fname!("inflationAllowance")))
+            .unwrap();
+        global_state
+            .push(NamedField::with(GS_ENGRAVINGS, fname!("engravings")))
+            .unwrap();
+        global_state
+            .push(NamedField::with(GS_ATTACHMENT_TYPES, fname!("attachmentTypes")))
+            .unwrap();
+        transitions
+            .push(NamedField::with(TS_ENGRAVE, fname!("engrave")))
+            .unwrap();
+    }
+
+    IfaceImpl {
+        version: VerNo::V1,
+        schema_id: schema.schema_id(),
+        iface_id: Rgb21::iface().iface_id(),
+        global_state,
+        assignments,
+        valencies: none!(),
+        transitions,
+        extensions: none!(),
+        script: Script::AluVM(AluScript {
+            libs: none!(),
+            entry_points: none!(),
+        }),
+    }
+}
+
+/// Tapret output seal of a test transaction with the given number.
+pub fn output(no: u8) -> XOutputSeal {
+    XChain::Bitcoin(OutputSeal::new(Method::TapretFirst, Outpoint::new(Txid::from([no; 32]), 0)))
+}
+
+/// Genesis seal closed over the [`output`] with the same number.
+pub fn genesis_seal(no: u8) -> XChain<GenesisSeal> {
+    XChain::Bitcoin(GenesisSeal::with_blinding(
+        Method::TapretFirst,
+        Txid::from([no; 32]),
+        0,
+        no as u64,
+    ))
+}
+
+/// Builder of a [`TestRgb20`] contract with the asset specification and
+/// terms already set.
+pub fn rgb20_builder() -> ContractBuilder {
+    ContractBuilder::testnet(Rgb20::iface(), TestRgb20::schema(), TestRgb20::issue_impl())
+        .unwrap()
+        .add_global_state("spec", AssetSpec::new("TEST", "Test asset", Precision::Indivisible))
+        .unwrap()
+        .add_global_state("terms", AssetTerms {
+            text: RicardianContract::default(),
+            media: None,
+        })
+        .unwrap()
+}
+
+/// Issues [`TestRgb20`] contract allocating `amounts` to the [`output`]s
+/// with the given numbers.
+pub fn rgb20_contract(amounts: impl IntoIterator<Item = (u8, u64)>) -> Contract {
+    let mut builder = rgb20_builder();
+    let mut issued = Amount::ZERO;
+    for (no, amount) in amounts {
+        builder = builder
+            .add_fungible_state("assetOwner", genesis_seal(no), amount)
+            .unwrap();
+        issued += Amount::from(amount);
+    }
+    builder
+        .add_global_state("issuedSupply", issued)
+        .unwrap()
+        .issue_contract_det(1_700_000_000)
+        .unwrap()
+}
+
+/// Builder of RGB21 contract with the schema `C`, with the asset
+/// specification, terms and the tokens with the given indexes already set.
+pub fn rgb21_builder<C: IssuerClass<IssuingIface = Rgb21>>(
+    tokens: impl IntoIterator<Item = u32>,
+) -> ContractBuilder {
+    let mut builder = ContractBuilder::testnet(Rgb21::iface(), C::schema(), C::issue_impl())
+        .unwrap()
+        .add_global_state("spec", AssetSpec::new("NFT", "Test collection", Precision::Indivisible))
+        .unwrap()
+        .add_global_state("terms", AssetTerms {
+            text: RicardianContract::default(),
+            media: None,
+        })
+        .unwrap();
+    for index in tokens {
+        let token = TokenData {
+            index: TokenIndex::from(index),
+            ..default!()
+        };
+        builder = builder.add_global_state("tokens", token).unwrap();
+    }
+    builder
+}
+
+/// Issues RGB21 contract with the schema `C`, defining the tokens with the
+/// given indexes and allocating `allocations` to the [`output`]s with the
+/// given numbers.
+pub fn rgb21_contract<C: IssuerClass<IssuingIface = Rgb21>>(
+    tokens: impl IntoIterator<Item = u32>,
+    allocations: impl IntoIterator<Item = (u8, Allocation)>,
+) -> Contract {
+    let mut builder = rgb21_builder::<C>(tokens);
+    for (no, allocation) in allocations {
+        builder = builder
+            .add_data("assetOwner", genesis_seal(no), allocation)
+            .unwrap();
+    }
+    builder.issue_contract_det(1_700_000_000).unwrap()
+}
+
+/// Interface of a contract which state is created by the `claim` state
+/// extension, redeeming `claim` valency defined by the genesis. The `bonus`
+/// valency is redeemable by the extension, but is not defined by any
+/// operation.
+pub fn claim_iface() -> Iface {
+    let types = StandardTypes::new();
+
+    Iface {
+        version: VerNo::V1,
+        name: tn!("TestClaim"),
+        inherits: none!(),
+        global_state: none!(),
+        assignments: tiny_bmap! {
+            fname!("claimRight") => AssignIface::public(OwnedIface::Rights, Req::NoneOrMore),
+        },
+        valencies: tiny_bmap! {
+            fname!("claim") => ValencyIface { required: true },
+            fname!("bonus") => ValencyIface { required: false },
+        },
+        genesis: GenesisIface {
+            modifier: Modifier::Final,
+            metadata: None,
+            globals: none!(),
+            assignments: none!(),
+            valencies: tiny_bset! { fname!("claim") },
+            errors: none!(),
+        },
+        transitions: none!(),
+        extensions: tiny_bmap! {
+            fname!("claim") => ExtensionIface {
+                modifier: Modifier::Final,
+                optional: false,
+                metadata: None,
+                globals: none!(),
+                assignments: tiny_bmap! { fname!("claimRight") => Occurrences::OnceOrMore },
+                redeems: tiny_bset! { fname!("claim"), fname!("bonus") },
+                valencies: none!(),
+                errors: none!(),
+                default_assignment: Some(fname!("claimRight")),
+            },
+        },
+        default_operation: None,
+        errors: none!(),
+        types: Types::Strict(types.type_system()),
+    }
+}
+
+/// Schema implementing [`claim_iface`], without any validation scripts.
+pub fn claim_schema() -> SubSchema {
+    let types = StandardTypes::new();
+    let unit = Ty::<SemId>::UNIT.sem_id_unnamed();
+
+    Schema {
+        ffv: zero!(),
+        flags: none!(),
+        subset_of: None,
+        global_types: none!(),
+        owned_types: tiny_bmap! { OS_CLAIM_RIGHT => StateSchema::Declarative },
+        valency_types: tiny_bset! { VS_CLAIM, VS_BONUS },
+        genesis: GenesisSchema {
+            metadata: unit,
+            globals: none!(),
+            assignments: none!(),
+            valencies: tiny_bset! { VS_CLAIM },
+        },
+        extensions: tiny_bmap! {
+            ES_CLAIM => ExtensionSchema {
+                metadata: unit,
+                globals: none!(),
+                redeems: tiny_bset! { VS_CLAIM, VS_BONUS },
+                assignments: tiny_bmap! { OS_CLAIM_RIGHT => Occurrences::OnceOrMore },
+                valencies: none!(),
+            },
+        },
+        transitions: none!(),
+        types: types.type_system().into(),
+        script: Script::AluVM(AluScript {
+            libs: none!(),
+            entry_points: none!(),
+        }),
+    }
+}
+
+/// Implementation of [`claim_iface`] by [`claim_schema`].
+pub fn claim_impl() -> IfaceImpl {
+    IfaceImpl {
+        version: VerNo::V1,
+        schema_id: claim_schema().schema_id(),
+        iface_id: claim_iface().iface_id(),
+        global_state: none!(),
+        assignments: tiny_bset! { NamedField::with(OS_CLAIM_RIGHT, fname!("claimRight")) },
+        valencies: tiny_bset! {
+            NamedField::with(VS_CLAIM, fname!("claim")),
+            NamedField::with(VS_BONUS, fname!("bonus")),
+        },
+        transitions: none!(),
+        extensions: tiny_bset! { NamedField::with(ES_CLAIM, fname!("claim")) },
+        script: Script::AluVM(AluScript {
+            libs: none!(),
+            entry_points: none!(),
+        }),
+    }
+}
+
+/// Issues contract implementing [`claim_iface`], which genesis defines the
+/// `claim` valency.
+pub fn claim_contract() -> Contract {
+    ContractBuilder::testnet(claim_iface(), claim_schema(), claim_impl())
+        .unwrap()
+        .add_valency("claim")
+        .unwrap()
+        .issue_contract_det(1_700_000_000)
+        .unwrap()
+}
+
+/// Test interface with transferable declarative `right` and `document`
+/// attachment state, both of which are moved with the `transfer` transition.
+pub fn rights_iface() -> Iface {
+    let types = StandardTypes::new();
+    let state = tiny_bmap! {
+        fname!("right") => Occurrences::NoneOrMore,
+        fname!("document") => Occurrences::NoneOrMore,
+    };
+
+    Iface {
+        version: VerNo::V1,
+        name: tn!("TestRights"),
+        inherits: none!(),
+        global_state: none!(),
+        assignments: tiny_bmap! {
+            fname!("right") => AssignIface::public(OwnedIface::Rights, Req::NoneOrMore),
+            fname!("document") => AssignIface::public(OwnedIface::AnyAttach, Req::NoneOrMore),
+        },
+        valencies: none!(),
+        genesis: GenesisIface {
+            modifier: Modifier::Final,
+            metadata: None,
+            globals: none!(),
+            assignments: state.clone(),
+            valencies: none!(),
+            errors: none!(),
+        },
+        transitions: tiny_bmap! {
+            fname!("transfer") => TransitionIface {
+                modifier: Modifier::Final,
+                optional: false,
+                metadata: None,
+                globals: none!(),
+                inputs: state.clone(),
+                assignments: state,
+                valencies: none!(),
+                errors: none!(),
+                default_assignment: None,
+            },
+        },
+        extensions: none!(),
+        default_operation: Some(fname!("transfer")),
+        errors: none!(),
+        types: Types::Strict(types.type_system()),
+    }
+}
+
+/// Schema implementing [`rights_iface`], without any validation scripts.
+pub fn rights_schema() -> SubSchema {
+    let types = StandardTypes::new();
+    let unit = Ty::<SemId>::UNIT.sem_id_unnamed();
+    let state = tiny_bmap! {
+        OS_RIGHT => Occurrences::NoneOrMore,
+        OS_DOCUMENT => Occurrences::NoneOrMore,
+    };
+
+    Schema {
+        ffv: zero!(),
+        flags: none!(),
+        subset_of: None,
+        global_types: none!(),
+        owned_types: tiny_bmap! {
+            OS_RIGHT => StateSchema::Declarative,
+            OS_DOCUMENT => StateSchema::Attachment(MediaType::Any),
+        },
+        valency_types: none!(),
+        genesis: GenesisSchema {
+            metadata: unit,
+            globals: none!(),
+            assignments: state.clone(),
+            valencies: none!(),
+        },
+        extensions: none!(),
+        transitions: tiny_bmap! {
+            TS_TRANSFER => TransitionSchema {
+                metadata: unit,
+                globals: none!(),
+                inputs: state.clone(),
+                assignments: state,
+                valencies: none!(),
+            },
+        },
+        types: types.type_system().into(),
+        script: Script::AluVM(AluScript {
+            libs: none!(),
+            entry_points: none!(),
+        }),
+    }
+}
+
+/// Implementation of [`rights_iface`] by [`rights_schema`].
+pub fn rights_impl() -> IfaceImpl {
+    IfaceImpl {
+        version: VerNo::V1,
+        schema_id: rights_schema().schema_id(),
+        iface_id: rights_iface().iface_id(),
+        global_state: none!(),
+        assignments: tiny_bset! {
+            NamedField::with(OS_RIGHT, fname!("right")),
+            NamedField::with(OS_DOCUMENT, fname!("document")),
+        },
+        valencies: none!(),
+        transitions: tiny_bset! { NamedField::with(TS_TRANSFER, fname!("transfer")) },
+        extensions: none!(),
+        script: Script::AluVM(AluScript {
+            libs: none!(),
+            entry_points: none!(),
+        }),
+    }
+}
+
+/// Builder of a contract implementing [`rights_iface`].
+pub fn rights_builder() -> ContractBuilder {
+    ContractBuilder::testnet(rights_iface(), rights_schema(), rights_impl()).unwrap()
+}
+
+/// Creates stock with the provided contracts imported.
+pub fn stock_with(contracts: impl IntoIterator<Item = Contract>) -> Stock {
+    let mut stock = Stock::default();
+    for contract in contracts {
+        stock
+            .import_contract(contract, &mut DumbResolver, &AcceptPolicy::default())
+            .unwrap();
+    }
+    stock
+}
+
+/// Constructs RGB20 transfer spending all the asset allocated to the
+/// [`output`] with number `from` and assigning `amounts` to the outputs of
+/// the witness transaction with the given vouts.
+pub fn rgb20_transfer(
+    stock: &Stock,
+    contract_id: ContractId,
+    from: u8,
+    amounts: impl IntoIterator<Item = (u32, u64)>,
+) -> Transition {
+    let mut builder = stock
+        .transition_builder(contract_id, "RGB20", None::<&str>)
+        .unwrap();
+    for ((opout, _), state) in stock.state_for_outpoints(contract_id, [output(from)]).unwrap() {
+        builder = builder.add_input(opout, state).unwrap();
+    }
+    for (vout, amount) in amounts {
+        let seal = GraphSeal::with_blinded_vout(Method::TapretFirst, Vout::from_u32(vout), 0);
+        builder = builder
+            .add_fungible_state("assetOwner", BuilderSeal::Revealed(XChain::Bitcoin(seal)), amount)
+            .unwrap();
+    }
+    builder.complete_transition().unwrap()
+}
+
+/// Outputs of the witness transaction with the given number, to which the
+/// tests allocate change and beneficiaries.
+pub fn witness_outputs(no: u8) -> [XOutpoint; 2] {
+    let txid = Txid::from([no; 32]);
+    [0, 1].map(|vout| XOutpoint::from(XChain::Bitcoin(Outpoint::new(txid, vout))))
+}
+
+/// Names of the interface assignments spent by the transition.
+pub fn spent_state(stock: &Stock, info: &TransitionInfo) -> Vec<FieldName> {
+    let schema = stock.contract_schema(info.transition.contract_id).unwrap();
+    let iimpl = schema.iimpls.values().next().unwrap();
+    info.transition
+        .inputs
+        .iter()
+        .map(|input| iimpl.assignment_name(input.prev_out.ty).unwrap().clone())
+        .collect()
+}
+
+/// Consumes the transitions into the stock, as if they were committed to by
+/// the witness transaction with the given number, and returns the witness id.
+pub fn consume(
+    stock: &mut Stock,
+    witness_no: u8,
+    transitions: impl IntoIterator<Item = Transition>,
+) -> WitnessId {
+    let txid = Txid::from([witness_no; 32]);
+    let mut vin = 0u32;
+    let mut bundles = BTreeMap::<ContractId, (BTreeMap<Vout, OpId>, BTreeMap<_, _>)>::new();
+    for transition in transitions {
+        let opid = transition.id();
+        let (input_map, known) = bundles.entry(transition.contract_id).or_default();
+        for _ in transition.inputs().iter() {
+            input_map.insert(Vout::from_u32(vin), opid);
+            vin += 1;
+        }
+        known.insert(opid, transition);
+    }
+    let bundles = bundles
+        .into_iter()
+        .map(|(contract_id, (input_map, known))| {
+            let bundle = TransitionBundle {
+                input_map: InputMap::from(Confined::try_from(input_map).unwrap()),
+                known_transitions: Confined::try_from(known).unwrap(),
+            };
+            (contract_id, bundle)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let messages = bundles
+        .iter()
+        .map(|(id, bundle)| (mpc::ProtocolId::from(*id), mpc::Message::from(bundle.bundle_id())));
+    let source = mpc::MultiSource {
+        messages: Confined::try_from_iter(messages).unwrap(),
+        ..default!()
+    };
+    let tree = mpc::MerkleTree::try_commit(&source).unwrap();
+    let anchor = Anchor::new(txid, mpc::MerkleBlock::from(&tree), OpretProof::default());
+    let fascia = Fascia {
+        anchor: XAnchor::Bitcoin(AnchorSet::Opret(anchor)),
+        bundles: Confined::try_from(bundles).unwrap(),
+    };
+    stock.consume(fascia).unwrap();
+    XChain::Bitcoin(txid)
+}
@@ -21,20 +21,30 @@
 
 #![allow(clippy::result_large_err)]
 
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Display;
+use std::iter;
+use std::rc::Rc;
 
-use amplify::confinement::{Confined, SmallOrdSet, TinyOrdMap, U16};
+use amplify::confinement::{Confined, SmallBlob, SmallOrdSet, TinyOrdMap, TinyOrdSet, U16};
 use amplify::{confinement, Wrapper};
 use chrono::Utc;
 use invoice::{Allocation, Amount};
+use rand::random;
+use rgb::validation::{CheckedConsignment, ConsignmentApi};
 use rgb::{
-    validation, AltLayer1, AltLayer1Set, AssetTag, Assign, AssignmentType, Assignments,
-    BlindingFactor, ContractId, DataState, ExposedSeal, FungibleType, Genesis, GenesisSeal,
-    GlobalState, GraphSeal, Input, Layer1, Opout, RevealedAttach, RevealedData, RevealedValue,
-    StateSchema, SubSchema, Transition, TransitionType, TypedAssigns, XChain, XOutpoint,
+    validation, AltLayer1, AltLayer1Set, AnchoredBundle, AssetTag, Assign, AssignmentType,
+    Assignments, BlindingFactor, BundleId, ContractId, DataState, ExposedSeal, Extension,
+    ExtensionType, FungibleType, Genesis, GenesisSeal, GlobalState, GraphSeal, Input, Issuer,
+    Layer1, Metadata, OccurrencesMismatch, OpId, OpRef, Operation, Opout, Redeemed,
+    RevealedAttach, RevealedData, RevealedValue, SecretSeal, StateSchema, SubSchema, Transition,
+    TransitionType, TypedAssigns, Valencies, ValencyType, VoidState, WitnessId, XChain, XOutpoint,
 };
-use strict_encoding::{FieldName, SerializeError, StrictSerialize};
-use strict_types::decode;
+use strict_encoding::{
+    FieldName, SerializeError, StrictDecode, StrictEncode, StrictReader, StrictSerialize,
+    StrictWriter,
+};
+use strict_types::{decode, SemId};
 
 use crate::containers::{BuilderSeal, Contract};
 use crate::interface::contract::AttachedState;
@@ -65,6 +75,46 @@ pub enum BuilderError {
     /// transition `{0}` is not known to the schema.
     TransitionNotFound(FieldName),
 
+    /// operation `{0}` doesn't support metadata.
+    MetadataNotSupported(FieldName),
+
+    /// metadata type of operation `{0}` defined by the schema doesn't match
+    /// the type required by the interface.
+    MetadataTypeMismatch(FieldName),
+
+    /// operation doesn't allow global state `{0}`.
+    GlobalNotAllowed(FieldName),
+
+    /// invalid number of global state `{0}` values: {1}.
+    GlobalOccurrences(FieldName, OccurrencesMismatch),
+
+    /// operation doesn't allow inputs of state `{0}`.
+    InputNotAllowed(FieldName),
+
+    /// invalid number of inputs of state `{0}`: {1}.
+    InputOccurrences(FieldName, OccurrencesMismatch),
+
+    /// operation doesn't allow assignments of state `{0}`.
+    AssignmentNotAllowed(FieldName),
+
+    /// invalid number of assignments of state `{0}`: {1}.
+    AssignmentOccurrences(FieldName, OccurrencesMismatch),
+
+    /// operation doesn't allow valency `{0}`.
+    ValencyNotAllowed(FieldName),
+
+    /// state transition doesn't pass schema validation: {0}
+    TransitionInvalid(validation::Failure),
+
+    /// extension `{0}` is not known to the schema.
+    ExtensionNotFound(FieldName),
+
+    /// valency `{0}` is not known to the schema.
+    ValencyNotFound(FieldName),
+
+    /// valency `{0}` can't be redeemed by the extension.
+    ValencyNotRedeemable(FieldName),
+
     /// state `{0}` provided to the builder has invalid name.
     InvalidStateField(FieldName),
 
@@ -140,6 +190,8 @@ pub struct ContractBuilder {
     builder: OperationBuilder<GenesisSeal>,
     testnet: bool,
     alt_layers1: AltLayer1Set,
+    metadata: Metadata,
+    issuer: Issuer,
 }
 
 impl ContractBuilder {
@@ -153,6 +205,8 @@ impl ContractBuilder {
             builder: OperationBuilder::with(iface, schema, iimpl)?,
             testnet,
             alt_layers1: none!(),
+            metadata: none!(),
+            issuer: none!(),
         })
     }
 
@@ -165,6 +219,8 @@ impl ContractBuilder {
             builder: OperationBuilder::with(iface, schema, iimpl)?,
             testnet: false,
             alt_layers1: none!(),
+            metadata: none!(),
+            issuer: none!(),
         })
     }
 
@@ -177,6 +233,8 @@ impl ContractBuilder {
             builder: OperationBuilder::with(iface, schema, iimpl)?,
             testnet: true,
             alt_layers1: none!(),
+            metadata: none!(),
+            issuer: none!(),
         })
     }
 
@@ -225,6 +283,47 @@ impl ContractBuilder {
         Ok(self)
     }
 
+    #[inline]
+    pub fn add_global_state_det(
+        mut self,
+        name: impl Into<FieldName>,
+        value: impl StrictSerialize,
+        salt: u128,
+    ) -> Result<Self, BuilderError> {
+        self.builder = self.builder.add_global_state_det(name, value, salt)?;
+        Ok(self)
+    }
+
+    #[inline]
+    pub fn add_valency(mut self, name: impl Into<FieldName>) -> Result<Self, BuilderError> {
+        self.builder = self.builder.add_valency(name)?;
+        Ok(self)
+    }
+
+    /// Adds metadata to the contract genesis, checking it against the type
+    /// defined by the interface and the schema for the genesis metadata.
+    pub fn add_metadata(mut self, value: impl StrictSerialize) -> Result<Self, BuilderError> {
+        let iface_sem_id = self.builder.iface.genesis.metadata;
+        let schema_sem_id = Some(self.builder.schema.genesis.metadata);
+        self.metadata = self
+            .builder
+            .metadata(fname!("genesis"), iface_sem_id, schema_sem_id, value)?;
+        Ok(self)
+    }
+
+    /// Sets contract issuer identity, which gets committed into the genesis.
+    ///
+    /// The identity is not validated by the consensus and must be checked at
+    /// the application level; for instance it may be an [`IdentityKey`].
+    ///
+    /// [`IdentityKey`]: crate::stl::IdentityKey
+    ///
+    /// Errors if the serialized identity exceeds [`U16`] bytes.
+    pub fn set_issuer(mut self, identity: impl StrictSerialize) -> Result<Self, BuilderError> {
+        self.issuer = issuer_from_blob(identity.to_strict_serialized::<U16>()?);
+        Ok(self)
+    }
+
     pub fn add_owned_state_det(
         mut self,
         name: impl Into<FieldName>,
@@ -310,7 +409,8 @@ impl ContractBuilder {
     }
 
     pub fn issue_contract_det(self, timestamp: i64) -> Result<Contract, BuilderError> {
-        let (schema, iface_pair, global, assignments, asset_tags) = self.builder.complete(None);
+        let (schema, iface_pair, global, assignments, valencies, asset_tags) =
+            self.builder.complete(None);
 
         let genesis = Genesis {
             ffv: none!(),
@@ -319,12 +419,11 @@ impl ContractBuilder {
             timestamp,
             testnet: self.testnet,
             alt_layers1: self.alt_layers1,
-            metadata: empty!(),
+            metadata: self.metadata,
             globals: global,
             assignments,
-            valencies: none!(),
-            // TODO: Add APIs for providing issuer information
-            issuer: none!(),
+            valencies,
+            issuer: self.issuer,
             script: none!(),
         };
 
@@ -349,6 +448,7 @@ pub struct TransitionBuilder {
     contract_id: ContractId,
     builder: OperationBuilder<GraphSeal>,
     transition_type: TransitionType,
+    metadata: Metadata,
     inputs: TinyOrdMap<Input, PersistedState>,
 }
 
@@ -401,12 +501,23 @@ impl TransitionBuilder {
             contract_id,
             builder: OperationBuilder::with(iface, schema, iimpl)?,
             transition_type,
+            metadata: none!(),
             inputs: none!(),
         })
     }
 
+    pub fn contract_id(&self) -> ContractId { self.contract_id }
+
     pub fn transition_type(&self) -> TransitionType { self.transition_type }
 
+    /// Returns ids of the operations which state is spent by the transition.
+    pub fn input_ops(&self) -> BTreeSet<OpId> {
+        self.inputs
+            .keys()
+            .map(|input| input.prev_out.op)
+            .collect()
+    }
+
     #[inline]
     pub fn asset_tag(&self, name: impl Into<FieldName>) -> Result<AssetTag, BuilderError> {
         self.builder.asset_tag(name)
@@ -442,6 +553,37 @@ impl TransitionBuilder {
         Ok(self)
     }
 
+    /// Adds metadata to the transition, checking it against the type defined
+    /// by the interface and the schema for the transition metadata.
+    pub fn add_metadata(mut self, value: impl StrictSerialize) -> Result<Self, BuilderError> {
+        let name = self
+            .builder
+            .iimpl
+            .transition_name(self.transition_type)
+            .cloned();
+        let iface_sem_id = name
+            .as_ref()
+            .and_then(|name| self.builder.iface.transitions.get(name))
+            .and_then(|t| t.metadata);
+        let schema_sem_id = self
+            .builder
+            .schema
+            .transitions
+            .get(&self.transition_type)
+            .map(|t| t.metadata);
+        let name = name.unwrap_or_else(|| fname!("blank"));
+        self.metadata = self
+            .builder
+            .metadata(name, iface_sem_id, schema_sem_id, value)?;
+        Ok(self)
+    }
+
+    #[inline]
+    pub fn add_valency(mut self, name: impl Into<FieldName>) -> Result<Self, BuilderError> {
+        self.builder = self.builder.add_valency(name)?;
+        Ok(self)
+    }
+
     pub fn add_input(mut self, opout: Opout, state: PersistedState) -> Result<Self, BuilderError> {
         self.inputs.insert(Input::with(opout), state)?;
         Ok(self)
@@ -573,25 +715,253 @@ impl TransitionBuilder {
         Ok(self)
     }
 
+    /// Completes the state transition, checking that its structure matches the
+    /// schema requirements.
     pub fn complete_transition(self) -> Result<Transition, BuilderError> {
-        let (_, _, global, assignments, _) = self.builder.complete(Some(&self.inputs));
+        self.complete_checked().map(|(transition, ..)| transition)
+    }
+
+    /// Completes the state transition, checking its structure and running the
+    /// schema validation script against it.
+    ///
+    /// The script requires the state spent by the transition, so all the
+    /// operations defining the transition inputs must be provided (genesis
+    /// is always taken into account).
+    pub fn complete_transition_validated<'op>(
+        self,
+        genesis: &'op Genesis,
+        prev_ops: impl IntoIterator<Item = OpRef<'op>>,
+    ) -> Result<Transition, BuilderError> {
+        let (transition, schema, asset_tags) = self.complete_checked()?;
+
+        let status = validate_operation(
+            &schema,
+            asset_tags.into_inner(),
+            genesis,
+            prev_ops,
+            OpRef::Transition(&transition),
+        );
+        if let Some(failure) = status.failures.into_iter().next() {
+            return Err(BuilderError::TransitionInvalid(failure));
+        }
+
+        Ok(transition)
+    }
+
+    fn complete_checked(
+        self,
+    ) -> Result<(Transition, SubSchema, TinyOrdMap<AssignmentType, AssetTag>), BuilderError> {
+        let (schema, iface_pair, global, assignments, valencies, asset_tags) =
+            self.builder.complete(Some(&self.inputs));
 
         let transition = Transition {
             ffv: none!(),
             contract_id: self.contract_id,
             transition_type: self.transition_type,
-            metadata: empty!(),
+            metadata: self.metadata,
             globals: global,
             inputs: SmallOrdSet::from_iter_unsafe(self.inputs.into_keys()).into(),
             assignments,
-            valencies: none!(),
+            valencies,
             witness: none!(),
             script: none!(),
         };
 
-        // TODO: Validate against schema
+        check_transition(&schema, &iface_pair.iimpl, &transition)?;
 
-        Ok(transition)
+        Ok((transition, schema, asset_tags))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ExtensionBuilder {
+    contract_id: ContractId,
+    builder: OperationBuilder<GenesisSeal>,
+    extension_type: ExtensionType,
+    metadata: Metadata,
+    redeemed: TinyOrdMap<ValencyType, OpId>,
+}
+
+impl ExtensionBuilder {
+    pub fn named_extension(
+        contract_id: ContractId,
+        iface: Iface,
+        schema: SubSchema,
+        iimpl: IfaceImpl,
+        extension_name: impl Into<FieldName>,
+    ) -> Result<Self, BuilderError> {
+        let extension_name = extension_name.into();
+        let extension_type = iimpl
+            .extension_type(&extension_name)
+            .ok_or(BuilderError::ExtensionNotFound(extension_name))?;
+        Ok(Self {
+            contract_id,
+            builder: OperationBuilder::with(iface, schema, iimpl)?,
+            extension_type,
+            metadata: none!(),
+            redeemed: none!(),
+        })
+    }
+
+    pub fn extension_type(&self) -> ExtensionType { self.extension_type }
+
+    #[inline]
+    pub fn asset_tag(&self, name: impl Into<FieldName>) -> Result<AssetTag, BuilderError> {
+        self.builder.asset_tag(name)
+    }
+
+    #[inline]
+    pub fn add_asset_tag(
+        mut self,
+        name: impl Into<FieldName>,
+        asset_tag: AssetTag,
+    ) -> Result<Self, BuilderError> {
+        self.builder = self.builder.add_asset_tag(name, asset_tag)?;
+        Ok(self)
+    }
+
+    #[inline]
+    pub fn add_asset_tag_raw(
+        mut self,
+        type_id: AssignmentType,
+        asset_tag: AssetTag,
+    ) -> Result<Self, BuilderError> {
+        self.builder = self.builder.add_asset_tag_raw(type_id, asset_tag)?;
+        Ok(self)
+    }
+
+    #[inline]
+    pub fn add_global_state(
+        mut self,
+        name: impl Into<FieldName>,
+        value: impl StrictSerialize,
+    ) -> Result<Self, BuilderError> {
+        self.builder = self.builder.add_global_state(name, value)?;
+        Ok(self)
+    }
+
+    /// Adds metadata to the extension, checking it against the type defined
+    /// by the interface and the schema for the extension metadata.
+    pub fn add_metadata(mut self, value: impl StrictSerialize) -> Result<Self, BuilderError> {
+        let name = self
+            .builder
+            .iimpl
+            .extension_name(self.extension_type)
+            .expect("reverse type")
+            .clone();
+        let iface_sem_id = self
+            .builder
+            .iface
+            .extensions
+            .get(&name)
+            .and_then(|e| e.metadata);
+        let schema_sem_id = self
+            .builder
+            .schema
+            .extensions
+            .get(&self.extension_type)
+            .map(|e| e.metadata);
+        self.metadata = self
+            .builder
+            .metadata(name, iface_sem_id, schema_sem_id, value)?;
+        Ok(self)
+    }
+
+    #[inline]
+    pub fn add_valency(mut self, name: impl Into<FieldName>) -> Result<Self, BuilderError> {
+        self.builder = self.builder.add_valency(name)?;
+        Ok(self)
+    }
+
+    /// Redeems valency with the given name, defined by the operation `opid`.
+    pub fn redeem_valency(
+        mut self,
+        name: impl Into<FieldName>,
+        opid: OpId,
+    ) -> Result<Self, BuilderError> {
+        let name = name.into();
+        let type_id = self
+            .builder
+            .iimpl
+            .valency_type(&name)
+            .ok_or(BuilderError::ValencyNotFound(name.clone()))?;
+        let redeems = &self
+            .builder
+            .schema
+            .extensions
+            .get(&self.extension_type)
+            .expect("schema should match interface: must be checked by the constructor")
+            .redeems;
+        if !redeems.contains(&type_id) {
+            return Err(BuilderError::ValencyNotRedeemable(name));
+        }
+        self.redeemed.insert(type_id, opid)?;
+        Ok(self)
+    }
+
+    pub fn add_rights(
+        mut self,
+        name: impl Into<FieldName>,
+        seal: impl Into<BuilderSeal<GenesisSeal>>,
+    ) -> Result<Self, BuilderError> {
+        self.builder = self.builder.add_rights(name, seal)?;
+        Ok(self)
+    }
+
+    pub fn add_fungible_state(
+        mut self,
+        name: impl Into<FieldName>,
+        seal: impl Into<BuilderSeal<GenesisSeal>>,
+        value: u64,
+    ) -> Result<Self, BuilderError> {
+        let name = name.into();
+        let type_id = self
+            .builder
+            .assignments_type(&name)
+            .ok_or(BuilderError::AssignmentNotFound(name.clone()))?;
+        let tag = self.builder.asset_tag_raw(type_id)?;
+
+        self.builder = self.builder.add_fungible_state(name, seal, value, tag)?;
+        Ok(self)
+    }
+
+    pub fn add_data(
+        mut self,
+        name: impl Into<FieldName>,
+        seal: impl Into<BuilderSeal<GenesisSeal>>,
+        value: impl StrictSerialize,
+    ) -> Result<Self, BuilderError> {
+        self.builder = self.builder.add_data(name, seal, value)?;
+        Ok(self)
+    }
+
+    pub fn add_attachment(
+        mut self,
+        name: impl Into<FieldName>,
+        seal: impl Into<BuilderSeal<GenesisSeal>>,
+        attachment: AttachedState,
+    ) -> Result<Self, BuilderError> {
+        self.builder = self.builder.add_attachment(name, seal, attachment)?;
+        Ok(self)
+    }
+
+    pub fn complete_extension(self) -> Result<Extension, BuilderError> {
+        let (_, _, global, assignments, valencies, _) = self.builder.complete(None);
+
+        let extension = Extension {
+            ffv: none!(),
+            contract_id: self.contract_id,
+            extension_type: self.extension_type,
+            metadata: self.metadata,
+            globals: global,
+            assignments,
+            redeemed: Redeemed::from(self.redeemed),
+            valencies,
+            witness: none!(),
+            script: none!(),
+        };
+
+        Ok(extension)
     }
 }
 
@@ -610,7 +980,7 @@ pub struct OperationBuilder<Seal: ExposedSeal> {
     data: TinyOrdMap<AssignmentType, Confined<BTreeMap<BuilderSeal<Seal>, RevealedData>, 1, U16>>,
     attachments:
         TinyOrdMap<AssignmentType, Confined<BTreeMap<BuilderSeal<Seal>, RevealedAttach>, 1, U16>>,
-    // TODO: add valencies
+    valencies: TinyOrdSet<ValencyType>,
 }
 
 impl<Seal: ExposedSeal> From<IssuerTriplet> for OperationBuilder<Seal> {
@@ -628,6 +998,7 @@ impl<Seal: ExposedSeal> From<IssuerTriplet> for OperationBuilder<Seal> {
             fungible: none!(),
             attachments: none!(),
             data: none!(),
+            valencies: none!(),
         }
     }
 }
@@ -711,9 +1082,18 @@ impl<Seal: ExposedSeal> OperationBuilder<Seal> {
     }
 
     pub fn add_global_state(
+        self,
+        name: impl Into<FieldName>,
+        value: impl StrictSerialize,
+    ) -> Result<Self, BuilderError> {
+        self.add_global_state_det(name, value, random())
+    }
+
+    pub fn add_global_state_det(
         mut self,
         name: impl Into<FieldName>,
         value: impl StrictSerialize,
+        salt: u128,
     ) -> Result<Self, BuilderError> {
         let name = name.into();
         let serialized = value.to_strict_serialized::<{ u16::MAX as usize }>()?;
@@ -739,11 +1119,41 @@ impl<Seal: ExposedSeal> OperationBuilder<Seal> {
             .strict_deserialize_type(sem_id, &serialized)?;
 
         self.global
-            .add_state(type_id, RevealedData::new_random_salt(serialized))?;
+            .add_state(type_id, RevealedData::with_salt(serialized, salt))?;
 
         Ok(self)
     }
 
+    fn metadata(
+        &self,
+        name: FieldName,
+        iface_sem_id: Option<SemId>,
+        schema_sem_id: Option<SemId>,
+        value: impl StrictSerialize,
+    ) -> Result<Metadata, BuilderError> {
+        let (Some(iface_sem_id), Some(sem_id)) = (iface_sem_id, schema_sem_id) else {
+            return Err(BuilderError::MetadataNotSupported(name));
+        };
+        if iface_sem_id != sem_id {
+            return Err(BuilderError::MetadataTypeMismatch(name));
+        }
+        let serialized = value.to_strict_serialized::<U16>()?;
+        self.schema
+            .types
+            .strict_deserialize_type(sem_id, &serialized)?;
+        Ok(Metadata::from(serialized))
+    }
+
+    fn add_valency(mut self, name: impl Into<FieldName>) -> Result<Self, BuilderError> {
+        let name = name.into();
+        let type_id = self
+            .iimpl
+            .valency_type(&name)
+            .ok_or(BuilderError::ValencyNotFound(name))?;
+        self.valencies.push(type_id)?;
+        Ok(self)
+    }
+
     fn add_owned_state_det(
         self,
         name: impl Into<FieldName>,
@@ -793,7 +1203,7 @@ impl<Seal: ExposedSeal> OperationBuilder<Seal> {
         seal: impl Into<BuilderSeal<Seal>>,
     ) -> Result<Self, BuilderError> {
         let state_schema = self.state_schema(type_id);
-        if *state_schema != StateSchema::Fungible(FungibleType::Unsigned64Bit) {
+        if *state_schema != StateSchema::Declarative {
             return Err(BuilderError::InvalidState(type_id));
         }
 
@@ -913,7 +1323,7 @@ impl<Seal: ExposedSeal> OperationBuilder<Seal> {
         state: RevealedAttach,
     ) -> Result<Self, BuilderError> {
         let state_schema = self.state_schema(type_id);
-        if let StateSchema::Structured(_) = *state_schema {
+        if let StateSchema::Attachment(_) = *state_schema {
             let seal = seal.into();
             match self.attachments.get_mut(&type_id) {
                 Some(assignments) => {
@@ -949,11 +1359,23 @@ impl<Seal: ExposedSeal> OperationBuilder<Seal> {
         )
     }
 
+    // NB: All assignments are created without locks: in the current consensus
+    //     version lock is a reserved field (`ReservedBytes<2, 0>`) which must
+    //     be zero, and validators reject any other value. Lock support in the
+    //     builders, `PersistedState` and compose must wait for the consensus
+    //     layer to define lock semantics.
+    #[allow(clippy::type_complexity)]
     fn complete(
         self,
         inputs: Option<&TinyOrdMap<Input, PersistedState>>,
-    ) -> (SubSchema, IfacePair, GlobalState, Assignments<Seal>, TinyOrdMap<AssignmentType, AssetTag>)
-    {
+    ) -> (
+        SubSchema,
+        IfacePair,
+        GlobalState,
+        Assignments<Seal>,
+        Valencies,
+        TinyOrdMap<AssignmentType, AssetTag>,
+    ) {
         let owned_state = self.fungible.into_iter().map(|(id, vec)| {
             let mut blindings = Vec::with_capacity(vec.len());
             let mut vec = vec
@@ -1019,16 +1441,421 @@ impl<Seal: ExposedSeal> OperationBuilder<Seal> {
             (id, state_data)
         });
 
+        let owned_rights = self.rights.into_iter().map(|(id, set)| {
+            let vec_rights = set.into_iter().map(|seal| match seal {
+                BuilderSeal::Revealed(seal) => Assign::Revealed {
+                    seal,
+                    state: VoidState::default(),
+                    lock: none!(),
+                },
+                BuilderSeal::Concealed(seal) => Assign::ConfidentialSeal {
+                    seal,
+                    state: VoidState::default(),
+                    lock: none!(),
+                },
+            });
+            let state_rights = Confined::try_from_iter(vec_rights).expect("at least one element");
+            let state_rights = TypedAssigns::Declarative(state_rights);
+            (id, state_rights)
+        });
+        let owned_attachments = self.attachments.into_iter().map(|(id, vec)| {
+            let vec_attach = vec.into_iter().map(|(seal, value)| match seal {
+                BuilderSeal::Revealed(seal) => Assign::Revealed {
+                    seal,
+                    state: value,
+                    lock: none!(),
+                },
+                BuilderSeal::Concealed(seal) => Assign::ConfidentialSeal {
+                    seal,
+                    state: value,
+                    lock: none!(),
+                },
+            });
+            let state_attach = Confined::try_from_iter(vec_attach).expect("at least one element");
+            let state_attach = TypedAssigns::Attachment(state_attach);
+            (id, state_attach)
+        });
+
         let owned_state = Confined::try_from_iter(owned_state).expect("same size");
         let owned_data = Confined::try_from_iter(owned_data).expect("same size");
+        let owned_rights = Confined::try_from_iter(owned_rights).expect("same size");
+        let owned_attachments = Confined::try_from_iter(owned_attachments).expect("same size");
 
         let mut assignments = Assignments::from_inner(owned_state);
         assignments
             .extend(Assignments::from_inner(owned_data).into_inner())
             .expect("");
+        assignments
+            .extend(Assignments::from_inner(owned_rights).into_inner())
+            .expect("");
+        assignments
+            .extend(Assignments::from_inner(owned_attachments).into_inner())
+            .expect("");
 
         let iface_pair = IfacePair::with(self.iface, self.iimpl);
 
-        (self.schema, iface_pair, self.global, assignments, self.asset_tags)
+        (
+            self.schema,
+            iface_pair,
+            self.global,
+            assignments,
+            Valencies::from(self.valencies),
+            self.asset_tags,
+        )
+    }
+}
+
+fn field_name(name: Option<&FieldName>, id: impl Display) -> FieldName {
+    name.cloned().unwrap_or_else(|| {
+        FieldName::try_from(format!("type{id}")).expect("type ids are valid field names")
+    })
+}
+
+/// Checks the state transition against occurrence rules defined by the schema.
+fn check_transition(
+    schema: &SubSchema,
+    iimpl: &IfaceImpl,
+    transition: &Transition,
+) -> Result<(), BuilderError> {
+    let blank_schema = schema.blank_transition();
+    let transition_schema = match schema.transitions.get(&transition.transition_type) {
+        Some(transition_schema) => transition_schema,
+        None if transition.transition_type == TransitionType::BLANK => &blank_schema,
+        None => {
+            let name = field_name(
+                iimpl.transition_name(transition.transition_type),
+                transition.transition_type,
+            );
+            return Err(BuilderError::TransitionNotFound(name));
+        }
+    };
+
+    for type_id in transition.globals.keys() {
+        if !transition_schema.globals.contains_key(type_id) {
+            let name = field_name(iimpl.global_name(*type_id), type_id);
+            return Err(BuilderError::GlobalNotAllowed(name));
+        }
+    }
+    for (type_id, occ) in &transition_schema.globals {
+        let count = transition
+            .globals
+            .get(type_id)
+            .map(|values| values.len() as u16)
+            .unwrap_or_default();
+        occ.check(count).map_err(|mismatch| {
+            let name = field_name(iimpl.global_name(*type_id), type_id);
+            BuilderError::GlobalOccurrences(name, mismatch)
+        })?;
+    }
+
+    let mut inputs = BTreeMap::<AssignmentType, u16>::new();
+    for input in &transition.inputs {
+        *inputs.entry(input.prev_out.ty).or_default() += 1;
+    }
+    for type_id in inputs.keys() {
+        if !transition_schema.inputs.contains_key(type_id) {
+            let name = field_name(iimpl.assignment_name(*type_id), type_id);
+            return Err(BuilderError::InputNotAllowed(name));
+        }
+    }
+    for (type_id, occ) in &transition_schema.inputs {
+        let count = inputs.get(type_id).copied().unwrap_or_default();
+        occ.check(count).map_err(|mismatch| {
+            let name = field_name(iimpl.assignment_name(*type_id), type_id);
+            BuilderError::InputOccurrences(name, mismatch)
+        })?;
+    }
+
+    for type_id in transition.assignments.keys() {
+        if !transition_schema.assignments.contains_key(type_id) {
+            let name = field_name(iimpl.assignment_name(*type_id), type_id);
+            return Err(BuilderError::AssignmentNotAllowed(name));
+        }
+    }
+    for (type_id, occ) in &transition_schema.assignments {
+        let count = transition
+            .assignments
+            .get(type_id)
+            .map(TypedAssigns::len_u16)
+            .unwrap_or_default();
+        occ.check(count).map_err(|mismatch| {
+            let name = field_name(iimpl.assignment_name(*type_id), type_id);
+            BuilderError::AssignmentOccurrences(name, mismatch)
+        })?;
+    }
+
+    for type_id in &transition.valencies {
+        if !transition_schema.valencies.contains(&type_id) {
+            let name = field_name(iimpl.valency_name(type_id), type_id);
+            return Err(BuilderError::ValencyNotAllowed(name));
+        }
+    }
+
+    Ok(())
+}
+
+/// Constructs contract issuer from its serialized identity.
+fn issuer_from_blob(blob: SmallBlob) -> Issuer {
+    // `Issuer` has no public constructor. Being a transparent wrapper around
+    // the blob, it shares the strict encoding with it, so we decode it from
+    // the strict encoding of the blob.
+    const LEN: usize = U16 + 2;
+    let data = blob
+        .strict_encode(StrictWriter::in_memory::<LEN>())
+        .expect("blob exceeds its strict encoding size")
+        .unbox()
+        .unconfine();
+    let mut reader = StrictReader::in_memory::<LEN>(data);
+    Issuer::strict_decode(&mut reader).expect("issuer encoding differs from the blob encoding")
+}
+
+/// Runs schema validation script against a single operation.
+///
+/// All the operations which state is spent or redeemed by the validated
+/// operation must be provided (genesis is always taken into account).
+pub(crate) fn validate_operation<'op>(
+    schema: &SubSchema,
+    asset_tags: BTreeMap<AssignmentType, AssetTag>,
+    genesis: &'op Genesis,
+    prev_ops: impl IntoIterator<Item = OpRef<'op>>,
+    op: OpRef,
+) -> validation::Status {
+    let mut operations = prev_ops
+        .into_iter()
+        .map(|op| (op.id(), op))
+        .collect::<BTreeMap<_, _>>();
+    operations.insert(genesis.id(), OpRef::Genesis(genesis));
+    let context = ValidationContext {
+        schema,
+        asset_tags,
+        genesis,
+        operations,
+    };
+    schema.validate_state(&CheckedConsignment::new(&context), op, &schema.script)
+}
+
+/// Minimal consignment API used to run schema validation script against a
+/// newly constructed operation.
+struct ValidationContext<'op> {
+    schema: &'op SubSchema,
+    asset_tags: BTreeMap<AssignmentType, AssetTag>,
+    genesis: &'op Genesis,
+    operations: BTreeMap<OpId, OpRef<'op>>,
+}
+
+impl<'op> ConsignmentApi for ValidationContext<'op> {
+    type Iter<'a> = iter::Empty<BundleId>;
+
+    fn schema(&self) -> &SubSchema { self.schema }
+
+    fn asset_tags(&self) -> &BTreeMap<AssignmentType, AssetTag> { &self.asset_tags }
+
+    fn operation(&self, opid: OpId) -> Option<OpRef<'_>> { self.operations.get(&opid).copied() }
+
+    fn genesis(&self) -> &Genesis { self.genesis }
+
+    fn terminals(&self) -> BTreeSet<(BundleId, XChain<SecretSeal>)> { none!() }
+
+    fn bundle_ids<'a>(&self) -> Self::Iter<'a> { iter::empty() }
+
+    fn anchored_bundle(&self, _bundle_id: BundleId) -> Option<Rc<AnchoredBundle>> { None }
+
+    fn op_witness_id(&self, _opid: OpId) -> Option<WitnessId> { None }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::TinyBlob;
+    use strict_encoding::StrictDeserialize;
+
+    use bp::dbc::Method;
+    use bp::{Txid, Vout};
+    use rgb::OutputSeal;
+
+    use super::*;
+    use crate::fixtures::{
+        consume, genesis_seal, output, rgb20_builder, rgb20_transfer, stock_with, TestRgb20,
+    };
+    use crate::interface::rgb21::{EmbeddedMedia, EngravingData, TokenIndex};
+    use crate::interface::{IfaceClass, IssuerClass, Rgb20};
+    use crate::persistence::{Inventory, Stash, Stock};
+    use crate::stl::{IdentityKey, IssueMeta, KeyAlgo, MediaType, StandardTypes};
+
+    fn seal(vout: u32) -> BuilderSeal<GraphSeal> {
+        let seal = GraphSeal::with_blinded_vout(Method::TapretFirst, Vout::from_u32(vout), 0);
+        BuilderSeal::Revealed(XChain::Bitcoin(seal))
+    }
+
+    /// Stock with RGB20 contract having 100 units allocated to [`output`] 1
+    /// and a burn right assigned to [`output`] 2.
+    fn rgb20_stock() -> (Stock, ContractId) {
+        let contract = rgb20_builder()
+            .add_fungible_state("assetOwner", genesis_seal(1), 100)
+            .unwrap()
+            .add_rights("burnRight", genesis_seal(2))
+            .unwrap()
+            .add_global_state("issuedSupply", Amount::from(100u64))
+            .unwrap()
+            .issue_contract_det(1_700_000_000)
+            .unwrap();
+        let contract_id = contract.contract_id();
+        (stock_with([contract]), contract_id)
+    }
+
+    /// Transfer transition builder spending all the state at the given
+    /// outputs.
+    fn transfer(stock: &Stock, contract_id: ContractId, from: &[u8]) -> TransitionBuilder {
+        let mut builder = stock
+            .transition_builder(contract_id, "RGB20", None::<&str>)
+            .unwrap();
+        let outputs = from.iter().copied().map(output);
+        for ((opout, _), state) in stock.state_for_outpoints(contract_id, outputs).unwrap() {
+            builder = builder.add_input(opout, state).unwrap();
+        }
+        builder
+    }
+
+    fn engraving(len: usize) -> EngravingData {
+        EngravingData {
+            applied_to: TokenIndex::from(1),
+            content: EmbeddedMedia {
+                ty: MediaType::with("text/plain"),
+                data: SmallBlob::try_from(vec![0xAB; len]).unwrap(),
+            },
+        }
+    }
+
+    fn issuer_data(builder: ContractBuilder) -> Vec<u8> {
+        let contract = builder
+            .add_global_state("issuedSupply", Amount::ZERO)
+            .unwrap()
+            .issue_contract_det(1_700_000_000)
+            .unwrap();
+        contract
+            .genesis
+            .issuer
+            .strict_encode(StrictWriter::in_memory::<{ U16 + 2 }>())
+            .unwrap()
+            .unbox()
+            .unconfine()
+    }
+
+    #[test]
+    fn set_issuer() {
+        let key = IdentityKey::new(KeyAlgo::Ed25519, TinyBlob::try_from(vec![7; 32]).unwrap());
+        let data = issuer_data(rgb20_builder().set_issuer(key.clone()).unwrap());
+        let identity = key.to_strict_serialized::<U16>().unwrap();
+        assert_eq!(data[..2], (identity.len() as u16).to_le_bytes());
+        assert_eq!(data[2..], identity[..]);
+        assert_eq!(IdentityKey::from_strict_serialized::<U16>(identity).unwrap(), key);
+    }
+
+    #[test]
+    fn set_issuer_limit() {
+        let overhead = engraving(0).to_strict_serialized::<U16>().unwrap().len();
+        let largest = engraving(U16 - overhead);
+        let data = issuer_data(rgb20_builder().set_issuer(largest).unwrap());
+        assert_eq!(data.len(), U16 + 2);
+        assert_eq!(data[..2], u16::MAX.to_le_bytes());
+
+        let err = rgb20_builder()
+            .set_issuer(engraving(U16 - overhead + 1))
+            .unwrap_err();
+        assert!(matches!(err, BuilderError::StrictEncode(_)));
+    }
+
+    #[test]
+    fn metadata_type_mismatch() {
+        let types = StandardTypes::new();
+        let builder = |meta_type: &'static str| {
+            let mut schema = TestRgb20::schema();
+            schema.genesis.metadata = types.get(meta_type);
+            let mut iimpl = TestRgb20::issue_impl();
+            iimpl.schema_id = schema.schema_id();
+            ContractBuilder::testnet(Rgb20::iface(), schema, iimpl).unwrap()
+        };
+        let meta = IssueMeta {
+            reserves: none!(),
+        };
+
+        // Burn metadata has the same layout, but it is not the type required
+        // by the interface
+        let err = builder("RGBContract.BurnMeta")
+            .add_metadata(meta.clone())
+            .unwrap_err();
+        assert_eq!(err, BuilderError::MetadataTypeMismatch(fname!("genesis")));
+        builder("RGBContract.IssueMeta").add_metadata(meta).unwrap();
+    }
+
+    #[test]
+    fn transition_rejected_inputs() {
+        let (stock, contract_id) = rgb20_stock();
+        let genesis = stock.genesis(contract_id).unwrap();
+
+        let err = transfer(&stock, contract_id, &[1, 2])
+            .add_fungible_state("assetOwner", seal(0), 100)
+            .unwrap()
+            .complete_transition_validated(genesis, [])
+            .unwrap_err();
+        assert_eq!(err, BuilderError::InputNotAllowed(fname!("burnRight")));
+    }
+
+    #[test]
+    fn transition_rejected_occurrences() {
+        let (stock, contract_id) = rgb20_stock();
+        let genesis = stock.genesis(contract_id).unwrap();
+
+        let err = transfer(&stock, contract_id, &[1])
+            .complete_transition_validated(genesis, [])
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            BuilderError::AssignmentOccurrences(name, _) if name == fname!("assetOwner")
+        ));
+
+        let err = transfer(&stock, contract_id, &[])
+            .add_fungible_state("assetOwner", seal(0), 100)
+            .unwrap()
+            .complete_transition_validated(genesis, [])
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            BuilderError::InputOccurrences(name, _) if name == fname!("assetOwner")
+        ));
+    }
+
+    #[test]
+    fn transition_validated() {
+        let (mut stock, contract_id) = rgb20_stock();
+        let transition = rgb20_transfer(&stock, contract_id, 1, [(0, 100)]);
+        consume(&mut stock, 3, [transition.clone()]);
+        let genesis = stock.genesis(contract_id).unwrap();
+
+        let spend = || {
+            let outpoint = XChain::Bitcoin(OutputSeal::new(
+                Method::TapretFirst,
+                Outpoint::new(Txid::from([3; 32]), 0),
+            ));
+            let mut builder = stock
+                .transition_builder(contract_id, "RGB20", None::<&str>)
+                .unwrap();
+            for ((opout, _), state) in stock.state_for_outpoints(contract_id, [outpoint]).unwrap()
+            {
+                builder = builder.add_input(opout, state).unwrap();
+            }
+            builder
+                .add_fungible_state("assetOwner", seal(1), 100)
+                .unwrap()
+        };
+
+        // the spent transition is required to run the validation
+        let err = spend()
+            .complete_transition_validated(genesis, [])
+            .unwrap_err();
+        assert!(matches!(err, BuilderError::TransitionInvalid(_)));
+
+        spend()
+            .complete_transition_validated(genesis, [OpRef::Transition(&transition)])
+            .unwrap();
     }
 }
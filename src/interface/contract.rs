@@ -19,20 +19,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use amplify::confinement::{SmallOrdSet, SmallVec};
-use invoice::{Allocation, Amount};
+use amplify::confinement::{SmallOrdMap, SmallOrdSet, SmallVec, U16};
+use amplify::Wrapper;
+use invoice::{Allocation, Amount, Precision};
 use rgb::{
     AssignmentWitness, AttachId, ContractId, ContractState, DataState, KnownState, MediaType, OpId,
-    OutputAssignment, RevealedAttach, RevealedData, RevealedValue, VoidState, WitnessId, XOutpoint,
-    XOutputSeal,
+    Opout, OutputAssignment, RevealedAttach, RevealedData, RevealedValue, TransitionType,
+    VoidState, WitnessAnchor, WitnessId, WitnessOrd, WitnessPos, XOutpoint, XOutputSeal,
+};
+use strict_encoding::{
+    DeserializeError, FieldName, StrictDecode, StrictDeserialize, StrictDumb, StrictEncode,
+    StrictReader, StrictWriter,
 };
-use strict_encoding::{FieldName, StrictDecode, StrictDumb, StrictEncode};
 use strict_types::typify::TypedVal;
 use strict_types::{decode, StrictVal};
 
-use crate::interface::{IfaceId, IfaceImpl, OutpointFilter, WitnessFilter};
+use crate::interface::{
+    FilterIncludeAll, IfaceId, IfaceImpl, Ledger, LedgerError, OutpointFilter, WitnessFilter,
+};
 use crate::LIB_NAME_RGB_STD;
 
 #[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
@@ -41,9 +47,29 @@ pub enum ContractError {
     /// field name {0} is unknown to the contract interface
     FieldNameUnknown(FieldName),
 
+    /// contract doesn't have a value for the global state {0} required by
+    /// the interface.
+    GlobalStateAbsent(FieldName),
+
     #[from]
     #[display(inner)]
     Reify(decode::Error),
+
+    /// contract state data can't be deserialized into the requested type.
+    /// Details: {0}
+    #[from]
+    Deserialize(DeserializeError),
+
+    /// contract history is inconsistent: the issued supply {0} is less than
+    /// the burned supply {1}.
+    SupplyMismatch(Amount, Amount),
+
+    /// sum of `{0}` state amounts exceeds the maximal amount value.
+    AmountOverflow(FieldName),
+
+    #[from]
+    #[display(inner)]
+    Ledger(LedgerError),
 }
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display, From)]
@@ -120,6 +146,28 @@ pub trait StateChange: Clone + Eq + StrictDumb + StrictEncode + StrictDecode {
     fn merge_received(&mut self, state: Self::State);
 }
 
+/// Marks whether a seal participating in an operation belongs to the local
+/// wallet (i.e. matches the outpoint filter used to query the history).
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD, tags = repr, into_u8, try_from_u8)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display(lowercase)]
+#[repr(u8)]
+pub enum Ownership {
+    #[strict_type(dumb)]
+    Local = 0,
+    External = 1,
+}
+
+impl Ownership {
+    pub fn is_local(self) -> bool { self == Ownership::Local }
+}
+
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
 #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
 #[strict_type(lib = LIB_NAME_RGB_STD)]
@@ -132,18 +180,48 @@ pub struct IfaceOp<S: StateChange> {
     pub opids: SmallOrdSet<OpId>,  // may come from multiple bundles
     pub inputs: SmallOrdSet<OpId>, // may come from multiple bundles
     pub state_change: S,
-    pub payers: SmallOrdSet<XOutputSeal>,
-    pub beneficiaries: SmallOrdSet<XOutputSeal>,
+    /// Names of the state transitions performed in the witness, as defined by
+    /// the interface implementation. Blank transitions are always named
+    /// `blank`; transitions unknown to the interface are omitted.
+    pub transitions: SmallOrdSet<FieldName>,
+    pub witness_ord: WitnessOrd,
+    /// Seals of the known state spent by the operation.
+    pub payers: SmallOrdMap<XOutputSeal, Ownership>,
+    /// Seals of the known state created by the operation.
+    pub beneficiaries: SmallOrdMap<XOutputSeal, Ownership>,
 }
 
 impl<C: StateChange> IfaceOp<C> {
+    /// Detects whether the operation consists only of blank state
+    /// transitions, i.e. it just moves the state without changing its owner.
+    pub fn is_blank(&self) -> bool {
+        !self.transitions.is_empty() && self.transitions.iter().all(|name| name == &blank_name())
+    }
+
+    /// Height of the block mining the witness transaction, if it is mined.
+    pub fn height(&self) -> Option<u32> {
+        match self.witness_ord {
+            WitnessOrd::OnChain(pos) => Some(pos.height().get()),
+            WitnessOrd::OffChain => None,
+        }
+    }
+
+    /// Timestamp of the block mining the witness transaction, if it is mined.
+    pub fn timestamp(&self) -> Option<i64> {
+        match self.witness_ord {
+            WitnessOrd::OnChain(pos) => Some(witness_timestamp(pos)),
+            WitnessOrd::OffChain => None,
+        }
+    }
+
     fn from_spent(alloc: OutputAssignment<C::State>) -> Self {
         Self {
             opids: none!(),
             inputs: confined_bset![alloc.opout.op],
             state_change: C::from_spent(alloc.state),
-            payers: none!(),
-            // TODO: Do something with beneficiary info
+            transitions: none!(),
+            witness_ord: WitnessOrd::OffChain,
+            payers: confined_bmap![alloc.seal => Ownership::Local],
             beneficiaries: none!(),
         }
     }
@@ -152,24 +230,104 @@ impl<C: StateChange> IfaceOp<C> {
             opids: confined_bset![alloc.opout.op],
             inputs: none!(),
             state_change: C::from_received(alloc.state),
-            // TODO: Do something with payer info
+            transitions: none!(),
+            witness_ord: WitnessOrd::OffChain,
             payers: none!(),
-            beneficiaries: none!(),
+            beneficiaries: confined_bmap![alloc.seal => Ownership::Local],
         }
     }
     fn merge_spent(&mut self, alloc: OutputAssignment<C::State>) {
+        self.add_payer(&alloc, Ownership::Local);
+        self.state_change.merge_spent(alloc.state);
+    }
+    fn merge_received(&mut self, alloc: OutputAssignment<C::State>) {
+        self.add_beneficiary(&alloc, Ownership::Local);
+        self.state_change.merge_received(alloc.state);
+    }
+    fn add_payer(&mut self, alloc: &OutputAssignment<C::State>, ownership: Ownership) {
         self.inputs
             .push(alloc.opout.op)
             .expect("internal inconsistency of stash data");
-        self.state_change.merge_spent(alloc.state);
-        // TODO: Do something with beneficiary info
+        self.payers
+            .insert(alloc.seal, ownership)
+            .expect("internal inconsistency of stash data");
     }
-    fn merge_received(&mut self, alloc: OutputAssignment<C::State>) {
+    fn add_beneficiary(&mut self, alloc: &OutputAssignment<C::State>, ownership: Ownership) {
         self.opids
             .push(alloc.opout.op)
             .expect("internal inconsistency of stash data");
-        self.state_change.merge_received(alloc.state);
-        // TODO: Do something with payer info
+        self.beneficiaries
+            .insert(alloc.seal, ownership)
+            .expect("internal inconsistency of stash data");
+    }
+}
+
+fn blank_name() -> FieldName { fname!("blank") }
+
+/// Extracts block timestamp from the witness position.
+fn witness_timestamp(pos: WitnessPos) -> i64 {
+    // `WitnessPos` doesn't expose the timestamp, so we read it back from the
+    // strict encoding of the position, which is the height followed by the
+    // timestamp.
+    const LEN: usize = 12;
+    let data = pos
+        .strict_encode(StrictWriter::in_memory::<LEN>())
+        .expect("witness position exceeds its strict encoding size")
+        .unbox()
+        .unconfine();
+    let mut reader = StrictReader::in_memory::<LEN>(data);
+    u32::strict_decode(&mut reader)
+        .and_then(|_| i64::strict_decode(&mut reader))
+        .expect("invalid witness position strict encoding")
+}
+
+/// Information about a known state transition which is not part of the
+/// [`ContractState`], but is required to construct the operation history.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct KnownTransition {
+    pub witness_id: WitnessId,
+    pub witness_ord: WitnessOrd,
+    pub transition_type: TransitionType,
+    pub inputs: SmallOrdSet<Opout>,
+}
+
+/// Point in the contract history as of which the contract state is
+/// reconstructed (see [`crate::persistence::Inventory::contract_iface_at`]).
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum StatePoint {
+    /// State including all operations ordered at or before the witness
+    /// anchor.
+    #[display(inner)]
+    Anchor(WitnessAnchor),
+
+    /// State including all operations mined at or below the block height.
+    #[display("height {0}")]
+    Height(u32),
+
+    /// State including all operations mined in blocks with timestamp not
+    /// exceeding the given one.
+    #[display("timestamp {0}")]
+    Timestamp(i64),
+}
+
+impl StatePoint {
+    /// Detects whether operations witnessed with the anchor are part of the
+    /// state as of this point. Operations with unmined witnesses are part of
+    /// the state only for anchors ordered after them.
+    pub fn includes(self, witness_anchor: WitnessAnchor) -> bool {
+        match (self, witness_anchor.witness_ord) {
+            (StatePoint::Anchor(point), _) => witness_anchor <= point,
+            (StatePoint::Height(height), WitnessOrd::OnChain(pos)) => pos.height().get() <= height,
+            (StatePoint::Timestamp(timestamp), WitnessOrd::OnChain(pos)) => {
+                witness_timestamp(pos) <= timestamp
+            }
+            (_, WitnessOrd::OffChain) => false,
+        }
     }
 }
 
@@ -179,6 +337,7 @@ impl<C: StateChange> IfaceOp<C> {
 pub struct ContractIface {
     pub state: ContractState,
     pub iface: IfaceImpl,
+    pub transitions: BTreeMap<OpId, KnownTransition>,
 }
 
 // TODO: Introduce witness checker: additional filter returning only those data
@@ -216,6 +375,65 @@ impl ContractIface {
         Ok(SmallVec::try_from_iter(state).expect("same or smaller collection size"))
     }
 
+    /// Returns global state values for the interface field `name`
+    /// deserialized into the Rust type `T`.
+    ///
+    /// Unlike [`Self::global`], does not panic if the contract data doesn't
+    /// match the interface definitions, returning an error instead.
+    pub fn global_typed<T: StrictDeserialize>(
+        &self,
+        name: impl Into<FieldName>,
+    ) -> Result<SmallVec<T>, ContractError> {
+        let name = name.into();
+        let type_id = self
+            .iface
+            .global_type(&name)
+            .ok_or(ContractError::FieldNameUnknown(name.clone()))?;
+        let max_items = self
+            .state
+            .schema
+            .global_types
+            .get(&type_id)
+            .map(|type_schema| type_schema.max_items as usize)
+            .ok_or(ContractError::FieldNameUnknown(name))?;
+        let state = unsafe { self.state.global_unchecked(type_id) };
+        let state = state
+            .into_iter()
+            .take(max_items)
+            .map(|revealed| T::from_strict_serialized::<U16>(revealed.value.to_inner()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(SmallVec::try_from_iter(state).expect("same or smaller collection size"))
+    }
+
+    /// Returns the first global state value for the interface field `name`
+    /// deserialized into the Rust type `T`, or an error if the contract has
+    /// no such state.
+    pub fn global_typed_first<T: StrictDeserialize>(
+        &self,
+        name: impl Into<FieldName>,
+    ) -> Result<T, ContractError> {
+        let name = name.into();
+        self.global_typed(name.clone())?
+            .into_iter()
+            .next()
+            .ok_or(ContractError::GlobalStateAbsent(name))
+    }
+
+    /// Returns structured owned state allocations for the interface field
+    /// `name` together with their data deserialized into the Rust type `T`.
+    pub fn data_typed<T: StrictDeserialize>(
+        &self,
+        name: impl Into<FieldName>,
+        filter: impl OutpointFilter,
+    ) -> Result<Vec<(DataAllocation, T)>, ContractError> {
+        self.extract_state(self.state.data(), name, filter)?
+            .map(|alloc: DataAllocation| {
+                let data = T::from_strict_serialized::<U16>(alloc.state.to_inner())?;
+                Ok((alloc, data))
+            })
+            .collect()
+    }
+
     fn extract_state<'c, S: KnownState + 'c, U: KnownState + 'c>(
         &'c self,
         state: impl IntoIterator<Item = &'c OutputAssignment<S>> + 'c,
@@ -304,48 +522,53 @@ impl ContractIface {
         self.allocations(outpoint)
     }
 
-    // TODO: Ignore blank state transition
     fn operations<'c, C: StateChange>(
         &'c self,
         state: impl IntoIterator<Item = OutputAssignment<C::State>> + 'c,
-        allocations: impl Iterator<Item = OutputAssignment<C::State>> + 'c,
         witness_filter: impl WitnessFilter + Copy,
-        // resolver: impl WitnessCheck + 'c,
+        outpoint_filter: impl OutpointFilter + Copy,
+        with_blanks: bool,
     ) -> HashMap<WitnessId, IfaceOp<C>>
     where
         C::State: 'c,
     {
-        fn f<'a, S: KnownState + 'a, U: KnownState + 'a>(
-            filter: impl WitnessFilter + 'a,
-            state: impl IntoIterator<Item = OutputAssignment<S>> + 'a,
-        ) -> impl Iterator<Item = OutputAssignment<U>> + 'a
-        where
-            S: Clone,
-            U: From<S>,
-        {
-            state
-                .into_iter()
-                .filter(move |outp| filter.include_witness(outp.witness))
-                .map(OutputAssignment::<S>::transmute)
-        }
+        let spent_by = self
+            .transitions
+            .values()
+            .flat_map(|transition| {
+                transition
+                    .inputs
+                    .iter()
+                    .map(|opout| (*opout, transition.witness_id))
+            })
+            .collect::<HashMap<_, _>>();
+        let (local, external): (Vec<_>, Vec<_>) = state
+            .into_iter()
+            .partition(|alloc| outpoint_filter.include_outpoint(alloc.seal));
 
-        let spent = f::<_, C::State>(witness_filter, state).map(OutputAssignment::from);
+        // Operations are constructed from the state owned by the local wallet;
+        // the external state only complements their payers and beneficiaries.
         let mut ops = HashMap::<WitnessId, IfaceOp<C>>::new();
-        for alloc in spent {
-            let AssignmentWitness::Present(witness_id) = alloc.witness else {
+        for alloc in local.iter().cloned() {
+            let Some(witness_id) = spent_by.get(&alloc.opout).copied() else {
                 continue;
             };
+            if !witness_filter.include_witness(AssignmentWitness::Present(witness_id)) {
+                continue;
+            }
             if let Some(op) = ops.get_mut(&witness_id) {
                 op.merge_spent(alloc);
             } else {
                 ops.insert(witness_id, IfaceOp::from_spent(alloc));
             }
         }
-
-        for alloc in allocations {
+        for alloc in local {
             let AssignmentWitness::Present(witness_id) = alloc.witness else {
                 continue;
             };
+            if !witness_filter.include_witness(alloc.witness) {
+                continue;
+            }
             if let Some(op) = ops.get_mut(&witness_id) {
                 op.merge_received(alloc);
             } else {
@@ -353,41 +576,107 @@ impl ContractIface {
             }
         }
 
+        for alloc in external {
+            if let Some(op) = spent_by
+                .get(&alloc.opout)
+                .and_then(|witness_id| ops.get_mut(witness_id))
+            {
+                op.add_payer(&alloc, Ownership::External);
+            }
+            if let AssignmentWitness::Present(witness_id) = alloc.witness {
+                if let Some(op) = ops.get_mut(&witness_id) {
+                    op.add_beneficiary(&alloc, Ownership::External);
+                }
+            }
+        }
+
+        for transition in self.transitions.values() {
+            let Some(op) = ops.get_mut(&transition.witness_id) else {
+                continue;
+            };
+            op.witness_ord = transition.witness_ord;
+            let name = if transition.transition_type == TransitionType::BLANK {
+                blank_name()
+            } else if let Some(name) = self.iface.transition_name(transition.transition_type) {
+                name.clone()
+            } else {
+                continue;
+            };
+            op.transitions
+                .push(name)
+                .expect("internal inconsistency of stash data");
+        }
+        if !with_blanks {
+            ops.retain(|_, op| !op.is_blank());
+        }
+
         ops
     }
 
+    /// Returns operations over fungible state, excluding operations
+    /// consisting only of blank state transitions.
     pub fn fungible_ops<C: StateChange<State = Amount>>(
         &self,
         name: impl Into<FieldName>,
         witness_filter: impl WitnessFilter + Copy,
         outpoint_filter: impl OutpointFilter + Copy,
     ) -> Result<HashMap<WitnessId, IfaceOp<C>>, ContractError> {
-        Ok(self.operations(
-            self.state
-                .fungibles()
-                .iter()
-                .cloned()
-                .map(OutputAssignment::transmute),
-            self.fungible(name, outpoint_filter)?,
-            witness_filter,
-        ))
+        let state = self.fungible(name, FilterIncludeAll)?;
+        Ok(self.operations(state, witness_filter, outpoint_filter, false))
     }
 
+    /// Returns chronologically sorted ledger of the operations over fungible
+    /// state `name` performed by the wallet owning outputs matching the
+    /// filter. Amounts in the ledger are formatted with the given precision.
+    pub fn fungible_ledger(
+        &self,
+        name: impl Into<FieldName>,
+        precision: Precision,
+        outpoint_filter: impl OutpointFilter + Copy,
+    ) -> Result<Ledger, ContractError> {
+        let name = name.into();
+        let opening_balance = Amount::checked_sum(
+            self.fungible(name.clone(), outpoint_filter)?
+                .filter(|alloc| alloc.witness == AssignmentWitness::Absent)
+                .map(|alloc| alloc.state),
+        )
+        .ok_or_else(|| ContractError::AmountOverflow(name.clone()))?;
+        let ops = self.fungible_ops(name, &FilterIncludeAll, outpoint_filter)?;
+        Ok(Ledger::with(self.contract_id(), precision, opening_balance, ops)?)
+    }
+
+    /// Returns operations over fungible state, including blank ones.
+    pub fn fungible_ops_with_blanks<C: StateChange<State = Amount>>(
+        &self,
+        name: impl Into<FieldName>,
+        witness_filter: impl WitnessFilter + Copy,
+        outpoint_filter: impl OutpointFilter + Copy,
+    ) -> Result<HashMap<WitnessId, IfaceOp<C>>, ContractError> {
+        let state = self.fungible(name, FilterIncludeAll)?;
+        Ok(self.operations(state, witness_filter, outpoint_filter, true))
+    }
+
+    /// Returns operations over structured state, excluding operations
+    /// consisting only of blank state transitions.
     pub fn data_ops<C: StateChange<State = DataState>>(
         &self,
         name: impl Into<FieldName>,
         witness_filter: impl WitnessFilter + Copy,
         outpoint_filter: impl OutpointFilter + Copy,
     ) -> Result<HashMap<WitnessId, IfaceOp<C>>, ContractError> {
-        Ok(self.operations(
-            self.state
-                .data()
-                .iter()
-                .cloned()
-                .map(OutputAssignment::transmute),
-            self.data(name, outpoint_filter)?,
-            witness_filter,
-        ))
+        let state = self.data(name, FilterIncludeAll)?;
+        Ok(self.operations(state, witness_filter, outpoint_filter, false))
+    }
+
+    /// Returns operations over structured state, including blank ones.
+    pub fn data_ops_with_blanks<C: StateChange<State = DataState>>(
+        &self,
+        name: impl Into<FieldName>,
+        witness_filter: impl WitnessFilter + Copy,
+        outpoint_filter: impl OutpointFilter + Copy,
+    ) -> Result<HashMap<WitnessId, IfaceOp<C>>, ContractError> {
+        let state = self.data(name, FilterIncludeAll)?;
+        Ok(self.operations(state, witness_filter, outpoint_filter, true))
     }
 
     pub fn wrap<W: IfaceWrapper>(self) -> W { W::from(self) }
@@ -397,3 +686,84 @@ pub trait IfaceWrapper: From<ContractIface> {
     const IFACE_NAME: &'static str;
     const IFACE_ID: IfaceId;
 }
+
+#[cfg(test)]
+mod test {
+    use bp::dbc::Method;
+    use bp::{Outpoint, Txid, Vout};
+    use rgb::{GraphSeal, OutputSeal, XChain};
+
+    use super::*;
+    use crate::fixtures::{consume, output, rgb20_contract, stock_with};
+    use crate::containers::BuilderSeal;
+    use crate::interface::{AmountChange, Rgb20};
+    use crate::persistence::Inventory;
+
+    #[test]
+    fn timestamp_from_witness_pos() {
+        let pos = WitnessPos::new(840000, 1713571767).unwrap();
+        assert_eq!(witness_timestamp(pos), 1713571767);
+    }
+
+    #[test]
+    fn state_point() {
+        let witness_id = WitnessId::Bitcoin([1u8; 32].into());
+        let pos = WitnessPos::new(840000, 1713571767).unwrap();
+        let mined = WitnessAnchor {
+            witness_ord: WitnessOrd::OnChain(pos),
+            witness_id,
+        };
+        let unmined = WitnessAnchor::from_mempool(witness_id);
+
+        assert!(StatePoint::Height(840000).includes(mined));
+        assert!(!StatePoint::Height(839999).includes(mined));
+        assert!(StatePoint::Timestamp(1713571767).includes(mined));
+        assert!(!StatePoint::Timestamp(1713571766).includes(mined));
+        assert!(!StatePoint::Height(u32::MAX).includes(unmined));
+        assert!(StatePoint::Anchor(unmined).includes(mined));
+        assert!(!StatePoint::Anchor(mined).includes(unmined));
+    }
+
+    #[test]
+    fn operation_parties() {
+        let contract = rgb20_contract([(1, 600), (2, 400)]);
+        let contract_id = contract.contract_id();
+        let mut stock = stock_with([contract]);
+
+        let seal = |vout: u32| {
+            let seal = GraphSeal::with_blinded_vout(Method::TapretFirst, Vout::from_u32(vout), 0);
+            BuilderSeal::Revealed(XChain::Bitcoin(seal))
+        };
+        let mut builder = stock
+            .transition_builder(contract_id, "RGB20", None::<&str>)
+            .unwrap();
+        for ((opout, _), state) in stock.state_for_outpoints(contract_id, [output(1)]).unwrap() {
+            builder = builder.add_input(opout, state).unwrap();
+        }
+        let transition = builder
+            .add_fungible_state("assetOwner", seal(0), 100)
+            .unwrap()
+            .add_fungible_state("assetOwner", seal(1), 500)
+            .unwrap()
+            .complete_transition()
+            .unwrap();
+        let witness_id = consume(&mut stock, 3, [transition]);
+
+        let witness_output = |vout: u32| {
+            let outpoint = Outpoint::new(Txid::from([3u8; 32]), vout);
+            XChain::Bitcoin(OutputSeal::new(Method::TapretFirst, outpoint))
+        };
+        let wallet = [output(1), output(2), witness_output(1)].map(|seal| seal.to_outpoint());
+        let rgb20 = stock.contract_iface_wrapped::<Rgb20>(contract_id).unwrap();
+        let history = rgb20.transfer_history(&FilterIncludeAll, &wallet).unwrap();
+        assert_eq!(history.len(), 1);
+        let op = &history[&witness_id];
+        assert_eq!(op.state_change, AmountChange::Dec(Amount::from(100u64)));
+        assert_eq!(op.payers.len(), 1);
+        assert_eq!(op.payers.get(&output(1)), Some(&Ownership::Local));
+        assert_eq!(op.beneficiaries.len(), 2);
+        assert_eq!(op.beneficiaries.get(&witness_output(0)), Some(&Ownership::External));
+        assert_eq!(op.beneficiaries.get(&witness_output(1)), Some(&Ownership::Local));
+        assert_eq!(op.transitions.iter().collect::<Vec<_>>(), vec![&fname!("transfer")]);
+    }
+}
@@ -0,0 +1,512 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contract issuance from a declarative specification, which can be kept in
+//! a TOML, YAML or JSON file.
+
+#![allow(clippy::result_large_err)]
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use amplify::confinement::U16;
+use bp::dbc::Method;
+use chrono::{TimeZone, Utc};
+use commit_verify::{DigestExt, Sha256};
+use invoice::Amount;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use rgb::{AltLayer1, AssetTag, BlindingFactor, DataState, GenesisSeal, StateSchema, XChain};
+use strict_encoding::{FieldName, StrictSerialize, TypeName, VariantName};
+use strict_types::value::{EnumTag, StrictNum};
+use strict_types::{typify, SemId, StrictVal, Ty, TypeRef, TypeSystem};
+
+use crate::containers::Contract;
+use crate::interface::{AttachedState, BuilderError, ContractBuilder, IssuerTriplet, RegistryError};
+use crate::persistence::PersistedState;
+use crate::Outpoint;
+
+const SEED_TAG: &str = "urn:lnp-bp:rgb:issuance-seed#2024-04-12";
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum IssuanceError {
+    #[from]
+    #[display(inner)]
+    Builder(BuilderError),
+
+    #[from]
+    #[display(inner)]
+    Registry(RegistryError),
+
+    /// invalid contract timestamp {0}.
+    InvalidTimestamp(i64),
+
+    /// value of state `{0}` doesn't match the schema: {1}
+    InvalidValue(FieldName, typify::Error),
+
+    /// value of state `{0}` misses field `{1}`.
+    FieldMissed(FieldName, FieldName),
+
+    /// value of state `{0}` contains an array with {2} items, while the schema
+    /// requires exactly {1}.
+    ArrayLength(FieldName, u16, usize),
+
+    /// value of state `{0}` contains a list for a fixed-size array which is not
+    /// a byte array; such arrays are not supported by the specifications.
+    ArrayUnsupported(FieldName),
+
+    /// value of state `{0}` contains an item which is not a byte.
+    NotByte(FieldName),
+}
+
+/// Declarative specification of a contract issuance.
+///
+/// Issuing the same specification always produces the same contract: all
+/// blinding factors and salts are derived from the `seed`, and the asset tags
+/// which are not given explicitly are derived from the contract `timestamp`
+/// and the `seed`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct IssuanceSpec {
+    /// Name of the interface the contract is issued with.
+    pub iface: TypeName,
+    /// Name under which the schema is registered in [`IssuerRegistry`].
+    ///
+    /// [`IssuerRegistry`]: crate::interface::IssuerRegistry
+    pub issuer: TypeName,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub testnet: bool,
+    pub timestamp: i64,
+    /// Secret 256-bit seed from which the seal blinding factors and state salts
+    /// are derived. Anyone knowing the seed can recover the blinding factors and
+    /// unblind the genesis seals and state, so the seed must be kept private
+    /// by the issuer and never be published together with the specification.
+    pub seed: [u8; 32],
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub alt_layers1: Vec<AltLayer1>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub asset_tags: BTreeMap<FieldName, AssetTag>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub globals: Vec<GlobalSpec>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub assignments: Vec<AssignmentSpec>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct GlobalSpec {
+    pub name: FieldName,
+    pub value: SpecValue,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct AssignmentSpec {
+    pub name: FieldName,
+    pub method: Method,
+    pub seal: XChain<Outpoint>,
+    pub state: StateSpec,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum StateSpec {
+    Rights,
+    Amount(Amount),
+    Data(SpecValue),
+    Attachment(AttachedState),
+}
+
+/// Value of a structured state in a specification.
+///
+/// The value gets converted into a strict value and checked against the type
+/// defined by the schema; structure fields are matched by their names,
+/// unions are given as objects with a single variant name key, and options as
+/// either a `null` or the value itself.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", untagged)
+)]
+pub enum SpecValue {
+    Unit,
+    Bool(bool),
+    Uint(u64),
+    Int(i64),
+    String(String),
+    List(Vec<SpecValue>),
+    Object(BTreeMap<String, SpecValue>),
+}
+
+impl From<SpecValue> for StrictVal {
+    fn from(value: SpecValue) -> Self {
+        match value {
+            SpecValue::Unit => StrictVal::Unit,
+            SpecValue::Bool(b) => StrictVal::bool(b),
+            SpecValue::Uint(n) => StrictVal::num(n),
+            SpecValue::Int(n) => StrictVal::num(n),
+            SpecValue::String(s) => StrictVal::String(s),
+            SpecValue::List(list) => StrictVal::List(list.into_iter().map(Self::from).collect()),
+            SpecValue::Object(map) => StrictVal::Map(
+                map.into_iter()
+                    .map(|(key, val)| (StrictVal::String(key), val.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Issues a contract from the specification using the interface, schema and
+/// implementation provided by the `triplet`, and validates it.
+pub(super) fn issue_contract(
+    triplet: IssuerTriplet,
+    spec: &IssuanceSpec,
+) -> Result<Contract, IssuanceError> {
+    let (iface, schema, iimpl) = (triplet.iface(), triplet.schema(), triplet.iimpl());
+    let timestamp = Utc
+        .timestamp_opt(spec.timestamp, 0)
+        .single()
+        .ok_or(IssuanceError::InvalidTimestamp(spec.timestamp))?;
+    let mut rng = ChaCha20Rng::from_seed(spec.seed);
+    // The seed itself must not leak into the published asset tags
+    let mut hasher = Sha256::from_tag(SEED_TAG);
+    hasher.input_raw(&spec.seed);
+    let tag_salt = u64::from_le_bytes(hasher.finish()[..8].try_into().expect("fixed size"));
+
+    let mut builder =
+        ContractBuilder::with(iface.clone(), schema.clone(), iimpl.clone(), spec.testnet)
+            .map_err(BuilderError::from)?;
+    for layer1 in &spec.alt_layers1 {
+        builder = builder.add_layer1(*layer1)?;
+    }
+
+    // Asset tags must be known before any fungible state is added
+    for (name, asset_tag) in &spec.asset_tags {
+        builder = builder.add_asset_tag(name.clone(), *asset_tag)?;
+    }
+    let fungible = spec
+        .assignments
+        .iter()
+        .filter(|a| matches!(a.state, StateSpec::Amount(_)))
+        .map(|a| &a.name)
+        .filter(|name| !spec.asset_tags.contains_key(*name))
+        .collect::<BTreeSet<_>>();
+    for name in fungible {
+        let type_id = iimpl
+            .assignments_type(name)
+            .ok_or_else(|| BuilderError::AssignmentNotFound(name.clone()))?;
+        let asset_tag = AssetTag::new_deterministic(
+            format!("{}/{}", schema.schema_id(), iface.iface_id()),
+            type_id,
+            timestamp,
+            tag_salt,
+        );
+        builder = builder.add_asset_tag(name.clone(), asset_tag)?;
+    }
+
+    for global in &spec.globals {
+        let type_id = iimpl
+            .global_type(&global.name)
+            .ok_or_else(|| BuilderError::GlobalNotFound(global.name.clone()))?;
+        let sem_id = schema
+            .global_types
+            .get(&type_id)
+            .expect("schema should match interface: must be checked by the constructor")
+            .sem_id;
+        let value = typify_value(&schema.types, &global.name, sem_id, &global.value)?;
+        builder = builder.add_global_state_det(global.name.clone(), value, rng.gen())?;
+    }
+
+    for assignment in &spec.assignments {
+        let name = &assignment.name;
+        let type_id = iimpl
+            .assignments_type(name)
+            .ok_or_else(|| BuilderError::AssignmentNotFound(name.clone()))?;
+        let blinding = rng.gen();
+        let method = assignment.method;
+        let seal = assignment.seal.map(|outpoint| {
+            GenesisSeal::with_blinding(method, outpoint.txid, outpoint.vout, blinding)
+        });
+        let state = match (&assignment.state, schema.owned_types.get(&type_id)) {
+            (StateSpec::Rights, Some(StateSchema::Declarative)) => PersistedState::Void,
+            (StateSpec::Amount(amount), Some(StateSchema::Fungible(_))) => {
+                let asset_tag = builder.asset_tag(name.clone())?;
+                let blinding = BlindingFactor::random_custom(&mut rng);
+                PersistedState::Amount(*amount, blinding, asset_tag)
+            }
+            (StateSpec::Data(value), Some(StateSchema::Structured(sem_id))) => {
+                let value = typify_value(&schema.types, name, *sem_id, value)?;
+                let data = value
+                    .to_strict_serialized::<U16>()
+                    .map_err(BuilderError::from)?;
+                PersistedState::Data(DataState::from(data), rng.gen())
+            }
+            (StateSpec::Attachment(attach), Some(StateSchema::Attachment(_))) => {
+                PersistedState::Attachment(attach.clone(), rng.gen())
+            }
+            _ => return Err(BuilderError::InvalidState(type_id).into()),
+        };
+        builder = builder.add_owned_state_det(name.clone(), seal, state)?;
+    }
+
+    Ok(builder.issue_contract_det(spec.timestamp)?)
+}
+
+fn typify_value(
+    types: &TypeSystem,
+    name: &FieldName,
+    sem_id: SemId,
+    value: &SpecValue,
+) -> Result<impl StrictSerialize, IssuanceError> {
+    let value = conform(types, name, sem_id, value.clone().into())?;
+    let typed = types
+        .typify(value, sem_id)
+        .map_err(|err| IssuanceError::InvalidValue(name.clone(), err))?;
+    Ok(types
+        .strict_serialize_type::<{ u16::MAX as usize }>(&typed)
+        .map_err(BuilderError::from)?)
+}
+
+/// Brings the value to the form expected by [`TypeSystem::typify`]: puts
+/// structure fields in the order of the type definition (which defines their
+/// serialization order), converts objects into union variants, lists into
+/// sets, tuples and byte arrays.
+fn conform(
+    types: &TypeSystem,
+    name: &FieldName,
+    sem_id: SemId,
+    value: StrictVal,
+) -> Result<StrictVal, IssuanceError> {
+    let Some(ty) = types.find(sem_id) else {
+        return Ok(value);
+    };
+    Ok(match (value, ty) {
+        (StrictVal::Map(mut entries), Ty::Struct(fields)) => {
+            let mut ordered = Vec::with_capacity(entries.len());
+            for field in fields {
+                let pos = entries
+                    .iter()
+                    .position(|(key, _)| {
+                        matches!(key, StrictVal::String(s) if s == field.name.as_str())
+                    })
+                    .ok_or_else(|| IssuanceError::FieldMissed(name.clone(), field.name.clone()))?;
+                let (key, val) = entries.remove(pos);
+                ordered.push((key, conform(types, name, field.ty, val)?));
+            }
+            // Remaining unknown fields are reported by the typification
+            ordered.extend(entries);
+            StrictVal::Map(ordered)
+        }
+        (StrictVal::Map(entries), ty @ Ty::Union(variants))
+            if !ty.is_option() && entries.len() == 1 =>
+        {
+            let (key, val) = entries.into_iter().next().expect("single entry");
+            let variant = match &key {
+                StrictVal::String(s) => VariantName::try_from(s.clone()).ok(),
+                _ => None,
+            };
+            match variant.as_ref().and_then(|v| variants.ty_by_name(v).map(|id| (v, *id))) {
+                Some((variant, id)) => StrictVal::Union(
+                    EnumTag::Name(variant.clone()),
+                    Box::new(conform(types, name, id, val)?),
+                ),
+                None => StrictVal::Map(vec![(key, val)]),
+            }
+        }
+        (StrictVal::Map(entries), Ty::Map(key_id, val_id, _)) => StrictVal::Map(
+            entries
+                .into_iter()
+                .map(|(key, val)| {
+                    Ok((conform(types, name, *key_id, key)?, conform(types, name, *val_id, val)?))
+                })
+                .collect::<Result<_, IssuanceError>>()?,
+        ),
+        (StrictVal::List(items), Ty::List(id, _)) => StrictVal::List(
+            items
+                .into_iter()
+                .map(|item| conform(types, name, *id, item))
+                .collect::<Result<_, _>>()?,
+        ),
+        (StrictVal::List(items), Ty::Set(id, _)) => StrictVal::Set(
+            items
+                .into_iter()
+                .map(|item| conform(types, name, *id, item))
+                .collect::<Result<_, _>>()?,
+        ),
+        (StrictVal::List(items), Ty::Tuple(fields)) if items.len() == fields.len() => {
+            StrictVal::Tuple(
+                items
+                    .into_iter()
+                    .zip(fields)
+                    .map(|(item, id)| conform(types, name, *id, item))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        (StrictVal::List(items), Ty::Array(id, len)) => {
+            if !id.is_byte() {
+                return Err(IssuanceError::ArrayUnsupported(name.clone()));
+            }
+            if items.len() != *len as usize {
+                return Err(IssuanceError::ArrayLength(name.clone(), *len, items.len()));
+            }
+            StrictVal::Bytes(
+                items
+                    .into_iter()
+                    .map(|item| match item {
+                        StrictVal::Number(StrictNum::Uint(n)) => u8::try_from(n).map_err(|_| ()),
+                        _ => Err(()),
+                    })
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| IssuanceError::NotByte(name.clone()))?,
+            )
+        }
+        (StrictVal::String(s), Ty::Array(_, len)) if s.len() != *len as usize => {
+            return Err(IssuanceError::ArrayLength(name.clone(), *len, s.len()));
+        }
+        (StrictVal::Unit, ty @ Ty::Union(_)) if ty.is_option() => StrictVal::Unit,
+        (value, ty @ Ty::Union(variants)) if ty.is_option() => {
+            let id = variants.ty_by_tag(1).expect("optional always have `Some`");
+            conform(types, name, *id, value)?
+        }
+        (value, Ty::Tuple(fields)) if fields.len() == 1 => conform(types, name, fields[0], value)?,
+        (value, _) => value,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use bp::Txid;
+    use invoice::Precision;
+
+    use super::*;
+    use crate::fixtures::TestRgb20;
+    use crate::interface::IssuerRegistry;
+    use crate::stl::{AssetSpec, StandardTypes};
+
+    fn rgb20_spec(seed: [u8; 32]) -> IssuanceSpec {
+        IssuanceSpec {
+            iface: tn!("RGB20"),
+            issuer: tn!("TestRgb20"),
+            testnet: true,
+            timestamp: 1_700_000_000,
+            seed,
+            alt_layers1: vec![],
+            asset_tags: none!(),
+            globals: vec![
+                GlobalSpec {
+                    name: fname!("spec"),
+                    value: SpecValue::Object(bmap! {
+                        s!("details") => SpecValue::Unit,
+                        s!("name") => SpecValue::String(s!("Test asset")),
+                        s!("precision") => SpecValue::String(s!("indivisible")),
+                        s!("ticker") => SpecValue::String(s!("TEST")),
+                    }),
+                },
+                GlobalSpec {
+                    name: fname!("terms"),
+                    value: SpecValue::Object(bmap! {
+                        s!("text") => SpecValue::String(s!("")),
+                        s!("media") => SpecValue::Unit,
+                    }),
+                },
+                GlobalSpec {
+                    name: fname!("issuedSupply"),
+                    value: SpecValue::Uint(100),
+                },
+            ],
+            assignments: vec![AssignmentSpec {
+                name: fname!("assetOwner"),
+                method: Method::TapretFirst,
+                seal: XChain::Bitcoin(Outpoint::new(Txid::from([1; 32]), 0)),
+                state: StateSpec::Amount(Amount::from(100u64)),
+            }],
+        }
+    }
+
+    #[test]
+    fn spec_value_field_order() {
+        let types = StandardTypes::new();
+        let sem_id = types.get("RGBContract.AssetSpec");
+        let value = SpecValue::Object(bmap! {
+            s!("details") => SpecValue::Unit,
+            s!("name") => SpecValue::String(s!("Test asset")),
+            s!("precision") => SpecValue::String(s!("centiMicro")),
+            s!("ticker") => SpecValue::String(s!("TEST")),
+        });
+        let serialized = typify_value(&types.type_system(), &fname!("spec"), sem_id, &value)
+            .unwrap()
+            .to_strict_serialized::<U16>()
+            .unwrap();
+        let expected = AssetSpec::new("TEST", "Test asset", Precision::CentiMicro)
+            .to_strict_serialized::<U16>()
+            .unwrap();
+        assert_eq!(serialized, expected);
+    }
+
+    #[test]
+    fn unknown_schema() {
+        let spec = IssuanceSpec {
+            iface: tn!("RGB20"),
+            issuer: tn!("Unknown"),
+            testnet: true,
+            timestamp: 1_700_000_000,
+            seed: [0; 32],
+            alt_layers1: vec![],
+            asset_tags: none!(),
+            globals: vec![],
+            assignments: vec![],
+        };
+        assert_eq!(
+            IssuerRegistry::new().issue(&spec).unwrap_err(),
+            IssuanceError::Registry(RegistryError::UnknownSchemaName(tn!("Unknown")))
+        );
+    }
+
+    #[test]
+    fn issue_deterministic() {
+        let mut registry = IssuerRegistry::standard();
+        registry.register::<TestRgb20>("TestRgb20").unwrap();
+
+        let contract = registry.issue(&rgb20_spec([1; 32])).unwrap();
+        let repeated = registry.issue(&rgb20_spec([1; 32])).unwrap();
+        assert_eq!(contract.contract_id(), repeated.contract_id());
+
+        let other = registry.issue(&rgb20_spec([2; 32])).unwrap();
+        assert_ne!(contract.contract_id(), other.contract_id());
+    }
+}
@@ -0,0 +1,408 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Chronological ledger of the operations over fungible contract state, used
+//! for the accounting export.
+
+use std::fmt::Write;
+
+use chrono::{DateTime, TimeZone, Utc};
+use invoice::{Amount, Precision};
+use rgb::{ContractId, WitnessAnchor, WitnessId, XOutputSeal};
+
+use crate::interface::{AmountChange, IfaceOp};
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum LedgerError {
+    /// wallet balance overflows with the funds received in the witness {0}.
+    BalanceOverflow(WitnessId),
+
+    /// witness {0} spends more funds than the wallet balance, meaning that the
+    /// contract history known to the wallet is inconsistent.
+    BalanceUnderflow(WitnessId),
+}
+
+/// Direction of the funds moved by a ledger entry, relative to the wallet.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display(lowercase)]
+pub enum Direction {
+    /// The wallet has received funds.
+    Incoming,
+    /// The wallet has sent funds.
+    Outgoing,
+    /// The funds were moved between outputs of the wallet.
+    Internal,
+}
+
+/// Single operation in the [`Ledger`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct LedgerEntry {
+    pub witness_id: WitnessId,
+    /// Height of the block mining the witness transaction, if it is mined.
+    pub height: Option<u32>,
+    /// Timestamp of the block mining the witness transaction, if it is mined.
+    pub timestamp: Option<i64>,
+    pub direction: Direction,
+    /// Amount of funds moved by the operation (in atomic units).
+    pub amount: Amount,
+    /// Outputs not belonging to the wallet which have paid or received the
+    /// funds.
+    pub counterparties: Vec<XOutputSeal>,
+    /// Wallet balance after the operation (in atomic units).
+    pub balance: Amount,
+}
+
+impl LedgerEntry {
+    /// Date of the block mining the witness transaction, if it is mined.
+    pub fn date(&self) -> Option<DateTime<Utc>> {
+        self.timestamp
+            .and_then(|timestamp| Utc.timestamp_opt(timestamp, 0).single())
+    }
+}
+
+/// Chronologically sorted ledger of the operations over fungible state of a
+/// contract, as seen by the wallet.
+///
+/// Operations with mined witness transactions go first, ordered by their
+/// position in the blockchain, followed by the unmined ones.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct Ledger {
+    pub contract_id: ContractId,
+    /// Precision used to format amounts.
+    pub precision: Precision,
+    /// Balance received by the wallet in the contract genesis and state
+    /// extensions, i.e. without witness transactions.
+    pub opening_balance: Amount,
+    pub entries: Vec<LedgerEntry>,
+}
+
+impl Ledger {
+    /// Constructs ledger from the operations returned by
+    /// [`crate::interface::ContractIface::fungible_ops`] or
+    /// [`crate::interface::rgb20::Rgb20::transfer_history`].
+    ///
+    /// # Errors
+    ///
+    /// If the wallet balance computed from the operations overflows or goes
+    /// below zero.
+    pub fn with(
+        contract_id: ContractId,
+        precision: Precision,
+        opening_balance: Amount,
+        ops: impl IntoIterator<Item = (WitnessId, IfaceOp<AmountChange>)>,
+    ) -> Result<Self, LedgerError> {
+        let mut ops = ops
+            .into_iter()
+            .map(|(witness_id, op)| {
+                let witness_anchor = WitnessAnchor {
+                    witness_ord: op.witness_ord,
+                    witness_id,
+                };
+                (witness_anchor, op)
+            })
+            .collect::<Vec<_>>();
+        // Witness positions are ordered by the block timestamps only, which
+        // are not monotonic, so the height is compared first.
+        ops.sort_by_key(|(witness_anchor, op)| (op.height().unwrap_or(u32::MAX), *witness_anchor));
+
+        let mut balance = opening_balance;
+        let mut entries = Vec::with_capacity(ops.len());
+        for (witness_anchor, op) in ops {
+            let witness_id = witness_anchor.witness_id;
+            let (direction, amount) = match op.state_change {
+                AmountChange::Inc(amount) => {
+                    balance
+                        .checked_add_assign(amount)
+                        .ok_or(LedgerError::BalanceOverflow(witness_id))?;
+                    (Direction::Incoming, amount)
+                }
+                AmountChange::Dec(amount) => {
+                    balance
+                        .checked_sub_assign(amount)
+                        .ok_or(LedgerError::BalanceUnderflow(witness_id))?;
+                    (Direction::Outgoing, amount)
+                }
+                AmountChange::Zero => (Direction::Internal, Amount::ZERO),
+            };
+            let counterparties = op
+                .payers
+                .iter()
+                .chain(&op.beneficiaries)
+                .filter(|(_, ownership)| !ownership.is_local())
+                .map(|(seal, _)| *seal)
+                .collect();
+            entries.push(LedgerEntry {
+                witness_id,
+                height: op.height(),
+                timestamp: op.timestamp(),
+                direction,
+                amount,
+                counterparties,
+                balance,
+            });
+        }
+
+        Ok(Ledger {
+            contract_id,
+            precision,
+            opening_balance,
+            entries,
+        })
+    }
+
+    /// Wallet balance after the last operation in the ledger.
+    pub fn closing_balance(&self) -> Amount {
+        self.entries
+            .last()
+            .map(|entry| entry.balance)
+            .unwrap_or(self.opening_balance)
+    }
+
+    /// Formats atomic amount as a decimal number with the ledger precision.
+    pub fn format_amount(&self, amount: Amount) -> String {
+        let (int, fract) = amount.split(self.precision);
+        match self.precision.decimals() as usize {
+            0 => int.to_string(),
+            decimals => format!("{int}.{fract:0decimals$}"),
+        }
+    }
+
+    /// Exports the ledger as CSV with a header line. Dates are formatted
+    /// according to RFC 3339; several counterparty outputs are separated by
+    /// spaces.
+    pub fn to_csv(&self) -> String {
+        let mut csv = s!("date,witness_id,height,direction,amount,counterparties,balance\n");
+        for entry in &self.entries {
+            let counterparties = entry
+                .counterparties
+                .iter()
+                .map(XOutputSeal::to_string)
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(
+                csv,
+                "{},{},{},{},{},{},{}",
+                entry.date().map(|date| date.to_rfc3339()).unwrap_or_default(),
+                entry.witness_id,
+                entry.height.map(|h| h.to_string()).unwrap_or_default(),
+                entry.direction,
+                self.format_amount(entry.amount),
+                counterparties,
+                self.format_amount(entry.balance),
+            )
+            .expect("writing to string never fails");
+        }
+        csv
+    }
+
+    /// Exports the ledger as a JSON object. Amounts are given as decimal
+    /// strings formatted with the ledger precision; height, timestamp and
+    /// date of unmined operations are `null`.
+    pub fn to_json(&self) -> String {
+        // All values are either numbers or display strings of identifiers,
+        // amounts and dates, which never require escaping.
+        fn opt(value: Option<impl ToString>, quoted: bool) -> String {
+            match value {
+                Some(value) if quoted => format!("\"{}\"", value.to_string()),
+                Some(value) => value.to_string(),
+                None => s!("null"),
+            }
+        }
+
+        let mut json = format!(
+            "{{\"contractId\":\"{}\",\"openingBalance\":\"{}\",\"closingBalance\":\"{}\",\
+             \"entries\":[",
+            self.contract_id,
+            self.format_amount(self.opening_balance),
+            self.format_amount(self.closing_balance()),
+        );
+        for (no, entry) in self.entries.iter().enumerate() {
+            let counterparties = entry
+                .counterparties
+                .iter()
+                .map(|seal| format!("\"{seal}\""))
+                .collect::<Vec<_>>()
+                .join(",");
+            write!(
+                json,
+                "{}{{\"date\":{},\"witnessId\":\"{}\",\"height\":{},\"timestamp\":{},\
+                 \"direction\":\"{}\",\"amount\":\"{}\",\"counterparties\":[{}],\
+                 \"balance\":\"{}\"}}",
+                if no == 0 { "" } else { "," },
+                opt(entry.date().map(|date| date.to_rfc3339()), true),
+                entry.witness_id,
+                opt(entry.height, false),
+                opt(entry.timestamp, false),
+                entry.direction,
+                self.format_amount(entry.amount),
+                counterparties,
+                self.format_amount(entry.balance),
+            )
+            .expect("writing to string never fails");
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::SmallOrdMap;
+    use bp::dbc::Method;
+    use bp::{Outpoint, Txid};
+    use rgb::{OutputSeal, WitnessOrd, WitnessPos, XChain};
+
+    use super::*;
+    use crate::interface::Ownership;
+
+    fn op(
+        change: AmountChange,
+        height: Option<u32>,
+        external: Option<XOutputSeal>,
+    ) -> IfaceOp<AmountChange> {
+        let witness_ord = match height {
+            Some(height) => WitnessOrd::OnChain(WitnessPos::new(height, 1713571767).unwrap()),
+            None => WitnessOrd::OffChain,
+        };
+        let beneficiaries = external
+            .map(|seal| SmallOrdMap::from_iter_unsafe([(seal, Ownership::External)]))
+            .unwrap_or_default();
+        IfaceOp {
+            opids: none!(),
+            inputs: none!(),
+            state_change: change,
+            transitions: none!(),
+            witness_ord,
+            payers: none!(),
+            beneficiaries,
+        }
+    }
+
+    #[test]
+    fn ledger_csv() {
+        let witness_id = |no: u8| XChain::Bitcoin(Txid::from([no; 32]));
+        let seal = XChain::Bitcoin(OutputSeal::new(
+            Method::TapretFirst,
+            Outpoint::new(Txid::from([9u8; 32]), 1),
+        ));
+        let ops = [
+            (witness_id(1), op(AmountChange::Dec(Amount::from(250u64)), None, Some(seal))),
+            (witness_id(2), op(AmountChange::Inc(Amount::from(1005u64)), Some(840001), None)),
+            (witness_id(3), op(AmountChange::Zero, Some(840000), None)),
+        ];
+        let ledger = Ledger::with(
+            ContractId::from([0u8; 32]),
+            Precision::Centi,
+            Amount::from(100u64),
+            ops,
+        )
+        .unwrap();
+
+        let witnesses = ledger.entries.iter().map(|e| e.witness_id).collect::<Vec<_>>();
+        assert_eq!(witnesses, vec![witness_id(3), witness_id(2), witness_id(1)]);
+        assert_eq!(ledger.closing_balance(), Amount::from(855u64));
+        assert_eq!(ledger.format_amount(Amount::from(1005u64)), "10.05");
+
+        let csv = ledger.to_csv();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[2].starts_with("2024-04-20T00:09:27+00:00,"));
+        assert!(lines[2].ends_with(",840001,incoming,10.05,,11.05"));
+        assert!(lines[3].starts_with(",bc:"));
+        assert!(lines[3].ends_with(&format!(",,outgoing,2.50,{seal},8.55")));
+    }
+
+    #[test]
+    fn ledger_json() {
+        let witness_id = |no: u8| XChain::Bitcoin(Txid::from([no; 32]));
+        let seal = XChain::Bitcoin(OutputSeal::new(
+            Method::TapretFirst,
+            Outpoint::new(Txid::from([9u8; 32]), 1),
+        ));
+        let ops = [
+            (witness_id(1), op(AmountChange::Dec(Amount::from(250u64)), None, Some(seal))),
+            (witness_id(2), op(AmountChange::Inc(Amount::from(1005u64)), Some(840001), None)),
+        ];
+        let ledger = Ledger::with(
+            ContractId::from([0u8; 32]),
+            Precision::Centi,
+            Amount::from(100u64),
+            ops,
+        )
+        .unwrap();
+
+        let contract_id = ledger.contract_id;
+        let (mined, unmined) = (witness_id(2), witness_id(1));
+        assert_eq!(
+            ledger.to_json(),
+            format!(
+                "{{\"contractId\":\"{contract_id}\",\"openingBalance\":\"1.00\",\
+                 \"closingBalance\":\"8.55\",\"entries\":[\
+                 {{\"date\":\"2024-04-20T00:09:27+00:00\",\"witnessId\":\"{mined}\",\
+                 \"height\":840001,\"timestamp\":1713571767,\"direction\":\"incoming\",\
+                 \"amount\":\"10.05\",\"counterparties\":[],\"balance\":\"11.05\"}},\
+                 {{\"date\":null,\"witnessId\":\"{unmined}\",\"height\":null,\
+                 \"timestamp\":null,\"direction\":\"outgoing\",\"amount\":\"2.50\",\
+                 \"counterparties\":[\"{seal}\"],\"balance\":\"8.55\"}}]}}"
+            )
+        );
+        let empty = Ledger::with(contract_id, Precision::Centi, Amount::ZERO, []).unwrap();
+        assert_eq!(
+            empty.to_json(),
+            format!(
+                "{{\"contractId\":\"{contract_id}\",\"openingBalance\":\"0.00\",\
+                 \"closingBalance\":\"0.00\",\"entries\":[]}}"
+            )
+        );
+    }
+
+    #[test]
+    fn ledger_balance_checked() {
+        let contract_id = ContractId::from([0u8; 32]);
+        let witness_id = XChain::Bitcoin(Txid::from([1; 32]));
+        let spent = [(witness_id, op(AmountChange::Dec(Amount::from(101u64)), None, None))];
+        assert_eq!(
+            Ledger::with(contract_id, Precision::Centi, Amount::from(100u64), spent),
+            Err(LedgerError::BalanceUnderflow(witness_id))
+        );
+        let received = [(witness_id, op(AmountChange::Inc(Amount::from(1u64)), None, None))];
+        assert_eq!(
+            Ledger::with(contract_id, Precision::Centi, Amount::from(u64::MAX), received),
+            Err(LedgerError::BalanceOverflow(witness_id))
+        );
+    }
+}
@@ -27,31 +27,45 @@ mod iface;
 mod iimpl;
 mod contract;
 mod builder;
+mod issuance;
+mod registry;
 pub mod rgb20;
 pub mod rgb21;
+pub mod rgb22;
+pub mod rgb23;
+pub mod rgb24;
 pub mod rgb25;
 mod suppl;
 mod filters;
+mod ledger;
 pub(crate) mod resolver;
 mod contractum;
 
-pub use builder::{BuilderError, ContractBuilder, TransitionBuilder, TxOutpoint};
+pub(crate) use builder::validate_operation;
+pub use builder::{BuilderError, ContractBuilder, ExtensionBuilder, TransitionBuilder, TxOutpoint};
 pub use contract::{
     AllocatedState, AttachAllocation, AttachedState, ContractError, ContractIface, DataAllocation,
-    FungibleAllocation, IfaceOp, IfaceWrapper, OwnedAllocation, RightsAllocation, StateChange,
+    FungibleAllocation, IfaceOp, IfaceWrapper, KnownTransition, OwnedAllocation, Ownership,
+    RightsAllocation, StateChange, StatePoint,
 };
 pub use contractum::IfaceDisplay;
 pub use filters::{FilterExclude, FilterIncludeAll, OutpointFilter, WitnessFilter};
+pub use ledger::{Direction, Ledger, LedgerEntry, LedgerError};
 pub use iface::{
     ArgMap, AssignIface, ExtensionIface, GenesisIface, GlobalIface, Iface, IfaceId,
     IfaceInconsistency, Modifier, OpName, OwnedIface, Req, TransitionIface, ValencyIface,
 };
+pub use issuance::{AssignmentSpec, GlobalSpec, IssuanceError, IssuanceSpec, SpecValue, StateSpec};
 pub use iimpl::{
     IfaceClass, IfaceImpl, IfacePair, ImplId, IssuerClass, IssuerTriplet, NamedField, NamedType,
     SchemaIfaces, SchemaIssuer, SchemaTypeIndex, WrongImplementation,
 };
-pub use rgb20::{AmountChange, Rgb20, LIB_NAME_RGB20};
-pub use rgb21::{Rgb21, LIB_ID_RGB21, LIB_NAME_RGB21};
+pub use registry::{IssuerRegistry, RegistryError};
+pub use rgb20::{AmountChange, Rgb20, Rgb20Inventory, LIB_NAME_RGB20};
+pub use rgb21::{Rgb21, Rgb21Inventory, LIB_ID_RGB21, LIB_NAME_RGB21};
+pub use rgb22::{Rgb22, LIB_NAME_RGB22};
+pub use rgb23::{Rgb23, LIB_NAME_RGB23};
+pub use rgb24::{Rgb24, LIB_NAME_RGB24};
 pub use rgb25::{Rgb25, LIB_NAME_RGB25};
 pub use suppl::{ContractSuppl, OwnedStateSuppl, SupplId, TickerSuppl, VelocityHint};
 
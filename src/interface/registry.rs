@@ -0,0 +1,288 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runtime registry of interfaces, schemata and interface implementations.
+
+#![allow(clippy::result_large_err)]
+
+use std::collections::BTreeMap;
+
+use amplify::confinement;
+use rgb::{validation, SchemaId, SubSchema};
+use strict_encoding::TypeName;
+
+use crate::containers::{Contract, UniversalFile};
+use crate::interface::issuance::issue_contract;
+use crate::interface::{
+    Iface, IfaceClass, IfaceId, IfaceImpl, IssuanceError, IssuanceSpec, IssuerClass, IssuerTriplet,
+    Rgb20, Rgb21, Rgb22, Rgb23, Rgb24, Rgb25, SchemaIfaces, WrongImplementation,
+};
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum RegistryError {
+    /// interface `{0}` is not registered.
+    UnknownIfaceName(TypeName),
+
+    /// interface {0::<0} is not registered.
+    UnknownIface(IfaceId),
+
+    /// schema `{0}` is not registered.
+    UnknownSchemaName(TypeName),
+
+    /// schema {0::<0} is not registered.
+    UnknownSchema(SchemaId),
+
+    /// schema {0::<0} doesn't have an implementation of interface {1::<0}.
+    NoImpl(SchemaId, IfaceId),
+
+    /// name `{0}` is already used by schema {1::<0}.
+    NameTaken(TypeName, SchemaId),
+
+    /// invalid schema.
+    ///
+    /// {0}
+    InvalidSchema(validation::Status),
+
+    /// the file contains {0}, while only interfaces, schemata and interface
+    /// implementations can be registered.
+    UnsupportedFile(&'static str),
+
+    #[from]
+    #[display(inner)]
+    WrongImplementation(WrongImplementation),
+
+    #[from]
+    #[display(inner)]
+    Confinement(confinement::Error),
+}
+
+/// Registry of interfaces, schemata and their implementations, which can be
+/// populated at runtime, including from [`UniversalFile`]s.
+///
+/// Schemata may be given names, which are used to reference them in the
+/// issuance specifications ([`crate::interface::IssuanceSpec`]).
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct IssuerRegistry {
+    ifaces: BTreeMap<IfaceId, Iface>,
+    schemata: BTreeMap<SchemaId, SchemaIfaces>,
+    names: BTreeMap<TypeName, SchemaId>,
+}
+
+impl IssuerRegistry {
+    pub fn new() -> Self { none!() }
+
+    /// Constructs registry with all standard interfaces.
+    pub fn standard() -> Self {
+        let mut registry = Self::new();
+        registry.register_iface(Rgb20::iface());
+        registry.register_iface(Rgb21::iface());
+        registry.register_iface(Rgb22::iface());
+        registry.register_iface(Rgb23::iface());
+        registry.register_iface(Rgb24::iface());
+        registry.register_iface(Rgb25::iface());
+        registry
+    }
+
+    /// Registers interface, schema and implementation defined by the issuer
+    /// class, giving the schema a `name`.
+    pub fn register<C: IssuerClass>(
+        &mut self,
+        name: impl Into<TypeName>,
+    ) -> Result<(), RegistryError> {
+        self.register_triplet(name, C::issuer().into_triplet())
+    }
+
+    pub fn register_triplet(
+        &mut self,
+        name: impl Into<TypeName>,
+        triplet: IssuerTriplet,
+    ) -> Result<(), RegistryError> {
+        let (iface, schema, iimpl) = triplet.into_split();
+        self.register_iface(iface);
+        let schema_id = self.register_schema(schema)?;
+        self.register_iimpl(iimpl)?;
+        self.set_name(name, schema_id)
+    }
+
+    /// Registers interface, returning whether it was not known before.
+    pub fn register_iface(&mut self, iface: Iface) -> bool {
+        self.ifaces.insert(iface.iface_id(), iface).is_none()
+    }
+
+    /// Verifies and registers schema. The schema may be given a name later
+    /// with [`Self::set_name`].
+    pub fn register_schema(&mut self, schema: SubSchema) -> Result<SchemaId, RegistryError> {
+        let status = schema.verify();
+        if !status.failures.is_empty() {
+            return Err(RegistryError::InvalidSchema(status));
+        }
+        let schema_id = schema.schema_id();
+        self.schemata
+            .entry(schema_id)
+            .or_insert_with(|| SchemaIfaces::new(schema));
+        Ok(schema_id)
+    }
+
+    /// Registers interface implementation; both the interface and the schema
+    /// must be registered before.
+    pub fn register_iimpl(&mut self, iimpl: IfaceImpl) -> Result<(), RegistryError> {
+        if !self.ifaces.contains_key(&iimpl.iface_id) {
+            return Err(RegistryError::UnknownIface(iimpl.iface_id));
+        }
+        let schema_ifaces = self
+            .schemata
+            .get_mut(&iimpl.schema_id)
+            .ok_or(RegistryError::UnknownSchema(iimpl.schema_id))?;
+        schema_ifaces.iimpls.insert(iimpl.iface_id, iimpl)?;
+        Ok(())
+    }
+
+    /// Registers interface, schema or interface implementation from a file.
+    pub fn register_file(&mut self, file: UniversalFile) -> Result<(), RegistryError> {
+        match file {
+            UniversalFile::Iface(iface) => {
+                self.register_iface(iface);
+            }
+            UniversalFile::Schema(schema) => {
+                self.register_schema(schema)?;
+            }
+            UniversalFile::Impl(iimpl) => self.register_iimpl(iimpl)?,
+            UniversalFile::Contract(_) => return Err(RegistryError::UnsupportedFile("contract")),
+            UniversalFile::Transfer(_) => return Err(RegistryError::UnsupportedFile("transfer")),
+            UniversalFile::Suppl(_) => {
+                return Err(RegistryError::UnsupportedFile("contract supplement"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Gives a name to the registered schema.
+    pub fn set_name(
+        &mut self,
+        name: impl Into<TypeName>,
+        schema_id: SchemaId,
+    ) -> Result<(), RegistryError> {
+        let name = name.into();
+        if !self.schemata.contains_key(&schema_id) {
+            return Err(RegistryError::UnknownSchema(schema_id));
+        }
+        match self.names.get(&name) {
+            Some(id) if *id != schema_id => Err(RegistryError::NameTaken(name, *id)),
+            _ => {
+                self.names.insert(name, schema_id);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn ifaces(&self) -> impl Iterator<Item = &Iface> { self.ifaces.values() }
+
+    pub fn schemata(&self) -> impl Iterator<Item = &SchemaIfaces> { self.schemata.values() }
+
+    pub fn names(&self) -> impl Iterator<Item = (&TypeName, SchemaId)> {
+        self.names.iter().map(|(name, id)| (name, *id))
+    }
+
+    pub fn iface_by_id(&self, id: IfaceId) -> Result<&Iface, RegistryError> {
+        self.ifaces.get(&id).ok_or(RegistryError::UnknownIface(id))
+    }
+
+    pub fn iface_by_name(&self, name: &TypeName) -> Result<&Iface, RegistryError> {
+        self.ifaces
+            .values()
+            .find(|iface| &iface.name == name)
+            .ok_or_else(|| RegistryError::UnknownIfaceName(name.clone()))
+    }
+
+    pub fn schema_by_id(&self, id: SchemaId) -> Result<&SchemaIfaces, RegistryError> {
+        self.schemata.get(&id).ok_or(RegistryError::UnknownSchema(id))
+    }
+
+    pub fn schema_by_name(&self, name: &TypeName) -> Result<&SchemaIfaces, RegistryError> {
+        let id = self
+            .names
+            .get(name)
+            .ok_or_else(|| RegistryError::UnknownSchemaName(name.clone()))?;
+        self.schema_by_id(*id)
+    }
+
+    /// Returns issuer for the schema and interface with the given ids.
+    pub fn issuer_by_id(
+        &self,
+        schema_id: SchemaId,
+        iface_id: IfaceId,
+    ) -> Result<IssuerTriplet, RegistryError> {
+        let schema_ifaces = self.schema_by_id(schema_id)?;
+        let iface = self.iface_by_id(iface_id)?;
+        self.issuer_for(schema_ifaces, iface)
+    }
+
+    /// Returns issuer for the schema and interface with the given names.
+    pub fn issuer_by_name(
+        &self,
+        schema: &TypeName,
+        iface: &TypeName,
+    ) -> Result<IssuerTriplet, RegistryError> {
+        let schema_ifaces = self.schema_by_name(schema)?;
+        let iface = self.iface_by_name(iface)?;
+        self.issuer_for(schema_ifaces, iface)
+    }
+
+    /// Issues a contract from the specification and validates it.
+    pub fn issue(&self, spec: &IssuanceSpec) -> Result<Contract, IssuanceError> {
+        let triplet = self.issuer_by_name(&spec.issuer, &spec.iface)?;
+        issue_contract(triplet, spec)
+    }
+
+    fn issuer_for(
+        &self,
+        schema_ifaces: &SchemaIfaces,
+        iface: &Iface,
+    ) -> Result<IssuerTriplet, RegistryError> {
+        let schema_id = schema_ifaces.schema.schema_id();
+        let iface_id = iface.iface_id();
+        let iimpl = schema_ifaces
+            .iimpls
+            .get(&iface_id)
+            .ok_or(RegistryError::NoImpl(schema_id, iface_id))?;
+        let triplet =
+            IssuerTriplet::new(iface.clone(), schema_ifaces.schema.clone(), iimpl.clone())?;
+        Ok(triplet)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn standard_ifaces() {
+        let registry = IssuerRegistry::standard();
+        assert_eq!(registry.ifaces().count(), 6);
+        let rgb20 = registry.iface_by_name(&tn!("RGB20")).unwrap();
+        assert_eq!(registry.iface_by_id(rgb20.iface_id()).unwrap(), rgb20);
+        assert_eq!(
+            registry.schema_by_name(&tn!("NonInflatableAsset")).unwrap_err(),
+            RegistryError::UnknownSchemaName(tn!("NonInflatableAsset"))
+        );
+    }
+}
@@ -19,27 +19,40 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::str::FromStr;
 
+use amplify::confinement::Confined;
 use bp::dbc::Method;
+use bp::seals::txout::CloseMethod;
+use bp::Vout;
 use chrono::Utc;
 use invoice::{Amount, Precision};
-use rgb::{AltLayer1, AssetTag, BlindingFactor, GenesisSeal, Occurrences, Types, WitnessId};
-use strict_encoding::{InvalidIdent, Variant};
+use rgb::{
+    AltLayer1, AssetTag, AssignmentType, BlindingFactor, ContractId, GenesisSeal, GraphSeal,
+    Occurrences, Types, WitnessId, XOutputSeal,
+};
+use strict_encoding::{FieldName, InvalidIdent, TypeName, Variant};
 use strict_types::TypeLib;
 
 use super::{
     AssignIface, BuilderError, ContractBuilder, GenesisIface, GlobalIface, Iface, IfaceClass,
     IfaceOp, IssuerClass, Modifier, OwnedIface, Req, RightsAllocation, SchemaIssuer, StateChange,
-    TransitionIface, VerNo, WitnessFilter,
+    TransitionBuilder, TransitionIface, VelocityHint, VerNo, WitnessFilter,
 };
-use crate::containers::Contract;
+use crate::containers::{Batch, BuilderSeal, Contract, TransitionInfo};
 use crate::interface::builder::TxOutpoint;
-use crate::interface::{ContractIface, FungibleAllocation, IfaceId, IfaceWrapper, OutpointFilter};
-use crate::persistence::PersistedState;
+use crate::interface::{
+    ContractError, ContractIface, FungibleAllocation, IfaceId, IfaceWrapper, Ledger,
+    OutpointFilter,
+};
+use crate::persistence::{
+    blank_transition, change_seal, complete_validated, compose_blanks, ComposeError, Inventory,
+    PersistedState, Stash,
+};
 use crate::stl::{
-    rgb_contract_stl, AssetSpec, AssetTerms, Attachment, RicardianContract, StandardTypes,
+    rgb_contract_stl, AssetSpec, AssetTerms, Attachment, BurnMeta, IssueMeta, ProofOfReserves,
+    RicardianContract, StandardTypes,
 };
 use crate::LIB_NAME_RGB_STD;
 
@@ -339,110 +352,99 @@ impl Rgb20 {
         PrimaryIssue::testnet_det::<C>(ticker, name, details, precision, asset_tag)
     }
 
-    pub fn spec(&self) -> AssetSpec {
-        let strict_val = &self
-            .0
-            .global("spec")
-            .expect("RGB20 interface requires global state `spec`")[0];
-        AssetSpec::from_strict_val_unchecked(strict_val)
-    }
+    pub fn spec(&self) -> Result<AssetSpec, ContractError> { self.0.global_typed_first("spec") }
 
-    pub fn balance(&self, filter: impl OutpointFilter) -> Amount {
-        self.allocations(filter)
-            .map(|alloc| alloc.state)
-            .sum::<Amount>()
+    pub fn balance(&self, filter: impl OutpointFilter) -> Result<Amount, ContractError> {
+        Amount::checked_sum(self.allocations(filter)?.map(|alloc| alloc.state))
+            .ok_or(ContractError::AmountOverflow(fname!("assetOwner")))
     }
 
     pub fn allocations<'c>(
         &'c self,
         filter: impl OutpointFilter + 'c,
-    ) -> impl Iterator<Item = FungibleAllocation> + 'c {
-        self.0
-            .fungible("assetOwner", filter)
-            .expect("RGB20 interface requires `assetOwner` state")
+    ) -> Result<impl Iterator<Item = FungibleAllocation> + 'c, ContractError> {
+        self.0.fungible("assetOwner", filter)
     }
 
     pub fn inflation_allowance_allocations<'c>(
         &'c self,
         filter: impl OutpointFilter + 'c,
-    ) -> impl Iterator<Item = FungibleAllocation> + 'c {
-        self.0
-            .fungible("inflationAllowance", filter)
-            .expect("RGB20 interface requires `inflationAllowance` state")
+    ) -> Result<impl Iterator<Item = FungibleAllocation> + 'c, ContractError> {
+        self.0.fungible("inflationAllowance", filter)
     }
 
     pub fn update_right<'c>(
         &'c self,
         filter: impl OutpointFilter + 'c,
-    ) -> impl Iterator<Item = RightsAllocation> + 'c {
-        self.0
-            .rights("updateRight", filter)
-            .expect("RGB20 interface requires `updateRight` state")
+    ) -> Result<impl Iterator<Item = RightsAllocation> + 'c, ContractError> {
+        self.0.rights("updateRight", filter)
     }
 
     pub fn burn_epoch<'c>(
         &'c self,
         filter: impl OutpointFilter + 'c,
-    ) -> impl Iterator<Item = RightsAllocation> + 'c {
-        self.0
-            .rights("burnEpoch", filter)
-            .expect("RGB20 interface requires `burnEpoch` state")
+    ) -> Result<impl Iterator<Item = RightsAllocation> + 'c, ContractError> {
+        self.0.rights("burnEpoch", filter)
     }
 
     pub fn burn_right<'c>(
         &'c self,
         filter: impl OutpointFilter + 'c,
-    ) -> impl Iterator<Item = RightsAllocation> + 'c {
-        self.0
-            .rights("burnRight", filter)
-            .expect("RGB20 interface requires `updateRight` state")
+    ) -> Result<impl Iterator<Item = RightsAllocation> + 'c, ContractError> {
+        self.0.rights("burnRight", filter)
     }
 
-    pub fn contract_terms(&self) -> AssetTerms {
-        let strict_val = &self
-            .0
-            .global("terms")
-            .expect("RGB20 interface requires global `terms`")[0];
-        AssetTerms::from_strict_val_unchecked(strict_val)
+    pub fn contract_terms(&self) -> Result<AssetTerms, ContractError> {
+        self.0.global_typed_first("terms")
     }
 
-    pub fn total_issued_supply(&self) -> Amount {
-        self.0
-            .global("issuedSupply")
-            .expect("RGB20 interface requires global `issuedSupply`")
-            .iter()
-            .map(Amount::from_strict_val_unchecked)
-            .sum()
+    pub fn total_issued_supply(&self) -> Result<Amount, ContractError> {
+        Amount::checked_sum(self.0.global_typed::<Amount>("issuedSupply")?)
+            .ok_or(ContractError::AmountOverflow(fname!("issuedSupply")))
     }
 
-    pub fn total_burned_supply(&self) -> Amount {
-        self.0
-            .global("burnedSupply")
-            .unwrap_or_default()
-            .iter()
-            .map(Amount::from_strict_val_unchecked)
-            .sum()
+    pub fn total_burned_supply(&self) -> Result<Amount, ContractError> {
+        self.optional_supply("burnedSupply")
     }
 
-    pub fn total_replaced_supply(&self) -> Amount {
-        self.0
-            .global("replacedSupply")
-            .unwrap_or_default()
-            .iter()
-            .map(Amount::from_strict_val_unchecked)
-            .sum()
+    pub fn total_replaced_supply(&self) -> Result<Amount, ContractError> {
+        self.optional_supply("replacedSupply")
     }
 
-    pub fn total_supply(&self) -> Amount { self.total_issued_supply() - self.total_burned_supply() }
+    pub fn total_supply(&self) -> Result<Amount, ContractError> {
+        let issued = self.total_issued_supply()?;
+        let burned = self.total_burned_supply()?;
+        issued
+            .checked_sub(burned)
+            .ok_or(ContractError::SupplyMismatch(issued, burned))
+    }
+
+    fn optional_supply(&self, name: &'static str) -> Result<Amount, ContractError> {
+        match self.0.global_typed::<Amount>(name) {
+            Ok(supply) => Amount::checked_sum(supply)
+                .ok_or(ContractError::AmountOverflow(FieldName::from(name))),
+            Err(ContractError::FieldNameUnknown(_)) => Ok(Amount::ZERO),
+            Err(err) => Err(err),
+        }
+    }
 
     pub fn transfer_history(
         &self,
         witness_filter: impl WitnessFilter + Copy,
         outpoint_filter: impl OutpointFilter + Copy,
-    ) -> HashMap<WitnessId, IfaceOp<AmountChange>> {
+    ) -> Result<HashMap<WitnessId, IfaceOp<AmountChange>>, ContractError> {
         self.0
             .fungible_ops("assetOwner", witness_filter, outpoint_filter)
-            .expect("state name is not correct")
+    }
+
+    /// Returns chronologically sorted ledger of the asset transfers performed
+    /// by the wallet owning outputs matching the filter.
+    pub fn ledger(
+        &self,
+        outpoint_filter: impl OutpointFilter + Copy,
+    ) -> Result<Ledger, ContractError> {
+        let precision = self.spec()?.precision;
+        self.0.fungible_ledger("assetOwner", precision, outpoint_filter)
     }
 }
 
@@ -661,11 +663,300 @@ impl PrimaryIssue {
     // TODO: Add secondary issuance and other methods
 }
 
+/// Composition of the RGB20-specific operations over the contract state
+/// known to the inventory.
+#[allow(clippy::result_large_err)]
+pub trait Rgb20Inventory: Inventory {
+    /// Composes a batch with an RGB20 secondary issuance transition, spending
+    /// the inflation allowance found in the provided set of previous outputs.
+    ///
+    /// The newly issued assets are assigned to the beneficiaries; the
+    /// remaining allowance is paid back as change, and the issued amount is
+    /// recorded as `issuedSupply` global state. Other state co-located with
+    /// the spent allowance is moved by a blank transition.
+    #[allow(clippy::too_many_arguments)]
+    fn compose_rgb20_issue(
+        &self,
+        contract_id: ContractId,
+        prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        beneficiaries: impl IntoIterator<Item = (BuilderSeal<GraphSeal>, Amount)>,
+        reserves: impl IntoIterator<Item = ProofOfReserves>,
+        method: CloseMethod,
+        allocator: impl Fn(ContractId, AssignmentType, VelocityHint) -> Option<Vout>,
+    ) -> Result<Batch, ComposeError<Self::Error, <Self::Stash as Stash>::Error>>
+    where
+        Self::Error: From<<Self::Stash as Stash>::Error>,
+    {
+        let meta = IssueMeta {
+            reserves: Confined::try_from_iter(reserves)?,
+        };
+        let op = Rgb20Op::Issue {
+            beneficiaries: beneficiaries.into_iter().collect(),
+            meta,
+        };
+        compose_rgb20(self, contract_id, prev_outputs, op, method, allocator)
+    }
+
+    /// Composes a batch with an RGB20 burn transition, spending a burn right
+    /// found in the provided set of previous outputs.
+    ///
+    /// The burned amount is recorded as `burnedSupply` global state and the
+    /// burn right is paid back as change. Other state co-located with the
+    /// spent burn right is moved by a blank transition.
+    #[allow(clippy::too_many_arguments)]
+    fn compose_rgb20_burn(
+        &self,
+        contract_id: ContractId,
+        prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        amount: Amount,
+        burn_proofs: impl IntoIterator<Item = ProofOfReserves>,
+        method: CloseMethod,
+        allocator: impl Fn(ContractId, AssignmentType, VelocityHint) -> Option<Vout>,
+    ) -> Result<Batch, ComposeError<Self::Error, <Self::Stash as Stash>::Error>>
+    where
+        Self::Error: From<<Self::Stash as Stash>::Error>,
+    {
+        let meta = BurnMeta {
+            burn_proofs: Confined::try_from_iter(burn_proofs)?,
+        };
+        let op = Rgb20Op::Burn { amount, meta };
+        compose_rgb20(self, contract_id, prev_outputs, op, method, allocator)
+    }
+
+    /// Composes a batch with an RGB20 replace transition, spending a burn
+    /// right found in the provided set of previous outputs.
+    ///
+    /// The replacement assets are assigned to the beneficiaries and their
+    /// total is recorded as `replacedSupply` global state, and the burn right
+    /// is paid back as change. Other state co-located with the spent burn
+    /// right is moved by a blank transition.
+    #[allow(clippy::too_many_arguments)]
+    fn compose_rgb20_replace(
+        &self,
+        contract_id: ContractId,
+        prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        beneficiaries: impl IntoIterator<Item = (BuilderSeal<GraphSeal>, Amount)>,
+        burn_proofs: impl IntoIterator<Item = ProofOfReserves>,
+        method: CloseMethod,
+        allocator: impl Fn(ContractId, AssignmentType, VelocityHint) -> Option<Vout>,
+    ) -> Result<Batch, ComposeError<Self::Error, <Self::Stash as Stash>::Error>>
+    where
+        Self::Error: From<<Self::Stash as Stash>::Error>,
+    {
+        let meta = BurnMeta {
+            burn_proofs: Confined::try_from_iter(burn_proofs)?,
+        };
+        let op = Rgb20Op::Replace {
+            beneficiaries: beneficiaries.into_iter().collect(),
+            meta,
+        };
+        compose_rgb20(self, contract_id, prev_outputs, op, method, allocator)
+    }
+}
+
+impl<I: Inventory + ?Sized> Rgb20Inventory for I {}
+
+enum Rgb20Op {
+    Issue {
+        beneficiaries: Vec<(BuilderSeal<GraphSeal>, Amount)>,
+        meta: IssueMeta,
+    },
+    Burn {
+        amount: Amount,
+        meta: BurnMeta,
+    },
+    Replace {
+        beneficiaries: Vec<(BuilderSeal<GraphSeal>, Amount)>,
+        meta: BurnMeta,
+    },
+}
+
+impl Rgb20Op {
+    fn transition_name(&self) -> FieldName {
+        match self {
+            Rgb20Op::Issue { .. } => fname!("issue"),
+            Rgb20Op::Burn { .. } => fname!("burn"),
+            Rgb20Op::Replace { .. } => fname!("replace"),
+        }
+    }
+
+    fn right_name(&self) -> FieldName {
+        match self {
+            Rgb20Op::Issue { .. } => fname!("inflationAllowance"),
+            Rgb20Op::Burn { .. } | Rgb20Op::Replace { .. } => fname!("burnRight"),
+        }
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::result_large_err)]
+fn compose_rgb20<I: Inventory + ?Sized>(
+    inventory: &I,
+    contract_id: ContractId,
+    prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+    op: Rgb20Op,
+    method: CloseMethod,
+    allocator: impl Fn(ContractId, AssignmentType, VelocityHint) -> Option<Vout>,
+) -> Result<Batch, ComposeError<I::Error, <I::Stash as Stash>::Error>>
+where
+    I::Error: From<<I::Stash as Stash>::Error>,
+{
+    let iface: TypeName = tn!("RGB20");
+    let prev_outputs = prev_outputs
+        .into_iter()
+        .map(|o| o.into())
+        .collect::<HashSet<XOutputSeal>>();
+
+    let mut builder =
+        inventory.transition_builder(contract_id, iface.clone(), Some(op.transition_name()))?;
+    let right_name = op.right_name();
+    let right_id = builder
+        .assignments_type(&right_name)
+        .ok_or(BuilderError::InvalidStateField(right_name.clone()))?;
+
+    // 1. Select the rights required for the operation
+    let mut state = inventory.state_for_outpoints(contract_id, prev_outputs.iter().cloned())?;
+    let mut rights = state
+        .keys()
+        .filter(|(opout, _)| opout.ty == right_id)
+        .copied()
+        .collect::<BTreeSet<_>>();
+    if !matches!(op, Rgb20Op::Issue { .. }) {
+        // Burn and replace operations are allowed to spend a single burn right
+        rights = rights.into_iter().take(1).collect();
+    }
+    let rights_outputs = rights
+        .iter()
+        .map(|(_, output)| *output)
+        .collect::<BTreeSet<_>>();
+    let layer1 = rights_outputs
+        .first()
+        .ok_or_else(|| ComposeError::NoRequiredState(right_name.clone()))?
+        .layer1();
+
+    let output_for_assignment = |id: ContractId, assignment_type: AssignmentType| {
+        change_seal(inventory, &allocator, method, layer1, id, assignment_type)
+    };
+
+    // 2. Spend the rights; the rest of the contract state assigned to the
+    //    same outputs is moved with a separate blank transition
+    let mut main_inputs = Vec::<XOutputSeal>::new();
+    let mut allowance = Amount::ZERO;
+    for key in rights {
+        let state = state.remove(&key).expect("selected from the state");
+        let (opout, output) = key;
+        builder = builder.add_input(opout, state.clone())?;
+        main_inputs.push(output);
+        if let PersistedState::Amount(value, _, _) = state {
+            allowance = allowance
+                .checked_add(value)
+                .ok_or(ComposeError::AmountOverflow)?;
+        }
+    }
+    state.retain(|(_, output), _| rights_outputs.contains(output));
+    let colocated = if state.is_empty() {
+        None
+    } else {
+        Some(blank_transition(inventory, contract_id, &iface, state, output_for_assignment)?)
+    };
+
+    // 3. Add operation-specific state
+    let asset_owner: FieldName = fname!("assetOwner");
+    let add_beneficiaries = |builder: TransitionBuilder,
+                             beneficiaries: Vec<(BuilderSeal<GraphSeal>, Amount)>|
+     -> Result<
+        (TransitionBuilder, Amount),
+        ComposeError<I::Error, <I::Stash as Stash>::Error>,
+    > {
+        let mut builder = builder;
+        let mut total = Amount::ZERO;
+        for (seal, amount) in beneficiaries {
+            total = total
+                .checked_add(amount)
+                .ok_or(ComposeError::AmountOverflow)?;
+            builder = builder.add_fungible_state(asset_owner.clone(), seal, amount.value())?;
+        }
+        Ok((builder, total))
+    };
+    let main_builder = match op {
+        Rgb20Op::Issue {
+            beneficiaries,
+            meta,
+        } => {
+            let (builder, issued) = add_beneficiaries(builder, beneficiaries)?;
+            let change = allowance
+                .checked_sub(issued)
+                .ok_or(ComposeError::InsufficientState)?;
+            let mut builder = builder
+                .add_global_state("issuedSupply", issued)?
+                .add_metadata(meta)?;
+            if change > Amount::ZERO {
+                let seal = output_for_assignment(contract_id, right_id)?;
+                builder = builder.add_fungible_state_raw(
+                    right_id,
+                    seal,
+                    change,
+                    BlindingFactor::random(),
+                )?;
+            }
+            builder
+        }
+        Rgb20Op::Burn { amount, meta } => {
+            let seal = output_for_assignment(contract_id, right_id)?;
+            builder
+                .add_global_state("burnedSupply", amount)?
+                .add_metadata(meta)?
+                .add_rights(right_name, seal)?
+        }
+        Rgb20Op::Replace {
+            beneficiaries,
+            meta,
+        } => {
+            let (builder, replaced) = add_beneficiaries(builder, beneficiaries)?;
+            let seal = output_for_assignment(contract_id, right_id)?;
+            builder
+                .add_global_state("replacedSupply", replaced)?
+                .add_metadata(meta)?
+                .add_rights(right_name, seal)?
+        }
+    };
+    let main_transition = complete_validated(inventory, main_builder)?;
+
+    // 4. Prepare blank transitions for the co-located state and the other
+    //    contracts
+    let mut blanks =
+        compose_blanks(inventory, contract_id, &iface, rights_outputs, output_for_assignment)?;
+    if let Some(colocated) = colocated {
+        blanks.push(colocated)?;
+    }
+
+    Ok(Batch {
+        main: TransitionInfo::new(main_transition, main_inputs)?,
+        blanks,
+    })
+}
+
+
 #[cfg(test)]
 mod test {
     use armor::AsciiArmor;
+    use bp::Txid;
+    use rgb::XChain;
 
     use super::*;
+    use crate::fixtures::{
+        consume, genesis_seal, output, rgb20_builder, rgb20_contract, spent_state, stock_with,
+        witness_outputs,
+    };
+    use crate::interface::FilterIncludeAll;
+
+    fn seal(no: u8, blinding: u64) -> XChain<GenesisSeal> {
+        let txid = Txid::from([no; 32]);
+        XChain::Bitcoin(GenesisSeal::with_blinding(Method::TapretFirst, txid, 0, blinding))
+    }
+
+    fn allocator(_: ContractId, _: AssignmentType, _: VelocityHint) -> Option<Vout> {
+        Some(Vout::from_u32(0))
+    }
 
     const RGB20: &str = include_str!("../../tests/data/rgb20.rgba");
 
@@ -692,4 +983,181 @@ mod test {
             panic!("invalid RGB20 interface definition");
         }
     }
+
+    #[test]
+    fn state_accessors() {
+        let contract = rgb20_contract([(1, 600), (2, 400)]);
+        let contract_id = contract.contract_id();
+        let stock = stock_with([contract]);
+        let rgb20 = stock.contract_iface_wrapped::<Rgb20>(contract_id).unwrap();
+
+        assert_eq!(rgb20.balance(FilterIncludeAll).unwrap(), Amount::from(1000u64));
+        assert_eq!(rgb20.allocations(FilterIncludeAll).unwrap().count(), 2);
+        assert_eq!(rgb20.inflation_allowance_allocations(FilterIncludeAll).unwrap().count(), 0);
+        assert_eq!(rgb20.burn_right(FilterIncludeAll).unwrap().count(), 0);
+        // Optional rights not implemented by the schema are reported as errors
+        assert!(matches!(
+            rgb20.update_right(FilterIncludeAll),
+            Err(ContractError::FieldNameUnknown(_))
+        ));
+        assert!(matches!(
+            rgb20.burn_epoch(FilterIncludeAll),
+            Err(ContractError::FieldNameUnknown(_))
+        ));
+        assert_eq!(rgb20.total_supply().unwrap(), Amount::from(1000u64));
+        assert!(rgb20
+            .transfer_history(&FilterIncludeAll, &FilterIncludeAll)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn supply_mismatch() {
+        let contract = rgb20_builder()
+            .add_fungible_state("assetOwner", genesis_seal(1), 100)
+            .unwrap()
+            .add_rights("burnRight", genesis_seal(2))
+            .unwrap()
+            .add_global_state("issuedSupply", Amount::from(100u64))
+            .unwrap()
+            .issue_contract_det(1_700_000_000)
+            .unwrap();
+        let contract_id = contract.contract_id();
+        let mut stock = stock_with([contract]);
+
+        // Schema scripts are absent, so nothing prevents burning more than issued
+        let batch = stock
+            .compose_rgb20_burn(
+                contract_id,
+                [output(2)],
+                Amount::from(150u64),
+                [],
+                CloseMethod::OpretFirst,
+                allocator,
+            )
+            .unwrap();
+        consume(&mut stock, 3, [batch.main.transition]);
+
+        let rgb20 = stock.contract_iface_wrapped::<Rgb20>(contract_id).unwrap();
+        assert_eq!(rgb20.total_burned_supply().unwrap(), Amount::from(150u64));
+        assert_eq!(
+            rgb20.total_supply().unwrap_err(),
+            ContractError::SupplyMismatch(Amount::from(100u64), Amount::from(150u64))
+        );
+    }
+
+    #[test]
+    fn balance_overflow() {
+        let contract = rgb20_builder()
+            .add_fungible_state("assetOwner", genesis_seal(1), u64::MAX)
+            .unwrap()
+            .add_fungible_state("assetOwner", genesis_seal(2), 1u64)
+            .unwrap()
+            .add_global_state("issuedSupply", Amount::from(u64::MAX))
+            .unwrap()
+            .issue_contract_det(1_700_000_000)
+            .unwrap();
+        let contract_id = contract.contract_id();
+        let stock = stock_with([contract]);
+        let rgb20 = stock.contract_iface_wrapped::<Rgb20>(contract_id).unwrap();
+
+        assert_eq!(
+            rgb20.balance(FilterIncludeAll),
+            Err(ContractError::AmountOverflow(fname!("assetOwner")))
+        );
+        assert_eq!(
+            rgb20
+                .0
+                .fungible_ledger("assetOwner", Precision::Indivisible, &FilterIncludeAll)
+                .unwrap_err(),
+            ContractError::AmountOverflow(fname!("assetOwner"))
+        );
+        assert_eq!(rgb20.balance(witness_outputs(1)).unwrap(), Amount::from(u64::MAX));
+    }
+
+    #[test]
+    fn rgb20_burn_colocated() {
+        let contract = rgb20_builder()
+            .add_fungible_state("assetOwner", seal(1, 1), 600)
+            .unwrap()
+            .add_rights("burnRight", seal(1, 2))
+            .unwrap()
+            .add_rights("burnRight", seal(1, 3))
+            .unwrap()
+            .add_global_state("issuedSupply", Amount::from(600u64))
+            .unwrap()
+            .issue_contract_det(1_700_000_000)
+            .unwrap();
+        let contract_id = contract.contract_id();
+        let mut stock = stock_with([contract]);
+
+        let batch = stock
+            .compose_rgb20_burn(
+                contract_id,
+                [output(1)],
+                Amount::from(100u64),
+                [],
+                CloseMethod::OpretFirst,
+                allocator,
+            )
+            .unwrap();
+        assert_eq!(spent_state(&stock, &batch.main), vec![fname!("burnRight")]);
+        assert_eq!(batch.blanks.len(), 1);
+        let mut colocated = spent_state(&stock, &batch.blanks[0]);
+        colocated.sort();
+        assert_eq!(colocated, vec![fname!("assetOwner"), fname!("burnRight")]);
+
+        consume(&mut stock, 3, batch.into_iter().map(|info| info.transition));
+        let rgb20 = stock.contract_iface_wrapped::<Rgb20>(contract_id).unwrap();
+        assert_eq!(rgb20.balance(witness_outputs(3)).unwrap(), Amount::from(600u64));
+        assert_eq!(rgb20.burn_right(witness_outputs(3)).unwrap().count(), 2);
+        assert_eq!(rgb20.total_burned_supply().unwrap(), Amount::from(100u64));
+    }
+
+    #[test]
+    fn rgb20_issue_colocated() {
+        let contract = rgb20_builder()
+            .add_fungible_state("assetOwner", seal(1, 1), 600)
+            .unwrap()
+            .add_fungible_state("inflationAllowance", seal(1, 2), 300)
+            .unwrap()
+            .add_fungible_state("inflationAllowance", seal(2, 3), 200)
+            .unwrap()
+            .add_global_state("issuedSupply", Amount::from(600u64))
+            .unwrap()
+            .issue_contract_det(1_700_000_000)
+            .unwrap();
+        let contract_id = contract.contract_id();
+        let mut stock = stock_with([contract]);
+
+        let beneficiary = GraphSeal::with_blinded_vout(Method::TapretFirst, Vout::from_u32(1), 0);
+        let beneficiary = BuilderSeal::Revealed(XChain::Bitcoin(beneficiary));
+        let batch = stock
+            .compose_rgb20_issue(
+                contract_id,
+                [output(1), output(2)],
+                [(beneficiary, Amount::from(400u64))],
+                [],
+                CloseMethod::OpretFirst,
+                allocator,
+            )
+            .unwrap();
+        assert_eq!(spent_state(&stock, &batch.main), vec![
+            fname!("inflationAllowance"),
+            fname!("inflationAllowance")
+        ]);
+        assert_eq!(batch.blanks.len(), 1);
+        assert_eq!(spent_state(&stock, &batch.blanks[0]), vec![fname!("assetOwner")]);
+
+        consume(&mut stock, 3, batch.into_iter().map(|info| info.transition));
+        let rgb20 = stock.contract_iface_wrapped::<Rgb20>(contract_id).unwrap();
+        assert_eq!(rgb20.balance(witness_outputs(3)).unwrap(), Amount::from(1000u64));
+        let allowance = rgb20
+            .inflation_allowance_allocations(witness_outputs(3))
+            .unwrap()
+            .map(|alloc| alloc.state)
+            .sum::<Amount>();
+        assert_eq!(allowance, Amount::from(100u64));
+        assert_eq!(rgb20.total_issued_supply().unwrap(), Amount::from(1000u64));
+    }
 }
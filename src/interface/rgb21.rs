@@ -21,29 +21,45 @@
 
 #![allow(unused_braces)]
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fmt::{self, Debug, Formatter};
 use std::str::FromStr;
 
 use amplify::ascii::AsciiString;
-use amplify::confinement::{Confined, NonEmptyVec, SmallBlob};
+use amplify::confinement::{Confined, NonEmptyVec, SmallBlob, SmallVec, U16};
+use amplify::Wrapper;
 use bp::bc::stl::bp_tx_stl;
-use rgb::{Occurrences, Types};
+use bp::dbc::Method;
+use bp::seals::txout::CloseMethod;
+use bp::Vout;
+use chrono::Utc;
+use invoice::Precision;
+use rgb::{
+    AltLayer1, AssignmentType, ContractId, DataState, GenesisSeal, Occurrences, Types,
+    XOutputSeal,
+};
 use strict_encoding::stl::AsciiPrintable;
 use strict_encoding::{
-    InvalidIdent, StrictDeserialize, StrictDumb, StrictEncode, StrictSerialize, TypedWrite, Variant,
+    InvalidIdent, StrictDeserialize, StrictDumb, StrictEncode, StrictSerialize, TypeName,
+    TypedWrite, Variant,
 };
 use strict_types::stl::std_stl;
 use strict_types::{CompileError, LibBuilder, StrictVal, TypeLib};
 
 use super::{
-    AssignIface, DataAllocation, GenesisIface, GlobalIface, Iface, IfaceClass, Modifier,
-    OutpointFilter, OwnedIface, Req, TransitionIface, VerNo,
+    AssignIface, BuilderError, ContractBuilder, DataAllocation, GenesisIface, GlobalIface, Iface,
+    IfaceClass, IssuerClass, Modifier, OutpointFilter, OwnedIface, Req, SchemaIssuer,
+    TransitionIface, TxOutpoint, VelocityHint, VerNo,
+};
+use crate::containers::{Batch, Contract, TransitionInfo};
+use crate::interface::{ContractError, ContractIface, IfaceId, IfaceWrapper};
+use crate::persistence::{
+    blank_transition, change_seal, complete_validated, compose_blanks, ComposeError, Inventory,
+    PersistedState, Stash,
 };
-use crate::interface::{ContractIface, IfaceId, IfaceWrapper};
 use crate::stl::{
     rgb_contract_stl, AssetSpec, AssetTerms, Attachment, Details, MediaType, Name, ProofOfReserves,
-    StandardTypes, Ticker,
+    RicardianContract, StandardTypes, Ticker,
 };
 
 pub const LIB_NAME_RGB21: &str = "RGB21";
@@ -65,6 +81,9 @@ pub const LIB_ID_RGB21: &str =
 )]
 pub struct ItemsCount(u32);
 
+impl StrictSerialize for ItemsCount {}
+impl StrictDeserialize for ItemsCount {}
+
 #[derive(
     Wrapper, WrapperMut, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default, From
 )]
@@ -125,6 +144,9 @@ pub struct EngravingData {
     pub content: EmbeddedMedia,
 }
 
+impl StrictSerialize for EngravingData {}
+impl StrictDeserialize for EngravingData {}
+
 impl EngravingData {
     pub fn from_strict_val_unchecked(value: &StrictVal) -> Self {
         let index = TokenIndex(value.unwrap_struct("index").unwrap_num().unwrap_uint());
@@ -175,6 +197,9 @@ pub struct AttachmentType {
     pub name: AttachmentName,
 }
 
+impl StrictSerialize for AttachmentType {}
+impl StrictDeserialize for AttachmentType {}
+
 impl AttachmentType {
     pub fn with(id: u8, name: &'static str) -> AttachmentType {
         AttachmentType {
@@ -339,7 +364,7 @@ fn _rgb21_stl() -> Result<TypeLib, CompileError> {
 }
 
 /// Generates strict type library providing data types for RGB21 interface.
-fn rgb21_stl() -> TypeLib { _rgb21_stl().expect("invalid strict type RGB21 library") }
+pub(crate) fn rgb21_stl() -> TypeLib { _rgb21_stl().expect("invalid strict type RGB21 library") }
 
 fn rgb21() -> Iface {
     let types = StandardTypes::with(rgb21_stl());
@@ -528,53 +553,638 @@ impl IfaceClass for Rgb21 {
 }
 
 impl Rgb21 {
-    pub fn spec(&self) -> AssetSpec {
-        let strict_val = &self
-            .0
-            .global("spec")
-            .expect("RGB21 interface requires global `spec`")[0];
-        AssetSpec::from_strict_val_unchecked(strict_val)
-    }
+    pub fn spec(&self) -> Result<AssetSpec, ContractError> { self.0.global_typed_first("spec") }
 
-    pub fn contract_terms(&self) -> AssetTerms {
-        let strict_val = &self
-            .0
-            .global("terms")
-            .expect("RGB21 interface requires global `terms`")[0];
-        AssetTerms::from_strict_val_unchecked(strict_val)
+    pub fn contract_terms(&self) -> Result<AssetTerms, ContractError> {
+        self.0.global_typed_first("terms")
     }
 
-    pub fn token_data(&self) -> TokenData {
-        let strict_val = &self
-            .0
-            .global("tokens")
-            .expect("RGB21 interface requires global `tokens`")[0];
-        TokenData::from_strict_val_unchecked(strict_val)
+    pub fn token_data(&self) -> Result<TokenData, ContractError> {
+        self.0.global_typed_first("tokens")
     }
 
-    pub fn engarving_data(&self) -> EngravingData {
-        let strict_val = &self
-            .0
-            .global("engravings")
-            .expect("RGB21 interface requires global state `engravings`")[0];
-        EngravingData::from_strict_val_unchecked(strict_val)
+    pub fn engarving_data(&self) -> Result<EngravingData, ContractError> {
+        self.0.global_typed_first("engravings")
     }
 
     pub fn allocations<'c>(
         &'c self,
         filter: impl OutpointFilter + 'c,
-    ) -> impl Iterator<Item = DataAllocation> + 'c {
-        self.0
-            .data("assetOwner", filter)
-            .expect("RGB21 interface requires `assetOwner` state")
+    ) -> Result<impl Iterator<Item = DataAllocation> + 'c, ContractError> {
+        self.0.data("assetOwner", filter)
+    }
+
+    /// Returns data of all tokens defined by the contract, indexed by their
+    /// token index.
+    pub fn tokens(&self) -> Result<BTreeMap<TokenIndex, TokenData>, ContractError> {
+        Ok(self
+            .0
+            .global_typed::<TokenData>("tokens")?
+            .into_iter()
+            .map(|data| (data.index, data))
+            .collect())
+    }
+
+    /// Returns data for a token with a specific index, if the contract
+    /// defines it.
+    pub fn token(&self, index: TokenIndex) -> Result<Option<TokenData>, ContractError> {
+        Ok(self
+            .0
+            .global_typed::<TokenData>("tokens")?
+            .into_iter()
+            .find(|data| data.index == index))
+    }
+
+    /// Returns all engravings applied to the contract tokens, which are
+    /// always empty if the contract schema doesn't support engravings.
+    pub fn engravings(&self) -> Result<SmallVec<EngravingData>, ContractError> {
+        self.optional_global("engravings")
     }
+
+    /// Returns engravings applied to a token with a specific index.
+    pub fn token_engravings(
+        &self,
+        index: TokenIndex,
+    ) -> Result<Vec<EngravingData>, ContractError> {
+        Ok(self
+            .engravings()?
+            .into_iter()
+            .filter(|engraving| engraving.applied_to == index)
+            .collect())
+    }
+
+    /// Returns names of attachment types defined by the contract, indexed by
+    /// the attachment type id. The types are always empty if the contract
+    /// schema doesn't support attachment types.
+    pub fn attachment_types(&self) -> Result<BTreeMap<u8, AttachmentName>, ContractError> {
+        Ok(self
+            .optional_global::<AttachmentType>("attachmentTypes")?
+            .into_iter()
+            .map(|ty| (ty.id, ty.name))
+            .collect())
+    }
+
+    fn optional_global<T: StrictDeserialize>(
+        &self,
+        name: &'static str,
+    ) -> Result<SmallVec<T>, ContractError> {
+        match self.0.global_typed::<T>(name) {
+            Ok(state) => Ok(state),
+            Err(ContractError::FieldNameUnknown(_)) => Ok(none!()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Resolves attachment type id, used as a key in
+    /// [`TokenData::attachments`], into the attachment type name.
+    pub fn attachment_name(&self, id: u8) -> Result<Option<AttachmentName>, ContractError> {
+        Ok(self.attachment_types()?.remove(&id))
+    }
+
+    /// Returns owned token allocations grouped by the token index.
+    pub fn token_allocations(
+        &self,
+        filter: impl OutpointFilter,
+    ) -> Result<BTreeMap<TokenIndex, Vec<(DataAllocation, OwnedFraction)>>, ContractError> {
+        let mut tokens = BTreeMap::<_, Vec<_>>::new();
+        for (alloc, allocation) in self.typed_allocations(filter)? {
+            tokens
+                .entry(allocation.token_index())
+                .or_default()
+                .push((alloc, allocation.fraction()));
+        }
+        Ok(tokens)
+    }
+
+    /// Returns total owned fraction for each of the tokens.
+    pub fn owned_fractions(
+        &self,
+        filter: impl OutpointFilter,
+    ) -> Result<BTreeMap<TokenIndex, OwnedFraction>, ContractError> {
+        Ok(self
+            .token_allocations(filter)?
+            .into_iter()
+            .map(|(index, allocs)| {
+                let fraction = allocs
+                    .iter()
+                    .map(|(_, fraction)| fraction.to_inner())
+                    .fold(0u64, u64::saturating_add);
+                (index, OwnedFraction::from(fraction))
+            })
+            .collect())
+    }
+
+    /// Returns owned token allocations together with their decoded token
+    /// index and owned fraction.
+    pub fn typed_allocations(
+        &self,
+        filter: impl OutpointFilter,
+    ) -> Result<Vec<(DataAllocation, Allocation)>, ContractError> {
+        self.0.data_typed("assetOwner", filter)
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum IssuerError {
+    /// contract genesis doesn't support allocating to liquid seals; request
+    /// liquid support first.
+    NoLiquidSupport,
+
+    /// token with index {0} is already defined.
+    TokenExists(TokenIndex),
+
+    /// token with index {0} is not defined; add token data before allocating
+    /// it.
+    TokenUnknown(TokenIndex),
+
+    /// token {0} must have a non-zero fractional supply.
+    ZeroSupply(TokenIndex),
+
+    /// zero fraction of token {0} can't be allocated.
+    ZeroFraction(TokenIndex),
+
+    /// allocations of token {0} exceed its fractional supply.
+    FractionOverflow(TokenIndex),
+
+    /// allocations of token {index} sum up to {allocated} fractions, while the
+    /// token supply is {supply} fractions.
+    FractionMismatch {
+        index: TokenIndex,
+        allocated: u64,
+        supply: u64,
+    },
+
+    /// attachment type {0} is already defined.
+    AttachmentTypeExists(u8),
+
+    /// token {0} uses attachment type {1} which is not defined.
+    AttachmentTypeUnknown(TokenIndex, u8),
+
+    #[from]
+    #[display(inner)]
+    InvalidIdent(InvalidIdent),
+
+    #[from]
+    #[display(inner)]
+    Builder(BuilderError),
 }
 
+/// Builder for issuing RGB21 collections of (possibly fractional) tokens.
+#[derive(Clone, Debug)]
+pub struct Issue {
+    builder: ContractBuilder,
+    terms: AssetTerms,
+    tokens: BTreeMap<TokenIndex, (TokenData, u64)>,
+    allocated: BTreeMap<TokenIndex, u64>,
+    attachment_types: BTreeMap<u8, AttachmentType>,
+    deterministic: bool,
+}
+
+#[allow(clippy::result_large_err)]
+impl Issue {
+    fn testnet_int(
+        issuer: SchemaIssuer<Rgb21>,
+        ticker: &str,
+        name: &str,
+        details: Option<&str>,
+        precision: Precision,
+    ) -> Result<Self, InvalidIdent> {
+        let spec = AssetSpec::with(ticker, name, precision, details)?;
+        let terms = AssetTerms {
+            text: RicardianContract::default(),
+            media: None,
+        };
+
+        let (schema, main_iface_impl) = issuer.into_split();
+        let builder = ContractBuilder::testnet(rgb21(), schema, main_iface_impl)
+            .expect("schema interface mismatch")
+            .add_global_state("spec", spec)
+            .expect("invalid RGB21 schema (token specification mismatch)");
+
+        Ok(Self {
+            builder,
+            terms,
+            tokens: none!(),
+            allocated: none!(),
+            attachment_types: none!(),
+            deterministic: false,
+        })
+    }
+
+    pub fn testnet<C: IssuerClass<IssuingIface = Rgb21>>(
+        ticker: &str,
+        name: &str,
+        details: Option<&str>,
+        precision: Precision,
+    ) -> Result<Self, InvalidIdent> {
+        Self::testnet_int(C::issuer(), ticker, name, details, precision)
+    }
+
+    pub fn testnet_with(
+        issuer: SchemaIssuer<Rgb21>,
+        ticker: &str,
+        name: &str,
+        details: Option<&str>,
+        precision: Precision,
+    ) -> Result<Self, InvalidIdent> {
+        Self::testnet_int(issuer, ticker, name, details, precision)
+    }
+
+    pub fn testnet_det<C: IssuerClass<IssuingIface = Rgb21>>(
+        ticker: &str,
+        name: &str,
+        details: Option<&str>,
+        precision: Precision,
+    ) -> Result<Self, InvalidIdent> {
+        let mut me = Self::testnet_int(C::issuer(), ticker, name, details, precision)?;
+        me.deterministic = true;
+        Ok(me)
+    }
+
+    pub fn support_liquid(mut self) -> Self {
+        self.builder = self
+            .builder
+            .add_layer1(AltLayer1::Liquid)
+            .expect("only one layer1 can be added");
+        self
+    }
+
+    pub fn add_terms(
+        mut self,
+        contract: &str,
+        media: Option<Attachment>,
+    ) -> Result<Self, InvalidIdent> {
+        let terms = RicardianContract::from_str(contract)?;
+        self.terms = AssetTerms { text: terms, media };
+        Ok(self)
+    }
+
+    /// Defines a new attachment type which may be used by the token
+    /// attachments.
+    pub fn add_attachment_type(mut self, id: u8, name: &str) -> Result<Self, IssuerError> {
+        if self.attachment_types.contains_key(&id) {
+            return Err(IssuerError::AttachmentTypeExists(id));
+        }
+        let name = AttachmentName::try_from(name.to_owned())?;
+        self.attachment_types
+            .insert(id, AttachmentType { id, name });
+        Ok(self)
+    }
+
+    /// Adds a token to the collection, which may be split into `fractions`
+    /// parts. Non-fractional tokens must have a supply of a single fraction.
+    pub fn add_token(mut self, token: TokenData, fractions: u64) -> Result<Self, IssuerError> {
+        let index = token.index;
+        if self.tokens.contains_key(&index) {
+            return Err(IssuerError::TokenExists(index));
+        }
+        if fractions == 0 {
+            return Err(IssuerError::ZeroSupply(index));
+        }
+        self.tokens.insert(index, (token, fractions));
+        Ok(self)
+    }
+
+    fn register_allocation(&mut self, allocation: Allocation) -> Result<(), IssuerError> {
+        let index = allocation.token_index();
+        let fraction = allocation.fraction().to_inner();
+        let (_, supply) = self
+            .tokens
+            .get(&index)
+            .ok_or(IssuerError::TokenUnknown(index))?;
+        if fraction == 0 {
+            return Err(IssuerError::ZeroFraction(index));
+        }
+        let allocated = self.allocated.entry(index).or_default();
+        match allocated.checked_add(fraction) {
+            Some(sum) if sum <= *supply => *allocated = sum,
+            _ => return Err(IssuerError::FractionOverflow(index)),
+        }
+        Ok(())
+    }
+
+    pub fn allocate<O: TxOutpoint>(
+        mut self,
+        method: Method,
+        beneficiary: O,
+        allocation: Allocation,
+    ) -> Result<Self, IssuerError> {
+        debug_assert!(
+            !self.deterministic,
+            "for creating deterministic contracts please use allocate_det method"
+        );
+
+        self.register_allocation(allocation)?;
+        let beneficiary = beneficiary.map_to_xchain(|outpoint| {
+            GenesisSeal::new_random(method, outpoint.txid, outpoint.vout)
+        });
+        self.builder = self
+            .builder
+            .add_data("assetOwner", beneficiary, allocation)
+            .map_err(IssuerError::from_builder)?;
+        Ok(self)
+    }
+
+    /// Add token allocation in a deterministic way.
+    pub fn allocate_det<O: TxOutpoint>(
+        mut self,
+        method: Method,
+        beneficiary: O,
+        seal_blinding: u64,
+        allocation: Allocation,
+        salt: u128,
+    ) -> Result<Self, IssuerError> {
+        debug_assert!(
+            self.deterministic,
+            "to add token allocation in deterministic way the contract builder has to be created \
+             using `*_det` constructor"
+        );
+
+        self.register_allocation(allocation)?;
+        let beneficiary = beneficiary.map_to_xchain(|outpoint| {
+            GenesisSeal::with_blinding(method, outpoint.txid, outpoint.vout, seal_blinding)
+        });
+        let state = DataState::from(
+            allocation
+                .to_strict_serialized::<U16>()
+                .expect("allocation data are small"),
+        );
+        self.builder = self
+            .builder
+            .add_owned_state_det("assetOwner", beneficiary, PersistedState::Data(state, salt))
+            .map_err(IssuerError::from_builder)?;
+        Ok(self)
+    }
+
+    /// Allows secondary issuance of up to `count` additional tokens.
+    pub fn add_inflation_allowance<O: TxOutpoint>(
+        mut self,
+        method: Method,
+        beneficiary: O,
+        count: ItemsCount,
+    ) -> Result<Self, IssuerError> {
+        debug_assert!(
+            !self.deterministic,
+            "for creating deterministic contracts please use add_inflation_allowance_det method"
+        );
+
+        let beneficiary = beneficiary.map_to_xchain(|outpoint| {
+            GenesisSeal::new_random(method, outpoint.txid, outpoint.vout)
+        });
+        self.builder = self
+            .builder
+            .add_data("inflationAllowance", beneficiary, count)
+            .map_err(IssuerError::from_builder)?;
+        Ok(self)
+    }
+
+    /// Allows secondary issuance of up to `count` additional tokens in a
+    /// deterministic way.
+    pub fn add_inflation_allowance_det<O: TxOutpoint>(
+        mut self,
+        method: Method,
+        beneficiary: O,
+        seal_blinding: u64,
+        count: ItemsCount,
+        salt: u128,
+    ) -> Result<Self, IssuerError> {
+        debug_assert!(
+            self.deterministic,
+            "to add inflation allowance in deterministic way the contract builder has to be \
+             created using `*_det` constructor"
+        );
+
+        let beneficiary = beneficiary.map_to_xchain(|outpoint| {
+            GenesisSeal::with_blinding(method, outpoint.txid, outpoint.vout, seal_blinding)
+        });
+        let state =
+            DataState::from(count.to_strict_serialized::<U16>().expect("count data are small"));
+        let state = PersistedState::Data(state, salt);
+        self.builder = self
+            .builder
+            .add_owned_state_det("inflationAllowance", beneficiary, state)
+            .map_err(IssuerError::from_builder)?;
+        Ok(self)
+    }
+
+    pub fn issue_contract(self) -> Result<Contract, IssuerError> {
+        debug_assert!(
+            !self.deterministic,
+            "to add token allocation in deterministic way you must use issue_contract_det method"
+        );
+        self.issue_contract_int(Utc::now().timestamp())
+    }
+
+    pub fn issue_contract_det(self, timestamp: i64) -> Result<Contract, IssuerError> {
+        debug_assert!(
+            self.deterministic,
+            "to add token allocation in deterministic way the contract builder has to be created \
+             using `*_det` constructor"
+        );
+        self.issue_contract_int(timestamp)
+    }
+
+    fn issue_contract_int(self, timestamp: i64) -> Result<Contract, IssuerError> {
+        for (index, (token, supply)) in &self.tokens {
+            let allocated = self.allocated.get(index).copied().unwrap_or_default();
+            if allocated != *supply {
+                return Err(IssuerError::FractionMismatch {
+                    index: *index,
+                    allocated,
+                    supply: *supply,
+                });
+            }
+            if let Some(id) = token
+                .attachments
+                .keys()
+                .find(|id| !self.attachment_types.contains_key(id))
+            {
+                return Err(IssuerError::AttachmentTypeUnknown(*index, *id));
+            }
+        }
+
+        let mut builder = self
+            .builder
+            .add_global_state("terms", self.terms)
+            .expect("invalid RGB21 schema (contract terms mismatch)");
+        for (token, _) in self.tokens.into_values() {
+            builder = builder.add_global_state("tokens", token)?;
+        }
+        for attachment_type in self.attachment_types.into_values() {
+            builder = builder.add_global_state("attachmentTypes", attachment_type)?;
+        }
+        Ok(builder.issue_contract_det(timestamp)?)
+    }
+}
+
+impl IssuerError {
+    fn from_builder(err: BuilderError) -> Self {
+        match err {
+            BuilderError::InvalidLayer1(_) => IssuerError::NoLiquidSupport,
+            err => IssuerError::Builder(err),
+        }
+    }
+}
+
+/// Composition of the RGB21-specific operations over the contract state
+/// known to the inventory.
+#[allow(clippy::result_large_err)]
+pub trait Rgb21Inventory: Inventory {
+    /// Composes a batch with an RGB21 engrave transition, adding engraving
+    /// to the token with the provided index.
+    ///
+    /// Only the outputs from the provided set which own a non-zero fraction
+    /// of the token are spent. Token allocations on them are carried forward
+    /// to new seals, and the other state of the contract is moved by a blank
+    /// transition.
+    fn compose_rgb21_engrave(
+        &self,
+        contract_id: ContractId,
+        prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        index: TokenIndex,
+        content: EmbeddedMedia,
+        method: CloseMethod,
+        allocator: impl Fn(ContractId, AssignmentType, VelocityHint) -> Option<Vout>,
+    ) -> Result<Batch, ComposeError<Self::Error, <Self::Stash as Stash>::Error>>
+    where
+        Self::Error: From<<Self::Stash as Stash>::Error>,
+    {
+        let iface: TypeName = tn!("RGB21");
+        let prev_outputs = prev_outputs
+            .into_iter()
+            .map(|o| o.into())
+            .collect::<HashSet<XOutputSeal>>();
+
+        let mut main_builder =
+            self.transition_builder(contract_id, iface.clone(), Some("engrave"))?;
+        let owner_name = fname!("assetOwner");
+        let owner_id = main_builder
+            .assignments_type(&owner_name)
+            .ok_or(BuilderError::InvalidStateField(owner_name))?;
+
+        // 1. Select outputs owning a fraction of the engraved token
+        let state = self.state_for_outpoints(contract_id, prev_outputs.iter().cloned())?;
+        let token_outputs = state
+            .iter()
+            .filter(|((opout, _), state)| {
+                let PersistedState::Data(data, _) = state else {
+                    return false;
+                };
+                opout.ty == owner_id &&
+                    Allocation::from_strict_serialized::<U16>(data.to_inner())
+                        .map(|alloc| {
+                            alloc.token_index() == index && alloc.fraction().to_inner() > 0
+                        })
+                        .unwrap_or_default()
+            })
+            .map(|((_, output), _)| *output)
+            .collect::<BTreeSet<_>>();
+        let layer1 = token_outputs
+            .first()
+            .ok_or(ComposeError::TokenNotOwned(index))?
+            .layer1();
+
+        let output_for_assignment = |id: ContractId, assignment_type: AssignmentType| {
+            change_seal(self, &allocator, method, layer1, id, assignment_type)
+        };
+
+        // 2. Carry the owned fractions forward; the rest of the contract state
+        //    assigned to the same outputs is moved with a blank transition
+        let mut main_inputs = Vec::<XOutputSeal>::new();
+        let mut colocated = BTreeMap::new();
+        for ((opout, output), state) in state {
+            if !token_outputs.contains(&output) {
+                continue;
+            }
+            if opout.ty != owner_id {
+                colocated.insert((opout, output), state);
+                continue;
+            }
+            main_builder = main_builder.add_input(opout, state.clone())?;
+            main_inputs.push(output);
+            // Token allocations are carried over with fresh salts, such that
+            // they can't be linked to the spent state
+            let state = match state {
+                PersistedState::Data(value, _) => PersistedState::Data(value, rand::random()),
+                state => state,
+            };
+            let seal = output_for_assignment(contract_id, opout.ty)?;
+            main_builder = main_builder.add_owned_state_raw(opout.ty, seal, state)?;
+        }
+        let engraving = EngravingData {
+            applied_to: index,
+            content,
+        };
+        let main_builder = main_builder.add_global_state("engravings", engraving)?;
+        let main_transition = complete_validated(self, main_builder)?;
+
+        // 3. Prepare blank transitions for the co-located state and the other
+        //    contracts
+        let mut blanks =
+            compose_blanks(self, contract_id, &iface, token_outputs, output_for_assignment)?;
+        if !colocated.is_empty() {
+            let colocated =
+                blank_transition(self, contract_id, &iface, colocated, output_for_assignment)?;
+            blanks.push(colocated)?;
+        }
+
+        Ok(Batch {
+            main: TransitionInfo::new(main_transition, main_inputs)?,
+            blanks,
+        })
+    }
+}
+
+impl<I: Inventory + ?Sized> Rgb21Inventory for I {}
+
 #[cfg(test)]
 mod test {
     use armor::AsciiArmor;
+    use bp::{Outpoint, Txid};
+    use rgb::{XChain, XOutpoint};
 
     use super::*;
+    use crate::fixtures::{
+        consume, output, rgb21_builder, rgb21_contract, spent_state, stock_with, witness_outputs,
+        MinimalRgb21, TestRgb21,
+    };
+    use crate::interface::FilterIncludeAll;
+
+    fn seal(no: u8, blinding: u64) -> XChain<GenesisSeal> {
+        let txid = Txid::from([no; 32]);
+        XChain::Bitcoin(GenesisSeal::with_blinding(Method::TapretFirst, txid, 0, blinding))
+    }
+
+    fn allocator(_: ContractId, _: AssignmentType, _: VelocityHint) -> Option<Vout> {
+        Some(Vout::from_u32(0))
+    }
+
+    fn outpoint(no: u8) -> Outpoint { Outpoint::new(Txid::from([no; 32]), 0) }
+
+    fn token(index: u32, attachment_type: Option<u8>) -> TokenData {
+        let attachments = attachment_type.map(|ty| {
+            let attachment = Attachment {
+                ty: MediaType::with("image/png"),
+                digest: [ty; 32],
+            };
+            (ty, attachment)
+        });
+        TokenData {
+            index: TokenIndex::from(index),
+            attachments: Confined::from_iter_unsafe(attachments),
+            ..default!()
+        }
+    }
+
+    fn allocation(index: u32, fraction: u64) -> Allocation {
+        Allocation::with(TokenIndex::from(index), OwnedFraction::from(fraction))
+    }
+
+    fn issue() -> Issue {
+        Issue::testnet_det::<TestRgb21>("NFT", "Test collection", None, Precision::Indivisible)
+            .unwrap()
+    }
 
     const RGB21: &str = include_str!("../../tests/data/rgb21.rgba");
 
@@ -607,4 +1217,168 @@ mod test {
             panic!("invalid RGB21 interface definition");
         }
     }
+
+    #[test]
+    fn optional_globals() {
+        let allocation = Allocation::with(TokenIndex::from(1), OwnedFraction::from(1));
+        let full = rgb21_contract::<TestRgb21>([1], [(1, allocation)]);
+        let minimal = rgb21_contract::<MinimalRgb21>([1], [(1, allocation)]);
+        let ids = [full.contract_id(), minimal.contract_id()];
+        let stock = stock_with([full, minimal]);
+
+        for id in ids {
+            let rgb21 = stock.contract_iface_wrapped::<Rgb21>(id).unwrap();
+            assert_eq!(rgb21.tokens().unwrap().len(), 1);
+            assert!(rgb21.engravings().unwrap().is_empty());
+            assert!(rgb21.token_engravings(TokenIndex::from(1)).unwrap().is_empty());
+            assert!(rgb21.attachment_types().unwrap().is_empty());
+            assert_eq!(rgb21.attachment_name(0).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn issue_collection() {
+        let contract = issue()
+            .add_attachment_type(7, "preview")
+            .unwrap()
+            .add_token(token(1, Some(7)), 4)
+            .unwrap()
+            .add_token(token(2, None), 1)
+            .unwrap()
+            .allocate_det(Method::TapretFirst, outpoint(1), 1, allocation(1, 3), 1)
+            .unwrap()
+            .allocate_det(Method::TapretFirst, outpoint(2), 2, allocation(1, 1), 2)
+            .unwrap()
+            .allocate_det(Method::TapretFirst, outpoint(2), 3, allocation(2, 1), 3)
+            .unwrap()
+            .issue_contract_det(1_700_000_000)
+            .unwrap();
+        let contract_id = contract.contract_id();
+        let stock = stock_with([contract]);
+        let rgb21 = stock.contract_iface_wrapped::<Rgb21>(contract_id).unwrap();
+
+        assert_eq!(rgb21.tokens().unwrap().len(), 2);
+        assert_eq!(rgb21.owned_fractions(&FilterIncludeAll).unwrap(), bmap! {
+            TokenIndex::from(1) => OwnedFraction::from(4),
+            TokenIndex::from(2) => OwnedFraction::from(1),
+        });
+        assert_eq!(rgb21.attachment_name(7).unwrap(), Some(AttachmentName::from("preview")));
+    }
+
+    #[test]
+    fn issuer_errors() {
+        let err = issue()
+            .add_attachment_type(1, "preview")
+            .and_then(|issue| issue.add_attachment_type(1, "other"))
+            .unwrap_err();
+        assert_eq!(err, IssuerError::AttachmentTypeExists(1));
+        let err = issue()
+            .add_token(token(1, None), 1)
+            .and_then(|issue| issue.add_token(token(1, None), 2))
+            .unwrap_err();
+        assert_eq!(err, IssuerError::TokenExists(TokenIndex::from(1)));
+        let err = issue().add_token(token(1, None), 0).unwrap_err();
+        assert_eq!(err, IssuerError::ZeroSupply(TokenIndex::from(1)));
+
+        let issue = issue().add_token(token(1, None), 2).unwrap();
+        let allocate = |issue: &Issue, allocation| {
+            issue
+                .clone()
+                .allocate_det(Method::TapretFirst, outpoint(1), 1, allocation, 1)
+        };
+        let err = allocate(&issue, allocation(2, 1)).unwrap_err();
+        assert_eq!(err, IssuerError::TokenUnknown(TokenIndex::from(2)));
+        let err = allocate(&issue, allocation(1, 0)).unwrap_err();
+        assert_eq!(err, IssuerError::ZeroFraction(TokenIndex::from(1)));
+        let err = allocate(&issue, allocation(1, 3)).unwrap_err();
+        assert_eq!(err, IssuerError::FractionOverflow(TokenIndex::from(1)));
+        let liquid = XOutpoint::from(XChain::Liquid(outpoint(1)));
+        let err = issue
+            .clone()
+            .allocate_det(Method::TapretFirst, liquid, 1, allocation(1, 1), 1)
+            .unwrap_err();
+        assert_eq!(err, IssuerError::NoLiquidSupport);
+        let err = allocate(&issue, allocation(1, 1))
+            .and_then(|issue| issue.issue_contract_det(1_700_000_000))
+            .unwrap_err();
+        assert_eq!(err, IssuerError::FractionMismatch {
+            index: TokenIndex::from(1),
+            allocated: 1,
+            supply: 2,
+        });
+
+        let issue = self::issue().add_token(token(1, Some(7)), 1).unwrap();
+        let err = allocate(&issue, allocation(1, 1))
+            .and_then(|issue| issue.issue_contract_det(1_700_000_000))
+            .unwrap_err();
+        assert_eq!(err, IssuerError::AttachmentTypeUnknown(TokenIndex::from(1), 7));
+    }
+
+    #[test]
+    fn rgb21_engrave_colocated() {
+        let contract = rgb21_builder::<TestRgb21>([1, 2])
+            .add_data("assetOwner", seal(1, 1), allocation(1, 1))
+            .unwrap()
+            .add_data("assetOwner", seal(1, 2), allocation(2, 1))
+            .unwrap()
+            .add_data("inflationAllowance", seal(1, 3), ItemsCount::from(5))
+            .unwrap()
+            .add_data("assetOwner", seal(2, 4), allocation(3, 1))
+            .unwrap()
+            .issue_contract_det(1_700_000_000)
+            .unwrap();
+        let contract_id = contract.contract_id();
+        let mut stock = stock_with([contract]);
+
+        let content = EmbeddedMedia {
+            ty: MediaType::with("text/plain"),
+            data: SmallBlob::try_from(b"engraved".to_vec()).unwrap(),
+        };
+        let spent_salts = stock
+            .state_for_outpoints(contract_id, [output(1), output(2)])
+            .unwrap()
+            .into_values()
+            .filter_map(|state| match state {
+                PersistedState::Data(_, salt) => Some(salt),
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>();
+        let batch = stock
+            .compose_rgb21_engrave(
+                contract_id,
+                [output(1), output(2)],
+                TokenIndex::from(1),
+                content,
+                CloseMethod::OpretFirst,
+                allocator,
+            )
+            .unwrap();
+        assert_eq!(spent_state(&stock, &batch.main), vec![
+            fname!("assetOwner"),
+            fname!("assetOwner")
+        ]);
+        assert_eq!(batch.blanks.len(), 1);
+        assert_eq!(spent_state(&stock, &batch.blanks[0]), vec![fname!("inflationAllowance")]);
+        // Carried over allocations can't be linked to the spent ones
+        let salts = batch
+            .main
+            .transition
+            .assignments
+            .values()
+            .flat_map(|assigns| assigns.as_structured())
+            .filter_map(|assign| assign.as_revealed_state())
+            .map(|data| data.salt)
+            .collect::<BTreeSet<_>>();
+        assert_eq!(salts.len(), 2);
+        assert!(salts.is_disjoint(&spent_salts));
+
+        consume(&mut stock, 3, batch.into_iter().map(|info| info.transition));
+        let rgb21 = stock.contract_iface_wrapped::<Rgb21>(contract_id).unwrap();
+        assert_eq!(rgb21.token_engravings(TokenIndex::from(1)).unwrap().len(), 1);
+        let owned = rgb21.owned_fractions(witness_outputs(3)).unwrap();
+        assert_eq!(owned.keys().copied().collect::<Vec<_>>(), vec![
+            TokenIndex::from(1),
+            TokenIndex::from(2)
+        ]);
+    }
 }
@@ -18,3 +18,226 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+
+use rgb::{Occurrences, Types};
+use strict_encoding::Variant;
+use strict_types::TypeLib;
+
+use super::{
+    AssignIface, GenesisIface, GlobalIface, Iface, IfaceClass, Modifier, OwnedIface, Req,
+    TransitionIface, VerNo,
+};
+use crate::interface::{
+    ContractError, ContractIface, IfaceId, IfaceWrapper, OutpointFilter, RightsAllocation,
+};
+use crate::stl::{rgb_records_stl, Details, IdentityKey, Name, StandardTypes};
+
+pub const LIB_NAME_RGB22: &str = "RGB22";
+
+const UNKNOWN_KEY: u8 = 1;
+const NO_KEYS: u8 = 2;
+
+fn rgb22() -> Iface {
+    let types = StandardTypes::with(rgb_records_stl());
+
+    Iface {
+        version: VerNo::V1,
+        name: tn!("RGB22"),
+        inherits: none!(),
+        global_state: tiny_bmap! {
+            fname!("name") => GlobalIface::required(types.get("RGBContract.Name")),
+            fname!("details") => GlobalIface::optional(types.get("RGBContract.Details")),
+            fname!("keys") => GlobalIface::one_or_many(types.get("RGBRecords.IdentityKey")),
+            fname!("revokedKeys") => GlobalIface::none_or_many(types.get("RGBRecords.IdentityKey")),
+        },
+        assignments: tiny_bmap! {
+            fname!("controller") => AssignIface::public(OwnedIface::Rights, Req::Required),
+        },
+        valencies: none!(),
+        genesis: GenesisIface {
+            modifier: Modifier::Final,
+            metadata: None,
+            globals: tiny_bmap! {
+                fname!("name") => Occurrences::Once,
+                fname!("details") => Occurrences::NoneOrOnce,
+                fname!("keys") => Occurrences::OnceOrMore,
+            },
+            assignments: tiny_bmap! {
+                fname!("controller") => Occurrences::Once,
+            },
+            valencies: none!(),
+            errors: none!(),
+        },
+        transitions: tiny_bmap! {
+            fname!("transfer") => TransitionIface {
+                modifier: Modifier::Final,
+                optional: false,
+                metadata: None,
+                globals: none!(),
+                inputs: tiny_bmap! {
+                    fname!("controller") => Occurrences::Once,
+                },
+                assignments: tiny_bmap! {
+                    fname!("controller") => Occurrences::Once,
+                },
+                valencies: none!(),
+                errors: none!(),
+                default_assignment: Some(fname!("controller")),
+            },
+            fname!("rotate") => TransitionIface {
+                modifier: Modifier::Final,
+                optional: false,
+                metadata: None,
+                globals: tiny_bmap! {
+                    fname!("keys") => Occurrences::OnceOrMore,
+                    fname!("revokedKeys") => Occurrences::NoneOrMore,
+                },
+                inputs: tiny_bmap! {
+                    fname!("controller") => Occurrences::Once,
+                },
+                assignments: tiny_bmap! {
+                    fname!("controller") => Occurrences::Once,
+                },
+                valencies: none!(),
+                errors: tiny_bset! {
+                    UNKNOWN_KEY
+                },
+                default_assignment: Some(fname!("controller")),
+            },
+            fname!("revoke") => TransitionIface {
+                modifier: Modifier::Final,
+                optional: true,
+                metadata: None,
+                globals: tiny_bmap! {
+                    fname!("revokedKeys") => Occurrences::OnceOrMore,
+                },
+                inputs: tiny_bmap! {
+                    fname!("controller") => Occurrences::Once,
+                },
+                assignments: tiny_bmap! {
+                    fname!("controller") => Occurrences::NoneOrOnce,
+                },
+                valencies: none!(),
+                errors: tiny_bset! {
+                    UNKNOWN_KEY,
+                    NO_KEYS
+                },
+                default_assignment: None,
+            },
+        },
+        extensions: none!(),
+        errors: tiny_bmap! {
+            Variant::named(UNKNOWN_KEY, vname!("unknownKey"))
+                => tiny_s!("the revoked key was never assigned to the identity"),
+
+            Variant::named(NO_KEYS, vname!("noKeys"))
+                => tiny_s!("the operation revokes all keys while keeping the identity controlled"),
+        },
+        default_operation: Some(fname!("transfer")),
+        types: Types::Strict(types.type_system()),
+    }
+}
+
+#[derive(Wrapper, WrapperMut, Clone, Eq, PartialEq, Debug)]
+#[wrapper(Deref)]
+#[wrapper_mut(DerefMut)]
+pub struct Rgb22(ContractIface);
+
+impl From<ContractIface> for Rgb22 {
+    fn from(iface: ContractIface) -> Self {
+        if iface.iface.iface_id != Rgb22::IFACE_ID {
+            panic!("the provided interface is not RGB22 interface");
+        }
+        Self(iface)
+    }
+}
+
+impl IfaceWrapper for Rgb22 {
+    const IFACE_NAME: &'static str = LIB_NAME_RGB22;
+    const IFACE_ID: IfaceId = IfaceId::from_array([
+        0xe8, 0x1e, 0x26, 0x3c, 0xf2, 0xef, 0x1c, 0xa8, 0x5c, 0x3b, 0xab, 0xf8, 0xeb, 0xda, 0x8a,
+        0x0f, 0xe8, 0x4e, 0x3c, 0x60, 0x40, 0xa1, 0x9b, 0x7c, 0x69, 0xc5, 0xb4, 0xcc, 0x74, 0xf9,
+        0x35, 0xea,
+    ]);
+}
+
+impl IfaceClass for Rgb22 {
+    fn iface() -> Iface { rgb22() }
+    fn stl() -> TypeLib { rgb_records_stl() }
+}
+
+impl Rgb22 {
+    pub fn name(&self) -> Result<Name, ContractError> { self.0.global_typed_first("name") }
+
+    pub fn details(&self) -> Result<Option<Details>, ContractError> {
+        Ok(self
+            .0
+            .global_typed::<Details>("details")?
+            .into_iter()
+            .next())
+    }
+
+    /// Returns all keys ever assigned to the identity, including the revoked
+    /// ones.
+    pub fn all_keys(&self) -> Result<Vec<IdentityKey>, ContractError> {
+        Ok(self.0.global_typed::<IdentityKey>("keys")?.into_inner())
+    }
+
+    pub fn revoked_keys(&self) -> Result<Vec<IdentityKey>, ContractError> {
+        match self.0.global_typed::<IdentityKey>("revokedKeys") {
+            Ok(keys) => Ok(keys.into_inner()),
+            Err(ContractError::FieldNameUnknown(_)) => Ok(vec![]),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns keys of the identity which were not revoked.
+    pub fn keys(&self) -> Result<Vec<IdentityKey>, ContractError> {
+        let revoked = self.revoked_keys()?;
+        Ok(self
+            .all_keys()?
+            .into_iter()
+            .filter(|key| !revoked.contains(key))
+            .collect())
+    }
+
+    pub fn controller(
+        &self,
+        filter: impl OutpointFilter,
+    ) -> Result<Option<RightsAllocation>, ContractError> {
+        Ok(self.0.rights("controller", filter)?.next())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use armor::AsciiArmor;
+
+    use super::*;
+
+    const RGB22: &str = include_str!("../../tests/data/rgb22.rgba");
+
+    #[test]
+    fn iface_id() {
+        eprintln!("{:#04x?}", rgb22().iface_id().to_byte_array());
+        assert_eq!(Rgb22::IFACE_ID, rgb22().iface_id());
+    }
+
+    #[test]
+    fn iface_creation() { rgb22(); }
+
+    #[test]
+    fn iface_bindle() {
+        assert_eq!(format!("{}", rgb22().to_ascii_armored_string()), RGB22);
+    }
+
+    #[test]
+    fn iface_check() {
+        if let Err(err) = rgb22().check() {
+            for e in err {
+                eprintln!("{e}");
+            }
+            panic!("invalid RGB22 interface definition");
+        }
+    }
+}
@@ -18,3 +18,178 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+
+use rgb::{Occurrences, Types};
+use strict_types::TypeLib;
+
+use super::{
+    AssignIface, GenesisIface, GlobalIface, Iface, IfaceClass, Modifier, OwnedIface, Req,
+    TransitionIface, VerNo,
+};
+use crate::interface::{
+    ContractError, ContractIface, IfaceId, IfaceWrapper, OutpointFilter, RightsAllocation,
+};
+use crate::stl::{rgb_records_stl, Details, LogEntry, Name, StandardTypes};
+
+pub const LIB_NAME_RGB23: &str = "RGB23";
+
+fn rgb23() -> Iface {
+    let types = StandardTypes::with(rgb_records_stl());
+
+    Iface {
+        version: VerNo::V1,
+        name: tn!("RGB23"),
+        inherits: none!(),
+        global_state: tiny_bmap! {
+            fname!("name") => GlobalIface::required(types.get("RGBContract.Name")),
+            fname!("details") => GlobalIface::optional(types.get("RGBContract.Details")),
+            fname!("entries") => GlobalIface::none_or_many(types.get("RGBRecords.LogEntry")),
+        },
+        assignments: tiny_bmap! {
+            fname!("appendRight") => AssignIface::public(OwnedIface::Rights, Req::Required),
+        },
+        valencies: none!(),
+        genesis: GenesisIface {
+            modifier: Modifier::Final,
+            metadata: None,
+            globals: tiny_bmap! {
+                fname!("name") => Occurrences::Once,
+                fname!("details") => Occurrences::NoneOrOnce,
+                fname!("entries") => Occurrences::NoneOrMore,
+            },
+            assignments: tiny_bmap! {
+                fname!("appendRight") => Occurrences::Once,
+            },
+            valencies: none!(),
+            errors: none!(),
+        },
+        transitions: tiny_bmap! {
+            fname!("transfer") => TransitionIface {
+                modifier: Modifier::Final,
+                optional: false,
+                metadata: None,
+                globals: none!(),
+                inputs: tiny_bmap! {
+                    fname!("appendRight") => Occurrences::Once,
+                },
+                assignments: tiny_bmap! {
+                    fname!("appendRight") => Occurrences::Once,
+                },
+                valencies: none!(),
+                errors: none!(),
+                default_assignment: Some(fname!("appendRight")),
+            },
+            fname!("append") => TransitionIface {
+                modifier: Modifier::Final,
+                optional: false,
+                metadata: None,
+                globals: tiny_bmap! {
+                    fname!("entries") => Occurrences::OnceOrMore,
+                },
+                inputs: tiny_bmap! {
+                    fname!("appendRight") => Occurrences::Once,
+                },
+                assignments: tiny_bmap! {
+                    fname!("appendRight") => Occurrences::Once,
+                },
+                valencies: none!(),
+                errors: none!(),
+                default_assignment: Some(fname!("appendRight")),
+            },
+        },
+        extensions: none!(),
+        errors: none!(),
+        default_operation: Some(fname!("transfer")),
+        types: Types::Strict(types.type_system()),
+    }
+}
+
+#[derive(Wrapper, WrapperMut, Clone, Eq, PartialEq, Debug)]
+#[wrapper(Deref)]
+#[wrapper_mut(DerefMut)]
+pub struct Rgb23(ContractIface);
+
+impl From<ContractIface> for Rgb23 {
+    fn from(iface: ContractIface) -> Self {
+        if iface.iface.iface_id != Rgb23::IFACE_ID {
+            panic!("the provided interface is not RGB23 interface");
+        }
+        Self(iface)
+    }
+}
+
+impl IfaceWrapper for Rgb23 {
+    const IFACE_NAME: &'static str = LIB_NAME_RGB23;
+    const IFACE_ID: IfaceId = IfaceId::from_array([
+        0x74, 0xc1, 0x70, 0xdb, 0x69, 0x07, 0xe3, 0x09, 0xce, 0x4d, 0x4f, 0x46, 0x0e, 0xd7, 0xce,
+        0xea, 0x36, 0x24, 0xd5, 0xf4, 0x66, 0xb7, 0xaf, 0x3d, 0x69, 0x6d, 0x4f, 0x14, 0x8d, 0x72,
+        0x56, 0xbc,
+    ]);
+}
+
+impl IfaceClass for Rgb23 {
+    fn iface() -> Iface { rgb23() }
+    fn stl() -> TypeLib { rgb_records_stl() }
+}
+
+impl Rgb23 {
+    pub fn name(&self) -> Result<Name, ContractError> { self.0.global_typed_first("name") }
+
+    pub fn details(&self) -> Result<Option<Details>, ContractError> {
+        Ok(self
+            .0
+            .global_typed::<Details>("details")?
+            .into_iter()
+            .next())
+    }
+
+    pub fn entries(&self) -> Result<Vec<LogEntry>, ContractError> {
+        Ok(self.0.global_typed::<LogEntry>("entries")?.into_inner())
+    }
+
+    /// Checks whether the log contains an entry committing to the given
+    /// digest.
+    pub fn contains(&self, digest: [u8; 32]) -> Result<bool, ContractError> {
+        Ok(self.entries()?.iter().any(|entry| entry.digest == digest))
+    }
+
+    pub fn append_right(
+        &self,
+        filter: impl OutpointFilter,
+    ) -> Result<Option<RightsAllocation>, ContractError> {
+        Ok(self.0.rights("appendRight", filter)?.next())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use armor::AsciiArmor;
+
+    use super::*;
+
+    const RGB23: &str = include_str!("../../tests/data/rgb23.rgba");
+
+    #[test]
+    fn iface_id() {
+        eprintln!("{:#04x?}", rgb23().iface_id().to_byte_array());
+        assert_eq!(Rgb23::IFACE_ID, rgb23().iface_id());
+    }
+
+    #[test]
+    fn iface_creation() { rgb23(); }
+
+    #[test]
+    fn iface_bindle() {
+        assert_eq!(format!("{}", rgb23().to_ascii_armored_string()), RGB23);
+    }
+
+    #[test]
+    fn iface_check() {
+        if let Err(err) = rgb23().check() {
+            for e in err {
+                eprintln!("{e}");
+            }
+            panic!("invalid RGB23 interface definition");
+        }
+    }
+}
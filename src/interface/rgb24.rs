@@ -18,3 +18,236 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+
+use rgb::{Occurrences, Types};
+use strict_encoding::Variant;
+use strict_types::TypeLib;
+
+use super::{
+    AssignIface, GenesisIface, GlobalIface, Iface, IfaceClass, Modifier, OwnedIface, Req,
+    TransitionIface, VerNo,
+};
+use crate::interface::{
+    ContractError, ContractIface, DataAllocation, IfaceId, IfaceWrapper, OutpointFilter,
+};
+use crate::stl::{rgb_records_stl, Details, DomainName, DomainRecord, Name, StandardTypes};
+
+pub const LIB_NAME_RGB24: &str = "RGB24";
+
+const DOMAIN_EXISTS: u8 = 1;
+const UNKNOWN_DOMAIN: u8 = 2;
+const NOT_DOMAIN_OWNER: u8 = 3;
+const NON_EQUAL_DOMAINS: u8 = 4;
+
+fn rgb24() -> Iface {
+    let types = StandardTypes::with(rgb_records_stl());
+
+    Iface {
+        version: VerNo::V1,
+        name: tn!("RGB24"),
+        inherits: none!(),
+        global_state: tiny_bmap! {
+            fname!("name") => GlobalIface::required(types.get("RGBContract.Name")),
+            fname!("details") => GlobalIface::optional(types.get("RGBContract.Details")),
+            fname!("domains") => GlobalIface::none_or_many(types.get("RGBRecords.DomainName")),
+            fname!("records") => GlobalIface::none_or_many(types.get("RGBRecords.DomainRecord")),
+        },
+        assignments: tiny_bmap! {
+            fname!("registrar") => AssignIface::public(OwnedIface::Rights, Req::Optional),
+            fname!("domainOwner") => AssignIface::private(OwnedIface::Data(types.get("RGBRecords.DomainName")), Req::NoneOrMore),
+        },
+        valencies: none!(),
+        genesis: GenesisIface {
+            modifier: Modifier::Final,
+            metadata: None,
+            globals: tiny_bmap! {
+                fname!("name") => Occurrences::Once,
+                fname!("details") => Occurrences::NoneOrOnce,
+                fname!("domains") => Occurrences::NoneOrMore,
+            },
+            assignments: tiny_bmap! {
+                fname!("registrar") => Occurrences::NoneOrOnce,
+                fname!("domainOwner") => Occurrences::NoneOrMore,
+            },
+            valencies: none!(),
+            errors: tiny_bset! {
+                DOMAIN_EXISTS,
+                UNKNOWN_DOMAIN
+            },
+        },
+        transitions: tiny_bmap! {
+            fname!("transfer") => TransitionIface {
+                modifier: Modifier::Final,
+                optional: false,
+                metadata: None,
+                globals: none!(),
+                inputs: tiny_bmap! {
+                    fname!("domainOwner") => Occurrences::OnceOrMore,
+                },
+                assignments: tiny_bmap! {
+                    fname!("domainOwner") => Occurrences::OnceOrMore,
+                },
+                valencies: none!(),
+                errors: tiny_bset! {
+                    NON_EQUAL_DOMAINS
+                },
+                default_assignment: Some(fname!("domainOwner")),
+            },
+            fname!("register") => TransitionIface {
+                modifier: Modifier::Final,
+                optional: true,
+                metadata: None,
+                globals: tiny_bmap! {
+                    fname!("domains") => Occurrences::OnceOrMore,
+                },
+                inputs: tiny_bmap! {
+                    fname!("registrar") => Occurrences::Once,
+                },
+                assignments: tiny_bmap! {
+                    fname!("registrar") => Occurrences::NoneOrOnce,
+                    fname!("domainOwner") => Occurrences::OnceOrMore,
+                },
+                valencies: none!(),
+                errors: tiny_bset! {
+                    DOMAIN_EXISTS,
+                    UNKNOWN_DOMAIN
+                },
+                default_assignment: Some(fname!("domainOwner")),
+            },
+            fname!("update") => TransitionIface {
+                modifier: Modifier::Final,
+                optional: false,
+                metadata: None,
+                globals: tiny_bmap! {
+                    fname!("records") => Occurrences::OnceOrMore,
+                },
+                inputs: tiny_bmap! {
+                    fname!("domainOwner") => Occurrences::Once,
+                },
+                assignments: tiny_bmap! {
+                    fname!("domainOwner") => Occurrences::Once,
+                },
+                valencies: none!(),
+                errors: tiny_bset! {
+                    NOT_DOMAIN_OWNER,
+                    NON_EQUAL_DOMAINS
+                },
+                default_assignment: Some(fname!("domainOwner")),
+            },
+        },
+        extensions: none!(),
+        errors: tiny_bmap! {
+            Variant::named(DOMAIN_EXISTS, vname!("domainExists"))
+                => tiny_s!("the domain name is already registered"),
+
+            Variant::named(UNKNOWN_DOMAIN, vname!("unknownDomain"))
+                => tiny_s!("the allocated domain name is not registered with the contract"),
+
+            Variant::named(NOT_DOMAIN_OWNER, vname!("notDomainOwner"))
+                => tiny_s!("the records are updated for a domain which is not owned by the operation inputs"),
+
+            Variant::named(NON_EQUAL_DOMAINS, vname!("nonEqualDomains"))
+                => tiny_s!("the set of domains in the outputs doesn't match the set of domains in the inputs"),
+        },
+        default_operation: Some(fname!("transfer")),
+        types: Types::Strict(types.type_system()),
+    }
+}
+
+#[derive(Wrapper, WrapperMut, Clone, Eq, PartialEq, Debug)]
+#[wrapper(Deref)]
+#[wrapper_mut(DerefMut)]
+pub struct Rgb24(ContractIface);
+
+impl From<ContractIface> for Rgb24 {
+    fn from(iface: ContractIface) -> Self {
+        if iface.iface.iface_id != Rgb24::IFACE_ID {
+            panic!("the provided interface is not RGB24 interface");
+        }
+        Self(iface)
+    }
+}
+
+impl IfaceWrapper for Rgb24 {
+    const IFACE_NAME: &'static str = LIB_NAME_RGB24;
+    const IFACE_ID: IfaceId = IfaceId::from_array([
+        0x29, 0x5c, 0x2e, 0x64, 0xa8, 0x29, 0x77, 0xad, 0xbc, 0x1b, 0x31, 0x14, 0x2f, 0x16, 0x4d,
+        0xfd, 0xcb, 0xa6, 0x1d, 0x22, 0xd1, 0xa7, 0x2e, 0x65, 0x8f, 0xde, 0x36, 0x6e, 0x2e, 0xfe,
+        0xa2, 0x68,
+    ]);
+}
+
+impl IfaceClass for Rgb24 {
+    fn iface() -> Iface { rgb24() }
+    fn stl() -> TypeLib { rgb_records_stl() }
+}
+
+impl Rgb24 {
+    pub fn name(&self) -> Result<Name, ContractError> { self.0.global_typed_first("name") }
+
+    pub fn details(&self) -> Result<Option<Details>, ContractError> {
+        Ok(self
+            .0
+            .global_typed::<Details>("details")?
+            .into_iter()
+            .next())
+    }
+
+    pub fn domains(&self) -> Result<Vec<DomainName>, ContractError> {
+        Ok(self.0.global_typed::<DomainName>("domains")?.into_inner())
+    }
+
+    pub fn records(&self) -> Result<Vec<DomainRecord>, ContractError> {
+        Ok(self.0.global_typed::<DomainRecord>("records")?.into_inner())
+    }
+
+    /// Returns records for a specific domain, in the order they were added to
+    /// the contract.
+    pub fn domain_records(&self, domain: &DomainName) -> Result<Vec<DomainRecord>, ContractError> {
+        Ok(self
+            .records()?
+            .into_iter()
+            .filter(|record| &record.domain == domain)
+            .collect())
+    }
+
+    pub fn owned_domains(
+        &self,
+        filter: impl OutpointFilter,
+    ) -> Result<Vec<(DataAllocation, DomainName)>, ContractError> {
+        self.0.data_typed("domainOwner", filter)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use armor::AsciiArmor;
+
+    use super::*;
+
+    const RGB24: &str = include_str!("../../tests/data/rgb24.rgba");
+
+    #[test]
+    fn iface_id() {
+        eprintln!("{:#04x?}", rgb24().iface_id().to_byte_array());
+        assert_eq!(Rgb24::IFACE_ID, rgb24().iface_id());
+    }
+
+    #[test]
+    fn iface_creation() { rgb24(); }
+
+    #[test]
+    fn iface_bindle() {
+        assert_eq!(format!("{}", rgb24().to_ascii_armored_string()), RGB24);
+    }
+
+    #[test]
+    fn iface_check() {
+        if let Err(err) = rgb24().check() {
+            for e in err {
+                eprintln!("{e}");
+            }
+            panic!("invalid RGB24 interface definition");
+        }
+    }
+}
@@ -37,7 +37,7 @@ use super::{
 };
 use crate::containers::Contract;
 use crate::interface::rgb20::AllocationError;
-use crate::interface::{ContractIface, IfaceId, IfaceWrapper};
+use crate::interface::{ContractError, ContractIface, IfaceId, IfaceWrapper};
 use crate::persistence::PersistedState;
 use crate::stl::{
     rgb_contract_stl, AssetTerms, Attachment, Details, Name, RicardianContract, StandardTypes,
@@ -182,58 +182,36 @@ impl IfaceClass for Rgb25 {
 }
 
 impl Rgb25 {
-    pub fn name(&self) -> Name {
-        let strict_val = &self
-            .0
-            .global("name")
-            .expect("RGB25 interface requires global `name`")[0];
-        Name::from_strict_val_unchecked(strict_val)
-    }
+    pub fn name(&self) -> Result<Name, ContractError> { self.0.global_typed_first("name") }
 
-    pub fn details(&self) -> Option<Details> {
-        let strict_val = &self
+    pub fn details(&self) -> Result<Option<Details>, ContractError> {
+        Ok(self
             .0
-            .global("details")
-            .expect("RGB25 interface requires global `details`");
-        if strict_val.len() == 0 {
-            None
-        } else {
-            Some(Details::from_strict_val_unchecked(&strict_val[0]))
-        }
+            .global_typed::<Details>("details")?
+            .into_iter()
+            .next())
     }
 
-    pub fn precision(&self) -> Precision {
-        let strict_val = &self
-            .0
-            .global("precision")
-            .expect("RGB25 interface requires global `precision`")[0];
-        Precision::from_strict_val_unchecked(strict_val)
+    pub fn precision(&self) -> Result<Precision, ContractError> {
+        self.0.global_typed_first("precision")
     }
 
-    pub fn total_issued_supply(&self) -> Amount {
-        self.0
-            .global("issuedSupply")
-            .expect("RGB25 interface requires global `issuedSupply`")
-            .iter()
-            .map(Amount::from_strict_val_unchecked)
-            .sum()
+    pub fn total_issued_supply(&self) -> Result<Amount, ContractError> {
+        Amount::checked_sum(self.0.global_typed::<Amount>("issuedSupply")?)
+            .ok_or(ContractError::AmountOverflow(fname!("issuedSupply")))
     }
 
-    pub fn total_burned_supply(&self) -> Amount {
-        self.0
-            .global("burnedSupply")
-            .unwrap_or_default()
-            .iter()
-            .map(Amount::from_strict_val_unchecked)
-            .sum()
+    pub fn total_burned_supply(&self) -> Result<Amount, ContractError> {
+        match self.0.global_typed::<Amount>("burnedSupply") {
+            Ok(supply) => Amount::checked_sum(supply)
+                .ok_or(ContractError::AmountOverflow(fname!("burnedSupply"))),
+            Err(ContractError::FieldNameUnknown(_)) => Ok(Amount::ZERO),
+            Err(err) => Err(err),
+        }
     }
 
-    pub fn contract_terms(&self) -> AssetTerms {
-        let strict_val = &self
-            .0
-            .global("terms")
-            .expect("RGB25 interface requires global `terms`")[0];
-        AssetTerms::from_strict_val_unchecked(strict_val)
+    pub fn contract_terms(&self) -> Result<AssetTerms, ContractError> {
+        self.0.global_typed_first("terms")
     }
 }
 
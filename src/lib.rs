@@ -87,6 +87,8 @@ pub mod containers;
 pub mod persistence;
 pub mod resolvers;
 pub mod accessors;
+#[cfg(test)]
+mod fixtures;
 
 pub use bp::{Outpoint, Txid};
 pub use rgb::*;
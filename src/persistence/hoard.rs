@@ -35,9 +35,7 @@ use strict_encoding::TypeName;
 
 use crate::accessors::{MergeReveal, MergeRevealError};
 use crate::containers::{Cert, Consignment, ContentId, ContentSigs};
-use crate::interface::{
-    ContractSuppl, Iface, IfaceClass, IfaceId, IfacePair, Rgb20, Rgb21, Rgb25, SchemaIfaces,
-};
+use crate::interface::{ContractSuppl, Iface, IfaceId, IfacePair, IssuerRegistry, SchemaIfaces};
 use crate::persistence::{InventoryError, Stash, StashError, StashInconsistency};
 use crate::LIB_NAME_RGB_STD;
 
@@ -86,19 +84,22 @@ pub struct Hoard {
 
 impl Hoard {
     pub fn preset() -> Self {
-        let rgb20 = Rgb20::iface();
-        let rgb20_id = rgb20.iface_id();
-        let rgb21 = Rgb21::iface();
-        let rgb21_id = rgb21.iface_id();
-        let rgb25 = Rgb25::iface();
-        let rgb25_id = rgb25.iface_id();
-        Hoard {
-            schemata: none!(),
-            ifaces: tiny_bmap! {
-                rgb20_id => rgb20,
-                rgb21_id => rgb21,
-                rgb25_id => rgb25,
-            },
+        Self::with_registry(&IssuerRegistry::standard())
+            .expect("standard interfaces always fit into the hoard")
+    }
+
+    /// Constructs hoard containing all interfaces, schemata and interface
+    /// implementations from the registry.
+    pub fn with_registry(registry: &IssuerRegistry) -> Result<Self, confinement::Error> {
+        let ifaces = registry
+            .ifaces()
+            .map(|iface| (iface.iface_id(), iface.clone()));
+        let schemata = registry
+            .schemata()
+            .map(|schema_ifaces| (schema_ifaces.schema.schema_id(), schema_ifaces.clone()));
+        Ok(Hoard {
+            schemata: Confined::try_from_iter(schemata)?,
+            ifaces: Confined::try_from_iter(ifaces)?,
             geneses: none!(),
             suppl: none!(),
             asset_tags: none!(),
@@ -106,7 +107,7 @@ impl Hoard {
             extensions: none!(),
             anchors: none!(),
             sigs: none!(),
-        }
+        })
     }
 
     pub(super) fn import_sigs_internal<I>(
@@ -219,6 +220,18 @@ impl Hoard {
         Ok(())
     }
 
+    // TODO: Move into Stash trait and re-implement using trait accessor methods
+    pub fn consume_extension(&mut self, extension: Extension) -> Result<(), ConsumeError> {
+        let opid = extension.id();
+        match self.extensions.get_mut(&opid) {
+            Some(e) => *e = e.clone().merge_reveal(extension)?,
+            None => {
+                self.extensions.insert(opid, extension)?;
+            }
+        }
+        Ok(())
+    }
+
     // TODO: Move into Stash trait and re-implement using trait accessor methods
     pub fn consume_anchor(
         &mut self,
@@ -19,36 +19,44 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cell::Cell;
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::error::Error;
 use std::ops::Deref;
 
-use amplify::confinement::{self, Confined, U24};
+use amplify::confinement::{self, Confined, U16, U24};
+use amplify::Wrapper;
 use bp::seals::txout::CloseMethod;
 use bp::{Txid, Vout};
 use chrono::Utc;
 use commit_verify::{mpc, Conceal};
 use invoice::{Amount, Beneficiary, InvoiceState, NonFungible, RgbInvoice};
 use rgb::{
-    validation, AnchoredBundle, AssignmentType, BlindingFactor, BundleId, ContractId, GraphSeal,
-    OpId, Operation, Opout, SchemaId, SecretSeal, SubSchema, Transition, TransitionBundle,
-    WitnessId, XAnchor, XChain, XOutpoint, XOutputSeal,
+    validation, AnchoredBundle, AssignmentType, BlindingFactor, BundleId, ContractId, Extension,
+    ExtensionType, GraphSeal, Layer1, OpId, OpRef, Operation, Opout, SchemaId, SecretSeal,
+    StateSchema, SubSchema, Transition, TransitionBundle, TransitionType, WitnessId, XAnchor,
+    XChain, XOutpoint, XOutputSeal,
 };
-use strict_encoding::{FieldName, TypeName};
+use strict_encoding::{FieldName, StrictDeserialize, TypeName};
 
 use crate::accessors::{BundleExt, MergeRevealError, RevealError};
 use crate::containers::{
     Batch, BuilderSeal, Cert, Consignment, ContentId, Contract, Fascia, Terminal, TerminalSeal,
     Transfer, TransitionInfo,
 };
+use crate::interface::rgb21::{OwnedFraction, TokenIndex};
 use crate::interface::{
-    BuilderError, ContractIface, Iface, IfaceId, IfaceImpl, IfacePair, IfaceWrapper,
-    TransitionBuilder, VelocityHint,
+    validate_operation, BuilderError, ContractIface, ExtensionBuilder, Iface, IfaceId, IfaceImpl,
+    IfacePair, IfaceWrapper, IssuerRegistry, StatePoint, TransitionBuilder, VelocityHint,
 };
 use crate::persistence::hoard::ConsumeError;
+use crate::persistence::selection::Candidate;
 use crate::persistence::stash::StashInconsistency;
-use crate::persistence::{PersistedState, Stash, StashError};
+use crate::persistence::{
+    AcceptPolicy, InputSelection, InputsSelected, OutgoingTransfer, PersistedState,
+    SelectedState, Stash, StashError, TransferPreview, TransferStatus,
+};
 use crate::resolvers::ResolveHeight;
 
 #[derive(Debug, Display, Error, From)]
@@ -61,6 +69,9 @@ pub enum ConsignerError<E1: Error, E2: Error> {
     /// too many transitions.
     TooManyBundles,
 
+    /// unable to construct consignment: too many state extensions.
+    TooManyExtensions,
+
     /// public state at operation output {0} is concealed.
     ConcealedPublicState(Opout),
 
@@ -102,6 +113,23 @@ pub enum ComposeError<E1: Error, E2: Error> {
     /// smart contract state.
     InsufficientState,
 
+    /// the provided outputs don't contain {0} state required for the operation.
+    NoRequiredState(FieldName),
+
+    /// the invoice state doesn't match the kind of state `{0}` defined by the
+    /// contract schema.
+    StateMismatch(FieldName),
+
+    /// the total amount of the operation exceeds the maximal allowed value.
+    AmountOverflow,
+
+    /// contract {0} doesn't implement any interface, which is required to
+    /// construct a blank transition.
+    NoBlankIface(ContractId),
+
+    /// the provided outputs don't own any fraction of the token {0}.
+    TokenNotOwned(TokenIndex),
+
     /// the operation produces too many state transitions which can't fit the
     /// container requirements.
     #[from]
@@ -229,6 +257,48 @@ pub enum DataError {
     /// schema {0} doesn't implement interface {1}.
     NoIfaceImpl(SchemaId, IfaceId),
 
+    /// interface {0} doesn't define state extension `{1}`.
+    NoExtension(TypeName, FieldName),
+
+    /// schema of contract {0} doesn't define state extension type {1}.
+    UnknownExtension(ContractId, ExtensionType),
+
+    /// state extension doesn't pass schema validation: {0}
+    InvalidExtension(validation::Failure),
+
+    /// outgoing transfer with witness {0} is not known.
+    UnknownTransfer(WitnessId),
+
+    /// contract {0} is issued on testnet and can't be accepted on mainnet.
+    TestnetContract(ContractId),
+
+    /// schema {0} is not allowed by the acceptance policy.
+    SchemaNotAllowed(SchemaId),
+
+    /// contract {0} doesn't implement any of the interfaces allowed by the
+    /// acceptance policy.
+    IfaceNotAllowed(ContractId),
+
+    /// consignment history contains {0} bundles, exceeding the limit of {1}
+    /// bundles set by the acceptance policy.
+    HistoryTooDeep(usize, usize),
+
+    /// consignment size of {0} bytes exceeds the limit of {1} bytes set by
+    /// the acceptance policy.
+    ConsignmentTooLarge(usize, usize),
+
+    /// genesis of contract {0} is not signed by any of the issuers trusted by
+    /// the acceptance policy.
+    UntrustedIssuer(ContractId),
+
+    /// terminal witness transaction {0} has {1} confirmations, while the
+    /// acceptance policy requires {2}.
+    InsufficientConfirmations(WitnessId, u32, u32),
+
+    /// outgoing transfer with witness {0} has status '{1}' and can't be moved
+    /// to status '{2}'.
+    TransferStatus(WitnessId, TransferStatus, TransferStatus),
+
     #[from]
     #[display(inner)]
     HeightResolver(Box<dyn Error>),
@@ -332,10 +402,30 @@ pub trait Inventory: Deref<Target = Self::Stash> {
         iimpl: IfaceImpl,
     ) -> Result<validation::Status, InventoryDataError<Self::Error>>;
 
+    /// Imports all interfaces, schemata and interface implementations known to
+    /// the registry.
+    fn import_registry(
+        &mut self,
+        registry: &IssuerRegistry,
+    ) -> Result<validation::Status, InventoryDataError<Self::Error>> {
+        let mut status = validation::Status::new();
+        for iface in registry.ifaces() {
+            status += self.import_iface(iface.clone())?;
+        }
+        for schema_ifaces in registry.schemata() {
+            status += self.import_schema(schema_ifaces.schema.clone())?;
+            for iimpl in schema_ifaces.iimpls.values() {
+                status += self.import_iface_impl(iimpl.clone())?;
+            }
+        }
+        Ok(status)
+    }
+
     fn import_contract<R: ResolveHeight>(
         &mut self,
         contract: Contract,
         resolver: &mut R,
+        policy: &AcceptPolicy,
     ) -> Result<validation::Status, InventoryError<Self::Error>>
     where
         R::Error: 'static;
@@ -344,11 +434,23 @@ pub trait Inventory: Deref<Target = Self::Stash> {
         &mut self,
         transfer: Transfer,
         resolver: &mut R,
-        force: bool,
+        policy: &AcceptPolicy,
     ) -> Result<validation::Status, InventoryError<Self::Error>>
     where
         R::Error: 'static;
 
+    /// Performs a dry-run of [`Self::accept_transfer`], reporting the state
+    /// which the transfer would assign to the wallet-controlled outputs
+    /// without modifying the inventory.
+    fn preview_transfer<R: ResolveHeight>(
+        &self,
+        transfer: &Transfer,
+        resolver: &mut R,
+        policy: &AcceptPolicy,
+    ) -> Result<TransferPreview, InventoryError<Self::Error>>
+    where
+        R::Error: 'static;
+
     /// Imports fascia into the stash, index and inventory.
     ///
     /// Part of the transfer workflow. Called once PSBT is completed and an RGB
@@ -357,10 +459,44 @@ pub trait Inventory: Deref<Target = Self::Stash> {
     ///
     /// Must be called before the consignment is created, when witness
     /// transaction is not yet mined.
+    ///
+    /// Records the outgoing transfer with the status
+    /// [`TransferStatus::Consumed`]. The beneficiaries are taken from the
+    /// non-blank state transitions of the fascia: all their concealed seals
+    /// and all outputs defined by their revealed seals, which include the
+    /// change outputs.
     fn consume(&mut self, fascia: Fascia) -> Result<(), InventoryError<Self::Error>> {
         let witness_id = fascia.anchor.witness_id().ok_or_else(|| {
             ConsumeError::AnchorInconsistent(fascia.anchor.witness_id_unchecked())
         })?;
+        let mut transfer = self
+            .outgoing_transfer(witness_id)?
+            .unwrap_or_else(|| OutgoingTransfer::new(witness_id));
+        for (contract_id, bundle) in &fascia.bundles {
+            let mut outputs = vec![];
+            let mut secret_seals = vec![];
+            for transition in bundle.known_transitions.values() {
+                if transition.transition_type == TransitionType::BLANK {
+                    continue;
+                }
+                for assignments in transition.assignments.values() {
+                    let concealed = assignments.to_confidential_seals();
+                    for index in 0..assignments.len_u16() {
+                        match assignments.revealed_seal_at(index).expect("index exists") {
+                            Some(seal) => outputs.push(
+                                seal.try_to_output_seal(witness_id)
+                                    .map_err(|_| DataError::ChainMismatch)?,
+                            ),
+                            None => secret_seals.push(concealed[index as usize]),
+                        }
+                    }
+                }
+            }
+            if !outputs.is_empty() || !secret_seals.is_empty() {
+                transfer.add_beneficiaries(*contract_id, outputs, secret_seals)?;
+            }
+        }
+
         unsafe { self.consume_anchor(fascia.anchor)? };
         for (contract_id, bundle) in fascia.bundles {
             let ids1 = bundle
@@ -374,9 +510,29 @@ pub trait Inventory: Deref<Target = Self::Stash> {
             }
             unsafe { self.consume_bundle(contract_id, bundle, witness_id)? };
         }
-        Ok(())
+        if transfer.status < TransferStatus::Consumed {
+            transfer.status = TransferStatus::Consumed;
+        }
+        self.store_outgoing_transfer(transfer)
     }
 
+    /// Imports state extension created locally with
+    /// [`Inventory::extension_builder`].
+    ///
+    /// The extension must match the contract schema and may redeem only
+    /// valencies defined by the contract genesis or state extensions already
+    /// known to the inventory.
+    ///
+    /// Since extensions are not anchored, the extension gets ordered in the
+    /// contract history as a part of the witness transaction `witness_id`,
+    /// which is expected to be the first one closing seals defined by the
+    /// extension.
+    fn import_extension(
+        &mut self,
+        extension: Extension,
+        witness_id: WitnessId,
+    ) -> Result<(), InventoryError<Self::Error>>;
+
     #[doc(hidden)]
     unsafe fn consume_anchor(
         &mut self,
@@ -430,6 +586,21 @@ pub trait Inventory: Deref<Target = Self::Stash> {
             .collect()
     }
 
+    fn contracts_by_iface_id(
+        &self,
+        iface_id: IfaceId,
+    ) -> Result<Vec<ContractIface>, InventoryError<Self::Error>>
+    where
+        Self::Error: From<<Self::Stash as Stash>::Error>,
+        InventoryError<Self::Error>: From<<Self::Stash as Stash>::Error>,
+    {
+        let iface = self.iface_by_id(iface_id)?.name.clone();
+        self.contract_ids_by_iface(&iface)?
+            .into_iter()
+            .map(|id| self.contract_iface_id(id, iface_id))
+            .collect()
+    }
+
     fn contract_iface_named(
         &self,
         contract_id: ContractId,
@@ -458,6 +629,27 @@ pub trait Inventory: Deref<Target = Self::Stash> {
         iface_id: IfaceId,
     ) -> Result<ContractIface, InventoryError<Self::Error>>;
 
+    /// Reconstructs contract state as it was at the given point of the
+    /// contract history, taking into account only operations witnessed at or
+    /// before that point.
+    ///
+    /// NB: like the current state, the reconstructed state includes
+    /// allocations spent before the point; they can be detected with the
+    /// inputs of [`ContractIface::transitions`], which are limited to the
+    /// transitions witnessed before the point as well.
+    ///
+    /// State extensions are ordered by the witness they were imported with
+    /// or, if they were received with a consignment, by the first transition
+    /// spending their state. Witness ordering is taken from the inventory
+    /// index, so locally created operations are taken into account only
+    /// once their witnesses are updated as mined.
+    fn contract_iface_at(
+        &self,
+        contract_id: ContractId,
+        iface_id: IfaceId,
+        point: StatePoint,
+    ) -> Result<ContractIface, InventoryError<Self::Error>>;
+
     fn anchored_bundle(&self, opid: OpId) -> Result<AnchoredBundle, InventoryError<Self::Error>>;
 
     fn transition_builder(
@@ -502,44 +694,68 @@ pub trait Inventory: Deref<Target = Self::Stash> {
         Ok(builder)
     }
 
-    fn blank_builder(
+    fn extension_builder(
         &self,
         contract_id: ContractId,
         iface: impl Into<TypeName>,
-    ) -> Result<TransitionBuilder, InventoryError<Self::Error>>
+        extension_name: impl Into<FieldName>,
+    ) -> Result<ExtensionBuilder, InventoryError<Self::Error>>
     where
         Self::Error: From<<Self::Stash as Stash>::Error>,
     {
         let schema_ifaces = self.contract_schema(contract_id)?;
         let iface = self.iface_by_name(&iface.into())?;
         let schema = &schema_ifaces.schema;
-        if schema_ifaces.iimpls.is_empty() {
-            return Err(InventoryError::DataError(DataError::NoIfaceImpl(
-                schema.schema_id(),
-                iface.iface_id(),
-            )));
+        let iimpl = schema_ifaces
+            .iimpls
+            .get(&iface.iface_id())
+            .ok_or(DataError::NoIfaceImpl(schema.schema_id(), iface.iface_id()))?;
+        let extension_name = extension_name.into();
+        if iimpl.extension_type(&extension_name).is_none() {
+            return Err(DataError::NoExtension(iface.name.clone(), extension_name).into());
         }
+        let mut builder = ExtensionBuilder::named_extension(
+            contract_id,
+            iface.clone(),
+            schema.clone(),
+            iimpl.clone(),
+            extension_name,
+        )
+        .expect("internal inconsistency");
+        let tags = self.contract_asset_tags(contract_id)?;
+        for (assignment_type, asset_tag) in tags {
+            builder = builder
+                .add_asset_tag_raw(*assignment_type, *asset_tag)
+                .expect("tags are in bset and must not repeat");
+        }
+        Ok(builder)
+    }
 
-        let mut builder = if let Some(iimpl) = schema_ifaces.iimpls.get(&iface.iface_id()) {
-            TransitionBuilder::blank_transition(
-                contract_id,
-                iface.clone(),
-                schema.clone(),
-                iimpl.clone(),
-            )
-            .expect("internal inconsistency")
-        } else {
-            let (default_iface_id, default_iimpl) = schema_ifaces.iimpls.first_key_value().unwrap();
-            let default_iface = self.iface_by_id(*default_iface_id)?;
+    /// Constructs builder for a blank state transition of the contract using
+    /// the provided interface, which must be implemented by the contract.
+    fn blank_builder(
+        &self,
+        contract_id: ContractId,
+        iface: impl Into<TypeName>,
+    ) -> Result<TransitionBuilder, InventoryError<Self::Error>>
+    where
+        Self::Error: From<<Self::Stash as Stash>::Error>,
+    {
+        let schema_ifaces = self.contract_schema(contract_id)?;
+        let iface = self.iface_by_name(&iface.into())?;
+        let schema = &schema_ifaces.schema;
+        let iimpl = schema_ifaces
+            .iimpls
+            .get(&iface.iface_id())
+            .ok_or(DataError::NoIfaceImpl(schema.schema_id(), iface.iface_id()))?;
 
-            TransitionBuilder::blank_transition(
-                contract_id,
-                default_iface.clone(),
-                schema.clone(),
-                default_iimpl.clone(),
-            )
-            .expect("internal inconsistency")
-        };
+        let mut builder = TransitionBuilder::blank_transition(
+            contract_id,
+            iface.clone(),
+            schema.clone(),
+            iimpl.clone(),
+        )
+        .expect("internal inconsistency");
         let tags = self.contract_asset_tags(contract_id)?;
         for (assignment_type, asset_tag) in tags {
             builder = builder
@@ -587,6 +803,74 @@ pub trait Inventory: Deref<Target = Self::Stash> {
 
     fn seal_secrets(&self) -> Result<BTreeSet<XChain<GraphSeal>>, InventoryError<Self::Error>>;
 
+    /// Stores record of an outgoing transfer, replacing the existing record
+    /// with the same witness id.
+    fn store_outgoing_transfer(
+        &mut self,
+        transfer: OutgoingTransfer,
+    ) -> Result<(), InventoryError<Self::Error>>;
+
+    fn outgoing_transfers(
+        &self,
+    ) -> Result<BTreeMap<WitnessId, OutgoingTransfer>, InventoryError<Self::Error>>;
+
+    /// Returns record of the outgoing transfer with the given witness id, if
+    /// it is known.
+    fn outgoing_transfer(
+        &self,
+        witness_id: WitnessId,
+    ) -> Result<Option<OutgoingTransfer>, InventoryError<Self::Error>>;
+
+    /// Returns outgoing transfers which have not completed their lifecycle
+    /// yet.
+    fn pending_transfers(
+        &self,
+    ) -> Result<BTreeMap<WitnessId, OutgoingTransfer>, InventoryError<Self::Error>> {
+        let mut transfers = self.outgoing_transfers()?;
+        transfers.retain(|_, transfer| !transfer.status.is_final());
+        Ok(transfers)
+    }
+
+    /// Moves outgoing transfer to a new status, which must not precede the
+    /// current one.
+    fn update_transfer_status(
+        &mut self,
+        witness_id: WitnessId,
+        status: TransferStatus,
+    ) -> Result<(), InventoryError<Self::Error>> {
+        let mut transfer = self
+            .outgoing_transfer(witness_id)?
+            .ok_or(DataError::UnknownTransfer(witness_id))?;
+        if status < transfer.status {
+            return Err(DataError::TransferStatus(witness_id, transfer.status, status).into());
+        }
+        transfer.status = status;
+        self.store_outgoing_transfer(transfer)
+    }
+
+    /// Regenerates consignments for the beneficiaries of an outgoing transfer,
+    /// one per each contract which state is transferred.
+    #[allow(clippy::type_complexity)]
+    fn outgoing_consignments(
+        &mut self,
+        witness_id: WitnessId,
+    ) -> Result<
+        BTreeMap<ContractId, Transfer>,
+        ConsignerError<Self::Error, <<Self as Deref>::Target as Stash>::Error>,
+    > {
+        let transfer = self
+            .outgoing_transfer(witness_id)?
+            .ok_or(InventoryError::from(DataError::UnknownTransfer(witness_id)))?;
+        let mut consignments = BTreeMap::new();
+        for (contract_id, beneficiaries) in transfer.contracts {
+            let outputs = beneficiaries.outputs.into_iter().collect::<Vec<_>>();
+            let secret_seals = beneficiaries.secret_seals.into_iter().collect::<Vec<_>>();
+            let consignment = self.transfer(contract_id, outputs, secret_seals)?;
+            consignments.insert(contract_id, consignment);
+        }
+        Ok(consignments)
+    }
+
     #[allow(clippy::type_complexity)]
     fn export_contract(
         &self,
@@ -599,16 +883,40 @@ pub trait Inventory: Deref<Target = Self::Stash> {
         // TODO: Add known sigs to the bindle
     }
 
+    /// Creates transfer consignment for the given beneficiaries.
+    ///
+    /// Outgoing transfers of the contract state to any of the beneficiaries,
+    /// which didn't have consignments created yet, are moved to the status
+    /// [`TransferStatus::Consigned`].
     #[allow(clippy::type_complexity)]
     fn transfer(
-        &self,
+        &mut self,
         contract_id: ContractId,
         outputs: impl AsRef<[XOutputSeal]>,
         secret_seals: impl AsRef<[XChain<SecretSeal>]>,
     ) -> Result<Transfer, ConsignerError<Self::Error, <<Self as Deref>::Target as Stash>::Error>>
     {
+        let outputs = outputs.as_ref();
+        let secret_seals = secret_seals.as_ref();
         let mut consignment = self.consign(contract_id, outputs, secret_seals)?;
         consignment.transfer = true;
+
+        for (_, mut transfer) in self.pending_transfers()? {
+            if transfer.status >= TransferStatus::Consigned {
+                continue;
+            }
+            let Some(beneficiaries) = transfer.contracts.get(&contract_id) else {
+                continue;
+            };
+            if outputs.iter().any(|output| beneficiaries.outputs.contains(output)) ||
+                secret_seals
+                    .iter()
+                    .any(|seal| beneficiaries.secret_seals.contains(seal))
+            {
+                transfer.status = TransferStatus::Consigned;
+                self.store_outgoing_transfer(transfer)?;
+            }
+        }
         Ok(consignment)
     }
 
@@ -633,13 +941,20 @@ pub trait Inventory: Deref<Target = Self::Stash> {
         opouts.extend(self.opouts_by_terminals(secret_seals.iter().copied())?);
 
         // 1.3. Collect all state transitions assigning state to the provided outpoints
+        let extension_ids = self.extension_ids().map_err(StashError::Connectivity)?;
         let mut anchored_bundles = BTreeMap::<OpId, AnchoredBundle>::new();
         let mut transitions = BTreeMap::<OpId, Transition>::new();
+        let mut extensions = BTreeMap::<OpId, Extension>::new();
         let mut terminals = BTreeMap::<BundleId, Terminal>::new();
         for opout in opouts {
             if opout.op == contract_id {
                 continue; // we skip genesis since it will be present anywhere
             }
+            if extension_ids.contains(&opout.op) {
+                // extensions are not anchored, so they can't provide terminals
+                extensions.insert(opout.op, self.extension(opout.op)?.clone());
+                continue;
+            }
             let transition = self.transition(opout.op)?;
             transitions.insert(opout.op, transition.clone());
             let anchored_bundle = self.anchored_bundle(opout.op)?;
@@ -674,10 +989,22 @@ pub trait Inventory: Deref<Target = Self::Stash> {
         for transition in transitions.values() {
             ids.extend(transition.inputs().iter().map(|input| input.prev_out.op));
         }
+        for extension in extensions.values() {
+            ids.extend(extension.redeemed.values().copied());
+        }
         while let Some(id) = ids.pop() {
             if id == contract_id {
                 continue; // we skip genesis since it will be present anywhere
             }
+            if extensions.contains_key(&id) {
+                continue;
+            }
+            if extension_ids.contains(&id) {
+                let extension = self.extension(id)?;
+                ids.extend(extension.redeemed.values().copied());
+                extensions.insert(id, extension.clone());
+                continue;
+            }
             let transition = self.transition(id)?;
             ids.extend(transition.inputs().iter().map(|input| input.prev_out.op));
             transitions.insert(id, transition.clone());
@@ -702,6 +1029,8 @@ pub trait Inventory: Deref<Target = Self::Stash> {
         }
         consignment.bundles = Confined::try_from_iter(anchored_bundles.into_values())
             .map_err(|_| ConsignerError::TooManyBundles)?;
+        consignment.extensions = Confined::try_from_iter(extensions.into_values())
+            .map_err(|_| ConsignerError::TooManyExtensions)?;
         consignment.terminals =
             Confined::try_from(terminals).map_err(|_| ConsignerError::TooManyTerminals)?;
 
@@ -711,6 +1040,115 @@ pub trait Inventory: Deref<Target = Self::Stash> {
         Ok(consignment)
     }
 
+    /// Selects outputs, among the `available` ones, holding enough of the
+    /// state requested by the invoice, according to the given `strategy`.
+    ///
+    /// The selected outputs can be passed to [`Self::compose`]; the returned
+    /// value also lists other contracts having state on the selected outputs,
+    /// for which the composed batch will contain blank state transitions.
+    fn select_inputs(
+        &self,
+        invoice: &RgbInvoice,
+        available: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        strategy: InputSelection,
+    ) -> Result<
+        InputsSelected,
+        ComposeError<Self::Error, <<Self as Deref>::Target as Stash>::Error>,
+    >
+    where
+        Self::Error: From<<Self::Stash as Stash>::Error>,
+    {
+        let contract_id = invoice.contract.ok_or(ComposeError::NoContract)?;
+        let iface = invoice.iface.as_ref().ok_or(ComposeError::NoIface)?;
+        let builder =
+            self.transition_builder(contract_id, iface.clone(), invoice.operation.clone())?;
+        let assignment_name = invoice
+            .assignment
+            .as_ref()
+            .or_else(|| builder.default_assignment().ok())
+            .ok_or(BuilderError::NoDefaultAssignment)?
+            .clone();
+        let assignment_id = builder
+            .assignments_type(&assignment_name)
+            .ok_or(BuilderError::InvalidStateField(assignment_name.clone()))?;
+
+        let mut candidates = vec![];
+        for output in available.into_iter().map(|o| o.into()).collect::<BTreeSet<_>>() {
+            let mut value = 0u64;
+            let mut suitable = false;
+            for ((opout, _), state) in self.state_for_outpoints(contract_id, [output])? {
+                if opout.ty != assignment_id {
+                    continue;
+                }
+                match (&invoice.owned_state, state) {
+                    (InvoiceState::Amount(_), PersistedState::Amount(amount, _, _)) => {
+                        value = value
+                            .checked_add(amount.value())
+                            .ok_or(ComposeError::AmountOverflow)?;
+                        suitable = true;
+                    }
+                    (InvoiceState::Void, PersistedState::Void) => suitable = true,
+                    (
+                        InvoiceState::Data(NonFungible::RGB21(allocation)),
+                        PersistedState::Data(data, _),
+                    ) => {
+                        let Ok(alloc) =
+                            invoice::Allocation::from_strict_serialized::<U16>(data.to_inner())
+                        else {
+                            continue;
+                        };
+                        if alloc.token_index() == allocation.token_index() {
+                            value = value
+                                .checked_add(alloc.fraction())
+                                .ok_or(ComposeError::AmountOverflow)?;
+                            suitable = true;
+                        }
+                    }
+                    (InvoiceState::Attach(id), PersistedState::Attachment(attach, _))
+                        if attach.id == *id =>
+                    {
+                        suitable = true
+                    }
+                    _ => {}
+                }
+            }
+            if !suitable {
+                continue;
+            }
+            let mut others = self.contracts_by_outputs([output])?;
+            others.remove(&contract_id);
+            candidates.push(Candidate {
+                output,
+                value,
+                others,
+            });
+        }
+
+        let target = match invoice.owned_state {
+            InvoiceState::Amount(amount) => Some(amount.value()),
+            InvoiceState::Data(NonFungible::RGB21(allocation)) => Some(allocation.fraction()),
+            _ => None,
+        };
+        let (selected, total) = strategy
+            .select(candidates, target)
+            .ok_or(ComposeError::InsufficientState)?;
+        let state = match invoice.owned_state {
+            InvoiceState::Amount(_) => SelectedState::Amount(Amount::from(total)),
+            InvoiceState::Data(_) => SelectedState::Fraction(OwnedFraction::from(total)),
+            InvoiceState::Void | InvoiceState::Attach(_) => SelectedState::Unquantified,
+        };
+        let mut inputs = InputsSelected {
+            outputs: none!(),
+            state,
+            blanks: none!(),
+        };
+        for candidate in selected {
+            inputs.outputs.insert(candidate.output);
+            inputs.blanks.extend(candidate.others);
+        }
+        Ok(inputs)
+    }
+
     /// Composes a batch of state transitions updating state for the provided
     /// set of previous outputs, satisfying requirements of the invoice, paying
     /// the change back and including the necessary blank state transitions.
@@ -759,6 +1197,13 @@ pub trait Inventory: Deref<Target = Self::Stash> {
             .map(|o| o.into())
             .collect::<HashSet<XOutputSeal>>();
 
+        // Each seal and state salt gets its own blinding even if the blinder
+        // is deterministic, so the allocations of the same type never coincide
+        let blinding_no = Cell::new(0u64);
+        let next_blinding = |id: ContractId, assignment_type: AssignmentType| {
+            let no = blinding_no.replace(blinding_no.get() + 1);
+            seal_blinder(id, assignment_type).wrapping_add(no)
+        };
         #[allow(clippy::type_complexity)]
         let output_for_assignment = |id: ContractId,
                                      assignment_type: AssignmentType|
@@ -774,7 +1219,7 @@ pub trait Inventory: Deref<Target = Self::Stash> {
             let vout = allocator(id, assignment_type, velocity)
                 .ok_or(ComposeError::NoBlankOrChange(velocity, assignment_type))?;
             let seal =
-                GraphSeal::with_blinded_vout(method, vout, seal_blinder(id, assignment_type));
+                GraphSeal::with_blinded_vout(method, vout, next_blinding(id, assignment_type));
             Ok(BuilderSeal::Revealed(XChain::with(layer1, seal)))
         };
 
@@ -797,6 +1242,21 @@ pub trait Inventory: Deref<Target = Self::Stash> {
         let assignment_id = main_builder
             .assignments_type(&assignment_name)
             .ok_or(BuilderError::InvalidStateField(assignment_name.clone()))?;
+        let state_schema = self
+            .contract_schema(contract_id)?
+            .schema
+            .owned_types
+            .get(&assignment_id)
+            .ok_or(BuilderError::InvalidStateField(assignment_name.clone()))?;
+        if !matches!(
+            (&invoice.owned_state, state_schema),
+            (InvoiceState::Void, StateSchema::Declarative) |
+                (InvoiceState::Amount(_), StateSchema::Fungible(_)) |
+                (InvoiceState::Data(_), StateSchema::Structured(_)) |
+                (InvoiceState::Attach(_), StateSchema::Attachment(_))
+        ) {
+            return Err(ComposeError::StateMismatch(assignment_name));
+        }
 
         let layer1 = invoice.beneficiary.chain_network().layer1();
         let beneficiary = match (invoice.beneficiary.into_inner(), beneficiary_vout) {
@@ -819,7 +1279,9 @@ pub trait Inventory: Deref<Target = Self::Stash> {
         // 2. Prepare transition
         let mut main_inputs = Vec::<XOutputSeal>::new();
         let mut sum_inputs = Amount::ZERO;
+        let mut rights_inputs = 0usize;
         let mut data_inputs = vec![];
+        let mut attach_inputs = vec![];
         for ((opout, output), mut state) in
             self.state_for_outpoints(contract_id, prev_outputs.iter().cloned())?
         {
@@ -830,13 +1292,19 @@ pub trait Inventory: Deref<Target = Self::Stash> {
                 state.update_blinding(pedersen_blinder(contract_id, assignment_id));
                 main_builder = main_builder.add_owned_state_raw(opout.ty, seal, state)?;
             } else if let PersistedState::Amount(value, _, _) = state {
-                sum_inputs += value;
+                sum_inputs = sum_inputs
+                    .checked_add(value)
+                    .ok_or(ComposeError::AmountOverflow)?;
             } else if let PersistedState::Data(value, _) = state {
                 data_inputs.push(value);
+            } else if let PersistedState::Attachment(attach, _) = state {
+                attach_inputs.push(attach);
+            } else if let PersistedState::Void = state {
+                rights_inputs += 1;
             }
         }
         // Add change
-        let main_transition = match invoice.owned_state.clone() {
+        let main_builder = match invoice.owned_state.clone() {
             InvoiceState::Amount(amt) => {
                 match sum_inputs.cmp(&amt) {
                     Ordering::Greater => {
@@ -858,12 +1326,47 @@ pub trait Inventory: Deref<Target = Self::Stash> {
                         amt,
                         pedersen_blinder(contract_id, assignment_id),
                     )?
-                    .complete_transition()?
             }
             InvoiceState::Data(data) => match data {
                 NonFungible::RGB21(allocation) => {
-                    if !data_inputs.into_iter().any(|x| x == allocation.into()) {
-                        return Err(ComposeError::InsufficientState);
+                    let index = allocation.token_index();
+                    let mut owned = None::<u64>;
+                    for value in data_inputs {
+                        match invoice::Allocation::from_strict_serialized::<U16>(value.to_inner()) {
+                            Ok(alloc) if alloc.token_index() == index => {
+                                owned = owned
+                                    .unwrap_or_default()
+                                    .checked_add(alloc.fraction())
+                                    .map(Some)
+                                    .ok_or(ComposeError::AmountOverflow)?;
+                            }
+                            // Allocations of other tokens are carried over
+                            _ => {
+                                let seal = output_for_assignment(contract_id, assignment_id)?;
+                                let salt = next_blinding(contract_id, assignment_id);
+                                let state = PersistedState::Data(value, salt.into());
+                                main_builder =
+                                    main_builder.add_owned_state_raw(assignment_id, seal, state)?;
+                            }
+                        }
+                    }
+                    let owned = owned.ok_or(ComposeError::TokenNotOwned(index.into()))?;
+                    match owned.cmp(&allocation.fraction()) {
+                        Ordering::Greater => {
+                            let seal = output_for_assignment(contract_id, assignment_id)?;
+                            let change = invoice::Allocation::with(
+                                index,
+                                owned - allocation.fraction(),
+                            );
+                            main_builder = main_builder.add_data_raw(
+                                assignment_id,
+                                seal,
+                                change,
+                                next_blinding(contract_id, assignment_id),
+                            )?;
+                        }
+                        Ordering::Less => return Err(ComposeError::InsufficientState),
+                        Ordering::Equal => {}
                     }
 
                     main_builder
@@ -871,51 +1374,849 @@ pub trait Inventory: Deref<Target = Self::Stash> {
                             assignment_id,
                             beneficiary,
                             allocation,
-                            seal_blinder(contract_id, assignment_id),
+                            next_blinding(contract_id, assignment_id),
                         )?
-                        .complete_transition()?
                 }
             },
-            _ => {
-                todo!("only TypedState::Amount and TypedState::Allocation are currently supported")
-            }
-        };
-
-        // 3. Prepare other transitions
-        // Enumerate state
-        let mut spent_state =
-            HashMap::<ContractId, BTreeMap<(Opout, XOutputSeal), PersistedState>>::new();
-        for output in prev_outputs {
-            for id in self.contracts_by_outputs([output])? {
-                if id == contract_id {
-                    continue;
+            InvoiceState::Void => {
+                if rights_inputs == 0 {
+                    return Err(ComposeError::InsufficientState);
                 }
-                spent_state
-                    .entry(id)
-                    .or_default()
-                    .extend(self.state_for_outpoints(id, [output])?);
+                // A single right goes to the beneficiary, while each of the
+                // remaining rights of the same type is returned as change
+                for _ in 1..rights_inputs {
+                    let seal = output_for_assignment(contract_id, assignment_id)?;
+                    main_builder = main_builder.add_owned_state_raw(
+                        assignment_id,
+                        seal,
+                        PersistedState::Void,
+                    )?;
+                }
+                main_builder
+                    .add_owned_state_raw(assignment_id, beneficiary, PersistedState::Void)?
             }
-        }
-        // Construct blank transitions
-        let mut blanks = Confined::<Vec<_>, 0, { U24 - 1 }>::with_capacity(spent_state.len());
-        for (id, opouts) in spent_state {
-            let mut blank_builder = self.blank_builder(id, iface.clone())?;
-            let mut outputs = Vec::with_capacity(opouts.len());
-            for ((opout, output), state) in opouts {
-                let seal = output_for_assignment(id, opout.ty)?;
-                outputs.push(output);
-                blank_builder = blank_builder
-                    .add_input(opout, state.clone())?
-                    .add_owned_state_raw(opout.ty, seal, state)?;
+            InvoiceState::Attach(attach_id) => {
+                let pos = attach_inputs
+                    .iter()
+                    .position(|attach| attach.id == attach_id)
+                    .ok_or(ComposeError::InsufficientState)?;
+                let attach = attach_inputs.remove(pos);
+                for change in attach_inputs {
+                    let seal = output_for_assignment(contract_id, assignment_id)?;
+                    let salt = next_blinding(contract_id, assignment_id);
+                    let state = PersistedState::Attachment(change, salt);
+                    main_builder = main_builder.add_owned_state_raw(assignment_id, seal, state)?;
+                }
+                let state =
+                    PersistedState::Attachment(attach, next_blinding(contract_id, assignment_id));
+                main_builder
+                    .add_owned_state_raw(assignment_id, beneficiary, state)?
             }
+        };
+        let main_transition = complete_validated(self, main_builder)?;
 
-            let transition = blank_builder.complete_transition()?;
-            blanks.push(TransitionInfo::new(transition, outputs)?)?;
-        }
+        // 3. Prepare other transitions
+        let blanks =
+            compose_blanks(self, contract_id, iface, prev_outputs, output_for_assignment)?;
 
         Ok(Batch {
             main: TransitionInfo::new(main_transition, main_inputs)?,
             blanks,
         })
     }
+
+    /// Composes a batch consolidating all the state of the contract assigned
+    /// to the provided set of previous outputs: fungible state of each type is
+    /// merged into a single assignment, while rights, data and attachments are
+    /// carried over one-to-one with fresh salts. The new seals are provided by
+    /// the `allocator`, thus the same method can be used for a self-transfer
+    /// of the contract state to new outputs.
+    ///
+    /// The state of other contracts assigned to the same outputs is moved with
+    /// blank state transitions.
+    fn compose_consolidation(
+        &self,
+        contract_id: ContractId,
+        prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        method: CloseMethod,
+        allocator: impl Fn(ContractId, AssignmentType, VelocityHint) -> Option<Vout>,
+    ) -> Result<Batch, ComposeError<Self::Error, <<Self as Deref>::Target as Stash>::Error>>
+    where
+        Self::Error: From<<Self::Stash as Stash>::Error>,
+    {
+        let prev_outputs = prev_outputs
+            .into_iter()
+            .map(|o| o.into())
+            .collect::<BTreeSet<XOutputSeal>>();
+        let layer1 = prev_outputs
+            .first()
+            .ok_or(ComposeError::InsufficientState)?
+            .layer1();
+        let output_for_assignment = |id: ContractId, assignment_type: AssignmentType| {
+            change_seal(self, &allocator, method, layer1, id, assignment_type)
+        };
+
+        let state = self.state_for_outpoints(contract_id, prev_outputs.iter().cloned())?;
+        if state.is_empty() {
+            return Err(ComposeError::InsufficientState);
+        }
+        let iface = blank_iface(self, contract_id, None)?;
+        let main = consolidate(self, contract_id, &iface, state, output_for_assignment)?;
+        let blanks =
+            compose_blanks(self, contract_id, &iface, prev_outputs, output_for_assignment)?;
+
+        Ok(Batch { main, blanks })
+    }
+
+    /// Composes a batch moving the state of all contracts assigned to the
+    /// provided set of previous outputs to the new seals provided by the
+    /// `allocator`, such that the previous outputs can be spent without
+    /// affecting RGB state. The state of each contract is consolidated in the
+    /// same way as by [`Self::compose_consolidation`].
+    fn compose_self_transfer(
+        &self,
+        prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        method: CloseMethod,
+        allocator: impl Fn(ContractId, AssignmentType, VelocityHint) -> Option<Vout>,
+    ) -> Result<Batch, ComposeError<Self::Error, <<Self as Deref>::Target as Stash>::Error>>
+    where
+        Self::Error: From<<Self::Stash as Stash>::Error>,
+    {
+        let prev_outputs = prev_outputs
+            .into_iter()
+            .map(|o| o.into())
+            .collect::<BTreeSet<XOutputSeal>>();
+        let layer1 = prev_outputs
+            .first()
+            .ok_or(ComposeError::InsufficientState)?
+            .layer1();
+        let output_for_assignment = |id: ContractId, assignment_type: AssignmentType| {
+            change_seal(self, &allocator, method, layer1, id, assignment_type)
+        };
+
+        let mut transitions = vec![];
+        for contract_id in self.contracts_by_outputs(prev_outputs.iter().cloned())? {
+            let state = self.state_for_outpoints(contract_id, prev_outputs.iter().cloned())?;
+            let iface = blank_iface(self, contract_id, None)?;
+            transitions.push(consolidate(self, contract_id, &iface, state, output_for_assignment)?);
+        }
+        let mut transitions = transitions.into_iter();
+        let main = transitions.next().ok_or(ComposeError::InsufficientState)?;
+        let blanks = Confined::try_from_iter(transitions)?;
+
+        Ok(Batch { main, blanks })
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::result_large_err)]
+pub(crate) fn compose_blanks<I: Inventory + ?Sized>(
+    inventory: &I,
+    contract_id: ContractId,
+    iface: &TypeName,
+    prev_outputs: impl IntoIterator<Item = XOutputSeal>,
+    output_for_assignment: impl Fn(
+        ContractId,
+        AssignmentType,
+    ) -> Result<
+        BuilderSeal<GraphSeal>,
+        ComposeError<I::Error, <I::Stash as Stash>::Error>,
+    >,
+) -> Result<
+    Confined<Vec<TransitionInfo>, 0, { U24 - 1 }>,
+    ComposeError<I::Error, <I::Stash as Stash>::Error>,
+>
+where
+    I::Error: From<<I::Stash as Stash>::Error>,
+{
+    // Enumerate state
+    let mut spent_state =
+        HashMap::<ContractId, BTreeMap<(Opout, XOutputSeal), PersistedState>>::new();
+    for output in prev_outputs {
+        for id in inventory.contracts_by_outputs([output])? {
+            if id == contract_id {
+                continue;
+            }
+            spent_state
+                .entry(id)
+                .or_default()
+                .extend(inventory.state_for_outpoints(id, [output])?);
+        }
+    }
+    // Construct blank transitions
+    let mut blanks = Confined::<Vec<_>, 0, { U24 - 1 }>::with_capacity(spent_state.len());
+    for (id, opouts) in spent_state {
+        blanks.push(blank_transition(inventory, id, iface, opouts, &output_for_assignment)?)?;
+    }
+    Ok(blanks)
+}
+
+/// Checks state extension against the contract schema and ensures that all
+/// the valencies it redeems are defined by the contract genesis or state
+/// extensions known to the inventory.
+#[allow(clippy::result_large_err)]
+pub(crate) fn validate_extension<I: Inventory + ?Sized>(
+    inventory: &I,
+    extension: &Extension,
+) -> Result<(), InventoryError<I::Error>>
+where
+    I::Error: From<<I::Stash as Stash>::Error>,
+{
+    let contract_id = extension.contract_id;
+    let opid = extension.id();
+    let schema = &inventory.contract_schema(contract_id)?.schema;
+    if !schema.extensions.contains_key(&extension.extension_type) {
+        return Err(DataError::UnknownExtension(contract_id, extension.extension_type).into());
+    }
+    let genesis = inventory.genesis(contract_id)?;
+    let extension_ids = inventory
+        .extension_ids()
+        .map_err(StashError::Connectivity)?;
+
+    let mut prev_ops = Vec::with_capacity(extension.redeemed.len());
+    for (valency, prev_id) in &extension.redeemed {
+        let (valency, prev_id) = (*valency, *prev_id);
+        let prev_op = if prev_id == genesis.id() {
+            Some(OpRef::Genesis(genesis))
+        } else if extension_ids.contains(&prev_id) {
+            Some(inventory.extension(prev_id)?)
+                .filter(|prev| prev.contract_id == contract_id)
+                .map(OpRef::Extension)
+        } else {
+            None
+        };
+        let Some(prev_op) = prev_op else {
+            let failure = validation::Failure::ValencyNoParent {
+                opid,
+                prev_id,
+                valency,
+            };
+            return Err(DataError::InvalidExtension(failure).into());
+        };
+        if !prev_op.valencies().contains(&valency) {
+            let failure = validation::Failure::NoPrevValency {
+                opid,
+                prev_id,
+                valency,
+            };
+            return Err(DataError::InvalidExtension(failure).into());
+        }
+        prev_ops.push(prev_op);
+    }
+
+    let asset_tags = inventory.contract_asset_tags(contract_id)?.clone();
+    let status = validate_operation(
+        schema,
+        asset_tags.into_inner(),
+        genesis,
+        prev_ops,
+        OpRef::Extension(extension),
+    );
+    if let Some(failure) = status.failures.into_iter().next() {
+        return Err(DataError::InvalidExtension(failure).into());
+    }
+    Ok(())
+}
+
+/// Completes the state transition, running the schema validation script
+/// against it. The operations which state is spent by the transition are
+/// taken from the inventory.
+#[allow(clippy::result_large_err)]
+pub(crate) fn complete_validated<I: Inventory + ?Sized>(
+    inventory: &I,
+    builder: TransitionBuilder,
+) -> Result<Transition, ComposeError<I::Error, <I::Stash as Stash>::Error>>
+where
+    I::Error: From<<I::Stash as Stash>::Error>,
+{
+    let genesis = inventory.genesis(builder.contract_id())?;
+    let extension_ids = inventory
+        .extension_ids()
+        .map_err(StashError::Connectivity)?;
+    let mut prev_ops = Vec::new();
+    for opid in builder.input_ops() {
+        if opid == genesis.id() {
+            continue;
+        } else if extension_ids.contains(&opid) {
+            prev_ops.push(OpRef::Extension(inventory.extension(opid)?));
+        } else {
+            prev_ops.push(OpRef::Transition(inventory.transition(opid)?));
+        }
+    }
+    Ok(builder.complete_transition_validated(genesis, prev_ops)?)
+}
+
+/// Constructs blank transition moving the provided contract state to new
+/// seals without changing it.
+#[allow(clippy::type_complexity, clippy::result_large_err)]
+pub(crate) fn blank_transition<I: Inventory + ?Sized>(
+    inventory: &I,
+    contract_id: ContractId,
+    iface: &TypeName,
+    state: BTreeMap<(Opout, XOutputSeal), PersistedState>,
+    output_for_assignment: impl Fn(
+        ContractId,
+        AssignmentType,
+    ) -> Result<
+        BuilderSeal<GraphSeal>,
+        ComposeError<I::Error, <I::Stash as Stash>::Error>,
+    >,
+) -> Result<TransitionInfo, ComposeError<I::Error, <I::Stash as Stash>::Error>>
+where
+    I::Error: From<<I::Stash as Stash>::Error>,
+{
+    let blank_iface = blank_iface(inventory, contract_id, Some(iface))?;
+    let mut blank_builder = inventory.blank_builder(contract_id, blank_iface)?;
+    let mut outputs = Vec::with_capacity(state.len());
+    for ((opout, output), state) in state {
+        let seal = output_for_assignment(contract_id, opout.ty)?;
+        outputs.push(output);
+        blank_builder = blank_builder
+            .add_input(opout, state.clone())?
+            .add_owned_state_raw(opout.ty, seal, state)?;
+    }
+
+    let transition = complete_validated(inventory, blank_builder)?;
+    Ok(TransitionInfo::new(transition, outputs)?)
+}
+
+/// Selects interface for constructing a blank transition of the contract.
+///
+/// Blank transitions are defined by each schema, thus any interface which is
+/// implemented by the contract can be used for them, and the implementation
+/// doesn't need to name the assignment types moved by the transition. The
+/// `preferred` interface is used if it is implemented by the contract;
+/// otherwise the first implemented interface in the order of interface ids is
+/// selected.
+#[allow(clippy::result_large_err)]
+fn blank_iface<I: Inventory + ?Sized>(
+    inventory: &I,
+    contract_id: ContractId,
+    preferred: Option<&TypeName>,
+) -> Result<TypeName, ComposeError<I::Error, <I::Stash as Stash>::Error>>
+where
+    I::Error: From<<I::Stash as Stash>::Error>,
+{
+    let schema_ifaces = inventory.contract_schema(contract_id)?;
+    let mut candidates = vec![];
+    for iface_id in schema_ifaces.iimpls.keys() {
+        candidates.push(inventory.iface_by_id(*iface_id)?.name.clone());
+    }
+    if let Some(name) = preferred.filter(|name| candidates.contains(name)) {
+        return Ok(name.clone());
+    }
+    candidates
+        .into_iter()
+        .next()
+        .ok_or(ComposeError::NoBlankIface(contract_id))
+}
+
+/// Constructs blank transition spending the provided contract state and
+/// merging fungible state of the same type.
+#[allow(clippy::type_complexity, clippy::result_large_err)]
+fn consolidate<I: Inventory + ?Sized>(
+    inventory: &I,
+    contract_id: ContractId,
+    iface: &TypeName,
+    state: BTreeMap<(Opout, XOutputSeal), PersistedState>,
+    output_for_assignment: impl Fn(
+        ContractId,
+        AssignmentType,
+    ) -> Result<
+        BuilderSeal<GraphSeal>,
+        ComposeError<I::Error, <I::Stash as Stash>::Error>,
+    >,
+) -> Result<TransitionInfo, ComposeError<I::Error, <I::Stash as Stash>::Error>>
+where
+    I::Error: From<<I::Stash as Stash>::Error>,
+{
+    let mut builder = inventory.blank_builder(contract_id, iface.clone())?;
+    let mut inputs = Vec::with_capacity(state.len());
+    let mut amounts = BTreeMap::<AssignmentType, Amount>::new();
+    for ((opout, output), state) in state {
+        builder = builder.add_input(opout, state.clone())?;
+        inputs.push(output);
+        match state {
+            PersistedState::Amount(value, _, _) => {
+                let sum = amounts.entry(opout.ty).or_default();
+                *sum = sum.checked_add(value).ok_or(ComposeError::AmountOverflow)?;
+            }
+            // The rest of the state is carried over with fresh salts, such
+            // that it can't be linked to the spent state
+            PersistedState::Void => {
+                let seal = output_for_assignment(contract_id, opout.ty)?;
+                builder = builder.add_owned_state_raw(opout.ty, seal, PersistedState::Void)?;
+            }
+            PersistedState::Data(value, _) => {
+                let seal = output_for_assignment(contract_id, opout.ty)?;
+                let state = PersistedState::Data(value, rand::random());
+                builder = builder.add_owned_state_raw(opout.ty, seal, state)?;
+            }
+            PersistedState::Attachment(attach, _) => {
+                let seal = output_for_assignment(contract_id, opout.ty)?;
+                let state = PersistedState::Attachment(attach, rand::random());
+                builder = builder.add_owned_state_raw(opout.ty, seal, state)?;
+            }
+        }
+    }
+    for (assignment_type, amount) in amounts {
+        let seal = output_for_assignment(contract_id, assignment_type)?;
+        let blinding = BlindingFactor::random();
+        builder = builder.add_fungible_state_raw(assignment_type, seal, amount, blinding)?;
+    }
+
+    let transition = complete_validated(inventory, builder)?;
+    Ok(TransitionInfo::new(transition, inputs)?)
+}
+
+#[allow(clippy::type_complexity, clippy::result_large_err)]
+pub(crate) fn change_seal<I: Inventory + ?Sized>(
+    inventory: &I,
+    allocator: &impl Fn(ContractId, AssignmentType, VelocityHint) -> Option<Vout>,
+    method: CloseMethod,
+    layer1: Layer1,
+    id: ContractId,
+    assignment_type: AssignmentType,
+) -> Result<BuilderSeal<GraphSeal>, ComposeError<I::Error, <I::Stash as Stash>::Error>> {
+    let suppl = inventory.contract_suppl(id);
+    let velocity = suppl
+        .and_then(|suppl| suppl.owned_state.get(&assignment_type))
+        .map(|s| s.velocity)
+        .unwrap_or_default();
+    let vout = allocator(id, assignment_type, velocity)
+        .ok_or(ComposeError::NoBlankOrChange(velocity, assignment_type))?;
+    let seal = GraphSeal::with_blinded_vout(method, vout, rand::random());
+    Ok(BuilderSeal::Revealed(XChain::with(layer1, seal)))
+}
+
+#[cfg(test)]
+mod test {
+    use bp::dbc::Method;
+    use invoice::{RgbInvoiceBuilder, XChainNet};
+    use rgb::{AttachId, ExposedSeal, GenesisSeal, TypedAssigns};
+
+    use super::*;
+    use crate::fixtures::{
+        claim_contract, consume, output, rgb20_builder, rgb20_contract, rgb20_transfer,
+        rgb21_builder, rights_builder, rights_iface, spent_state, stock_with, witness_outputs,
+        TestRgb21,
+    };
+    use crate::interface::rgb21::Allocation;
+    use crate::interface::{Rgb20, Rgb21};
+    use crate::persistence::Stock;
+
+    fn seal(no: u8, blinding: u64) -> XChain<GenesisSeal> {
+        let txid = Txid::from([no; 32]);
+        XChain::Bitcoin(GenesisSeal::with_blinding(Method::TapretFirst, txid, 0, blinding))
+    }
+
+    fn allocator(_: ContractId, _: AssignmentType, _: VelocityHint) -> Option<Vout> {
+        Some(Vout::from_u32(0))
+    }
+
+    /// Salts of the revealed structured state assignments.
+    fn data_salts<Seal: ExposedSeal>(assigns: Option<&TypedAssigns<Seal>>) -> BTreeSet<u128> {
+        assigns
+            .map(TypedAssigns::as_structured)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|assign| assign.as_revealed_state())
+            .map(|data| data.salt)
+            .collect()
+    }
+
+    fn allocation(index: u32, fraction: u64) -> Allocation {
+        Allocation::with(TokenIndex::from(index), OwnedFraction::from(fraction))
+    }
+
+    /// Invoice for the `state` of the `assignment` type paid to a blinded
+    /// seal, which the composer can't confuse with any of the change seals.
+    fn invoice(
+        contract_id: ContractId,
+        iface: &'static str,
+        assignment: &'static str,
+        state: InvoiceState,
+    ) -> RgbInvoice {
+        let seal = GraphSeal::with_blinded_vout(Method::TapretFirst, Vout::from_u32(1), 0);
+        let beneficiary = XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(seal.conceal()));
+        let mut invoice = RgbInvoiceBuilder::with(contract_id, beneficiary)
+            .set_interface(iface)
+            .set_assignment(assignment)
+            .finish();
+        invoice.owned_state = state;
+        invoice
+    }
+
+    /// Composes a transfer for the `invoice` with deterministic blinders,
+    /// which return the same value for each assignment type.
+    fn compose(stock: &Stock, invoice: &RgbInvoice, prev_outputs: &[XOutputSeal]) -> Batch {
+        stock
+            .compose_deterministic(
+                invoice,
+                prev_outputs.iter().copied(),
+                CloseMethod::OpretFirst,
+                None::<Vout>,
+                allocator,
+                |_, _| BlindingFactor::EMPTY,
+                |_, _| 0,
+            )
+            .unwrap()
+    }
+
+    /// Number of the transition assignments of the type `name` and the number
+    /// of distinct seals they are assigned to.
+    fn assigned_seals(stock: &Stock, info: &TransitionInfo, name: &'static str) -> (usize, usize) {
+        let schema = stock.contract_schema(info.transition.contract_id).unwrap();
+        let iimpl = schema.iimpls.values().next().unwrap();
+        let ty = iimpl.assignments_type(&fname!(name)).unwrap();
+        let seals = info
+            .transition
+            .assignments
+            .get(&ty)
+            .map(|assigns| assigns.to_confidential_seals())
+            .unwrap_or_default();
+        let count = seals.len();
+        (count, seals.into_iter().collect::<BTreeSet<_>>().len())
+    }
+
+    #[test]
+    fn compose_rights() {
+        let contract = rights_builder()
+            .add_rights("right", seal(1, 1))
+            .unwrap()
+            .add_rights("right", seal(1, 2))
+            .unwrap()
+            .add_rights("right", seal(1, 3))
+            .unwrap()
+            .issue_contract_det(1_700_000_000)
+            .unwrap();
+        let contract_id = contract.contract_id();
+        let stock = stock_with([contract]);
+
+        let invoice = invoice(contract_id, "TestRights", "right", InvoiceState::Void);
+        let batch = compose(&stock, &invoice, &[output(1)]);
+        assert_eq!(spent_state(&stock, &batch.main).len(), 3);
+        // One right to the beneficiary and two change rights
+        assert_eq!(assigned_seals(&stock, &batch.main, "right"), (3, 3));
+        assert!(batch.blanks.is_empty());
+    }
+
+    #[test]
+    fn compose_rgb21_multi_token() {
+        let contract = rgb21_builder::<TestRgb21>([1, 2, 3])
+            .add_data("assetOwner", seal(1, 1), allocation(1, 1))
+            .unwrap()
+            .add_data("assetOwner", seal(1, 2), allocation(2, 1))
+            .unwrap()
+            .add_data("assetOwner", seal(1, 3), allocation(3, 1))
+            .unwrap()
+            .issue_contract_det(1_700_000_000)
+            .unwrap();
+        let contract_id = contract.contract_id();
+        let mut stock = stock_with([contract]);
+
+        let state = InvoiceState::Data(NonFungible::RGB21(invoice::Allocation::with(2, 1)));
+        let invoice = invoice(contract_id, "RGB21", "assetOwner", state);
+        let selected = stock
+            .select_inputs(&invoice, [output(1), output(2)], InputSelection::SmallestFirst)
+            .unwrap();
+        assert_eq!(selected.outputs, bset![output(1)]);
+        assert_eq!(selected.state, SelectedState::Fraction(OwnedFraction::from(1)));
+
+        let batch = compose(&stock, &invoice, &[output(1)]);
+        // The invoiced token to the beneficiary and two other tokens carried
+        // over to the change outputs
+        assert_eq!(assigned_seals(&stock, &batch.main, "assetOwner"), (3, 3));
+
+        consume(&mut stock, 3, batch.into_iter().map(|info| info.transition));
+        let rgb21 = stock.contract_iface_wrapped::<Rgb21>(contract_id).unwrap();
+        let owned = rgb21.owned_fractions(witness_outputs(3)).unwrap();
+        assert_eq!(owned.keys().copied().collect::<Vec<_>>(), vec![
+            TokenIndex::from(1),
+            TokenIndex::from(3)
+        ]);
+    }
+
+    #[test]
+    fn compose_blank_unnamed_state() {
+        let rgb20 = rgb20_builder()
+            .add_fungible_state("assetOwner", seal(1, 1), 100)
+            .unwrap()
+            .add_global_state("issuedSupply", Amount::from(100u64))
+            .unwrap()
+            .issue_contract_det(1_700_000_000)
+            .unwrap();
+        let mut rights = rights_builder()
+            .add_rights("right", seal(1, 2))
+            .unwrap()
+            .issue_contract_det(1_700_000_000)
+            .unwrap();
+        // The implementation known to the wallet doesn't name the rights, which
+        // must not prevent moving them with a blank transition
+        let iface_id = rights_iface().iface_id();
+        let iimpl = &mut rights.ifaces.get_mut(&iface_id).unwrap().iimpl;
+        iimpl.assignments = Confined::try_from_iter(
+            iimpl
+                .assignments
+                .iter()
+                .filter(|field| field.name != fname!("right"))
+                .cloned(),
+        )
+        .unwrap();
+        let (rgb20_id, rights_id) = (rgb20.contract_id(), rights.contract_id());
+        let stock = stock_with([rgb20, rights]);
+
+        let state = InvoiceState::Amount(Amount::from(100u64));
+        let invoice = invoice(rgb20_id, "RGB20", "assetOwner", state);
+        let batch = compose(&stock, &invoice, &[output(1)]);
+        assert_eq!(batch.blanks.len(), 1);
+        let blank = &batch.blanks[0].transition;
+        assert_eq!(blank.contract_id, rights_id);
+        assert_eq!(blank.inputs.len(), 1);
+        assert_eq!(blank.assignments.values().map(|a| a.len_u16()).sum::<u16>(), 1);
+    }
+
+    // NB: rgb-core rejects any operation assigning revealed attachments, so
+    // a contract owning documents can't be issued and only the case of the
+    // missing attachment can be tested
+    #[test]
+    fn compose_attachment_absent() {
+        let contract = rights_builder()
+            .add_rights("right", seal(1, 1))
+            .unwrap()
+            .issue_contract_det(1_700_000_000)
+            .unwrap();
+        let contract_id = contract.contract_id();
+        let stock = stock_with([contract]);
+
+        let state = InvoiceState::Attach(AttachId::from([1; 32]));
+        let invoice = invoice(contract_id, "TestRights", "document", state);
+        assert!(matches!(
+            stock.compose_deterministic(
+                &invoice,
+                [output(1)],
+                CloseMethod::OpretFirst,
+                None::<Vout>,
+                allocator,
+                |_, _| BlindingFactor::EMPTY,
+                |_, _| 0,
+            ),
+            Err(ComposeError::InsufficientState)
+        ));
+    }
+
+    #[test]
+    fn compose_consolidation() {
+        let contract = rgb20_builder()
+            .add_fungible_state("assetOwner", seal(1, 1), 100)
+            .unwrap()
+            .add_fungible_state("assetOwner", seal(1, 2), 200)
+            .unwrap()
+            .add_rights("burnRight", seal(1, 3))
+            .unwrap()
+            .add_rights("burnRight", seal(1, 4))
+            .unwrap()
+            .add_global_state("issuedSupply", Amount::from(300u64))
+            .unwrap()
+            .issue_contract_det(1_700_000_000)
+            .unwrap();
+        let contract_id = contract.contract_id();
+        let mut stock = stock_with([contract]);
+
+        let batch = stock
+            .compose_consolidation(contract_id, [output(1)], CloseMethod::OpretFirst, allocator)
+            .unwrap();
+        assert_eq!(spent_state(&stock, &batch.main).len(), 4);
+        assert!(batch.blanks.is_empty());
+        assert_eq!(assigned_seals(&stock, &batch.main, "assetOwner"), (1, 1));
+        assert_eq!(assigned_seals(&stock, &batch.main, "burnRight"), (2, 2));
+
+        consume(&mut stock, 3, batch.into_iter().map(|info| info.transition));
+        let rgb20 = stock.contract_iface_wrapped::<Rgb20>(contract_id).unwrap();
+        assert_eq!(rgb20.balance(witness_outputs(3)).unwrap(), Amount::from(300u64));
+        assert_eq!(rgb20.burn_right(witness_outputs(3)).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn compose_self_transfer() {
+        let rgb20 = rgb20_builder()
+            .add_fungible_state("assetOwner", seal(1, 1), 100)
+            .unwrap()
+            .add_global_state("issuedSupply", Amount::from(100u64))
+            .unwrap()
+            .issue_contract_det(1_700_000_000)
+            .unwrap();
+        let rgb21 = rgb21_builder::<TestRgb21>([1, 2])
+            .add_data("assetOwner", seal(1, 2), allocation(1, 1))
+            .unwrap()
+            .add_data("assetOwner", seal(1, 3), allocation(2, 1))
+            .unwrap()
+            .issue_contract_det(1_700_000_000)
+            .unwrap();
+        let (rgb20_id, rgb21_id) = (rgb20.contract_id(), rgb21.contract_id());
+        let asset_owner = AssignmentType::with(4000);
+        let genesis_salts = data_salts(rgb21.genesis.assignments.get(&asset_owner));
+        let mut stock = stock_with([rgb20, rgb21]);
+
+        let batch = stock
+            .compose_self_transfer([output(1)], CloseMethod::OpretFirst, allocator)
+            .unwrap();
+        assert_eq!(batch.blanks.len(), 1);
+        let transitions = batch.into_iter().map(|info| info.transition).collect::<Vec<_>>();
+        let rgb21_transition = transitions
+            .iter()
+            .find(|transition| transition.contract_id == rgb21_id)
+            .unwrap();
+        let transfer_salts = data_salts(rgb21_transition.assignments.get(&asset_owner));
+        assert_eq!(transfer_salts.len(), 2);
+        assert!(transfer_salts.is_disjoint(&genesis_salts));
+
+        consume(&mut stock, 3, transitions);
+        let rgb20 = stock.contract_iface_wrapped::<Rgb20>(rgb20_id).unwrap();
+        assert_eq!(rgb20.balance(witness_outputs(3)).unwrap(), Amount::from(100u64));
+        let rgb21 = stock.contract_iface_wrapped::<Rgb21>(rgb21_id).unwrap();
+        let owned = rgb21.owned_fractions(witness_outputs(3)).unwrap();
+        assert_eq!(owned.keys().copied().collect::<Vec<_>>(), vec![
+            TokenIndex::from(1),
+            TokenIndex::from(2)
+        ]);
+    }
+
+    #[test]
+    fn extension_builder() {
+        let contract = claim_contract();
+        let (contract_id, genesis_id) = (contract.contract_id(), contract.genesis.id());
+        let stock = stock_with([contract]);
+
+        let err = stock
+            .extension_builder(contract_id, "TestClaim", "airdrop")
+            .unwrap_err();
+        assert!(matches!(err, InventoryError::DataError(DataError::NoExtension(..))));
+
+        let builder = stock
+            .extension_builder(contract_id, "TestClaim", "claim")
+            .unwrap();
+        let err = builder
+            .clone()
+            .redeem_valency("unknown", genesis_id)
+            .unwrap_err();
+        assert_eq!(err, BuilderError::ValencyNotFound(fname!("unknown")));
+
+        let extension = builder
+            .redeem_valency("claim", genesis_id)
+            .unwrap()
+            .add_rights("claimRight", seal(1, 1))
+            .unwrap()
+            .complete_extension()
+            .unwrap();
+        assert_eq!(extension.contract_id, contract_id);
+        assert_eq!(extension.redeemed.values().copied().collect::<Vec<_>>(), vec![genesis_id]);
+        assert_eq!(extension.assignments.len(), 1);
+    }
+
+    #[test]
+    fn import_extension() {
+        let contract = claim_contract();
+        let (contract_id, genesis_id) = (contract.contract_id(), contract.genesis.id());
+        let mut stock = stock_with([contract]);
+
+        let extension = stock
+            .extension_builder(contract_id, "TestClaim", "claim")
+            .unwrap()
+            .redeem_valency("claim", genesis_id)
+            .unwrap()
+            .add_rights("claimRight", seal(1, 1))
+            .unwrap()
+            .complete_extension()
+            .unwrap();
+        let opid = extension.id();
+        stock
+            .import_extension(extension, XChain::Bitcoin(Txid::from([3; 32])))
+            .unwrap();
+        assert!(stock.extension_ids().unwrap().contains(&opid));
+    }
+
+    #[test]
+    fn import_extension_invalid() {
+        let contract = claim_contract();
+        let (contract_id, genesis_id) = (contract.contract_id(), contract.genesis.id());
+        let mut stock = stock_with([contract]);
+        let witness_id = XChain::Bitcoin(Txid::from([3; 32]));
+        let builder = stock
+            .extension_builder(contract_id, "TestClaim", "claim")
+            .unwrap();
+
+        // redeemed operation is not known
+        let extension = builder
+            .clone()
+            .redeem_valency("claim", OpId::from([1u8; 32]))
+            .unwrap()
+            .add_rights("claimRight", seal(1, 1))
+            .unwrap()
+            .complete_extension()
+            .unwrap();
+        let err = stock.import_extension(extension, witness_id).unwrap_err();
+        assert!(matches!(
+            err,
+            InventoryError::DataError(DataError::InvalidExtension(
+                validation::Failure::ValencyNoParent { .. }
+            ))
+        ));
+
+        // redeemed valency is not defined by the genesis
+        let extension = builder
+            .clone()
+            .redeem_valency("bonus", genesis_id)
+            .unwrap()
+            .add_rights("claimRight", seal(1, 1))
+            .unwrap()
+            .complete_extension()
+            .unwrap();
+        let err = stock.import_extension(extension, witness_id).unwrap_err();
+        assert!(matches!(
+            err,
+            InventoryError::DataError(DataError::InvalidExtension(
+                validation::Failure::NoPrevValency { .. }
+            ))
+        ));
+
+        // required assignment is missing
+        let extension = builder
+            .redeem_valency("claim", genesis_id)
+            .unwrap()
+            .complete_extension()
+            .unwrap();
+        let err = stock.import_extension(extension, witness_id).unwrap_err();
+        assert!(matches!(err, InventoryError::DataError(DataError::InvalidExtension(..))));
+
+        assert!(stock.extension_ids().unwrap().is_empty());
+    }
+
+    #[test]
+    fn outgoing_consignments() {
+        let contract = rgb20_contract([(1, 600)]);
+        let contract_id = contract.contract_id();
+        let mut stock = stock_with([contract]);
+        let transition = rgb20_transfer(&stock, contract_id, 1, [(0, 100), (1, 500)]);
+        let opid = transition.id();
+        let witness_id = consume(&mut stock, 3, [transition]);
+
+        let consignments = stock.outgoing_consignments(witness_id).unwrap();
+        assert_eq!(consignments.keys().copied().collect::<Vec<_>>(), vec![contract_id]);
+        let consignment = &consignments[&contract_id];
+        assert!(consignment.transfer);
+        assert_eq!(consignment.bundles.len(), 1);
+        let anchored_bundle = consignment.bundles.iter().next().unwrap();
+        assert_eq!(anchored_bundle.anchor.witness_id(), Some(witness_id));
+        assert!(anchored_bundle.bundle.known_transitions.contains_key(&opid));
+        let transfer = stock.outgoing_transfer(witness_id).unwrap().unwrap();
+        assert_eq!(transfer.status, TransferStatus::Consigned);
+
+        let unknown = XChain::Bitcoin(Txid::from([4; 32]));
+        assert!(matches!(
+            stock.outgoing_consignments(unknown),
+            Err(ConsignerError::InventoryError(InventoryError::DataError(
+                DataError::UnknownTransfer(_)
+            )))
+        ));
+    }
 }
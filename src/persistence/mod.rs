@@ -37,12 +37,19 @@ mod inventory;
 pub mod stock;
 pub mod hoard;
 mod state;
+mod selection;
+mod policy;
+mod transfers;
 
 pub use hoard::Hoard;
+pub(crate) use inventory::{blank_transition, change_seal, complete_validated, compose_blanks};
 pub use inventory::{
-    ComposeError, ConsignerError, Inventory, InventoryDataError, InventoryError,
+    ComposeError, ConsignerError, DataError, Inventory, InventoryDataError, InventoryError,
     InventoryInconsistency,
 };
+pub use policy::{AcceptPolicy, SigVerifier, TerminalDepth, TrustedIssuers};
+pub use selection::{InputSelection, InputsSelected, SelectedState};
 pub use stash::{Stash, StashError, StashInconsistency};
 pub use state::PersistedState;
-pub use stock::Stock;
+pub use stock::{Stock, StockV0, StockVer};
+pub use transfers::{OutgoingTransfer, TransferBeneficiaries, TransferPreview, TransferStatus};
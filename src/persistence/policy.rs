@@ -0,0 +1,298 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Policy for accepting incoming consignments into the inventory.
+
+use std::collections::BTreeSet;
+
+use rgb::{SchemaId, WitnessOrd};
+use strict_encoding::StrictSerialize;
+
+use crate::containers::{Consignment, ContentId, Identity};
+use crate::interface::IfaceId;
+use crate::persistence::DataError;
+use crate::resolvers::ResolveHeight;
+
+/// Requirement for the depth of the witness transactions of consignment
+/// terminals.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct TerminalDepth {
+    /// Minimal number of confirmations.
+    pub min_confirmations: u32,
+    /// Height of the current blockchain tip, against which the number of
+    /// confirmations is computed.
+    pub tip_height: u32,
+}
+
+/// Verifier of a signature made by the identity over the content.
+///
+/// The library doesn't implement the cryptography of the identity suites, so
+/// the verification is provided by the caller.
+pub type SigVerifier = fn(&Identity, &ContentId, &[u8]) -> bool;
+
+/// Issuers trusted by [`AcceptPolicy`].
+#[derive(Clone, Debug)]
+pub struct TrustedIssuers {
+    pub identities: BTreeSet<Identity>,
+    pub verifier: SigVerifier,
+}
+
+/// Policy restricting which consignments may be imported into the inventory
+/// with [`crate::persistence::Inventory::import_contract`] and
+/// [`crate::persistence::Inventory::accept_transfer`].
+///
+/// Default policy accepts any valid consignment with mined terminals and
+/// resolved transactions.
+#[derive(Clone, Debug, Default)]
+pub struct AcceptPolicy {
+    /// Accept consignments containing unresolved transactions or unmined
+    /// terminals.
+    pub force: bool,
+    /// Schemata which contracts may use; any if `None`.
+    pub schemata: Option<BTreeSet<SchemaId>>,
+    /// Interfaces, at least one of which the consignment must implement; any
+    /// if `None`.
+    pub ifaces: Option<BTreeSet<IfaceId>>,
+    /// Required depth of the terminal witness transactions.
+    pub terminal_depth: Option<TerminalDepth>,
+    /// Maximal size of the serialized consignment, in bytes.
+    pub max_size: Option<usize>,
+    /// Maximal number of anchored bundles in the consignment history.
+    pub max_history: Option<usize>,
+    /// Issuers whose valid signature over the contract genesis is required;
+    /// no signature is required if `None`.
+    pub trusted_issuers: Option<TrustedIssuers>,
+    /// Reject contracts issued on testnet.
+    pub mainnet: bool,
+}
+
+impl AcceptPolicy {
+    /// Constructs policy accepting any valid consignment, including the ones
+    /// with unresolved transactions or unmined terminals.
+    pub fn forced() -> Self {
+        AcceptPolicy {
+            force: true,
+            ..default!()
+        }
+    }
+
+    /// Checks the consignment against the policy, returning the first
+    /// violated requirement as an error.
+    pub fn check<const TYPE: bool>(
+        &self,
+        consignment: &Consignment<TYPE>,
+        resolver: &mut impl ResolveHeight<Error = impl std::error::Error + 'static>,
+    ) -> Result<(), DataError> {
+        self.check_limits(consignment)?;
+        self.check_contract(consignment, resolver)
+    }
+
+    /// Checks the consignment against the size and history limits of the
+    /// policy, which don't require inspecting the consignment contents.
+    pub fn check_limits<const TYPE: bool>(
+        &self,
+        consignment: &Consignment<TYPE>,
+    ) -> Result<(), DataError> {
+        if let Some(max) = self.max_history {
+            let len = consignment.bundles.len();
+            if len > max {
+                return Err(DataError::HistoryTooDeep(len, max));
+            }
+        }
+        if let Some(max) = self.max_size {
+            let len = consignment
+                .strict_serialized_len::<{ usize::MAX }>()
+                .unwrap_or(usize::MAX);
+            if len > max {
+                return Err(DataError::ConsignmentTooLarge(len, max));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the contract and the terminals of the consignment against the
+    /// policy, skipping the checks done by [`Self::check_limits`].
+    pub fn check_contract<const TYPE: bool>(
+        &self,
+        consignment: &Consignment<TYPE>,
+        resolver: &mut impl ResolveHeight<Error = impl std::error::Error + 'static>,
+    ) -> Result<(), DataError> {
+        let contract_id = consignment.contract_id();
+        if self.mainnet && consignment.genesis.testnet {
+            return Err(DataError::TestnetContract(contract_id));
+        }
+        let schema_id = consignment.schema_id();
+        if matches!(&self.schemata, Some(schemata) if !schemata.contains(&schema_id)) {
+            return Err(DataError::SchemaNotAllowed(schema_id));
+        }
+        if let Some(ifaces) = &self.ifaces {
+            if !consignment.ifaces.keys().any(|id| ifaces.contains(id)) {
+                return Err(DataError::IfaceNotAllowed(contract_id));
+            }
+        }
+        if let Some(trusted) = &self.trusted_issuers {
+            let content_id = ContentId::Genesis(contract_id);
+            let signed = consignment
+                .signatures
+                .get(&content_id)
+                .map(|sigs| {
+                    sigs.iter().any(|cert| {
+                        trusted.identities.contains(&cert.signer) &&
+                            (trusted.verifier)(&cert.signer, &content_id, &cert.signature)
+                    })
+                })
+                .unwrap_or_default();
+            if !signed {
+                return Err(DataError::UntrustedIssuer(contract_id));
+            }
+        }
+        if let Some(depth) = self.terminal_depth {
+            for bundle_id in consignment.terminals.keys() {
+                let Some(anchor) = consignment
+                    .bundles
+                    .iter()
+                    .find(|ab| ab.bundle.bundle_id() == *bundle_id)
+                    .map(|ab| &ab.anchor)
+                else {
+                    continue;
+                };
+                let witness_anchor = resolver
+                    .resolve_anchor(anchor)
+                    .map_err(|err| DataError::HeightResolver(Box::new(err)))?;
+                let confirmations = match witness_anchor.witness_ord {
+                    WitnessOrd::OnChain(pos) => {
+                        depth.tip_height.saturating_add(1).saturating_sub(pos.height().get())
+                    }
+                    WitnessOrd::OffChain => 0,
+                };
+                if confirmations < depth.min_confirmations {
+                    return Err(DataError::InsufficientConfirmations(
+                        witness_anchor.witness_id,
+                        confirmations,
+                        depth.min_confirmations,
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::{Confined, TinyBlob};
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::containers::{Cert, ContentSigs, Contract};
+    use crate::interface::resolver::DumbResolver;
+
+    #[test]
+    fn rejections() {
+        let mut contract = Contract::strict_dumb();
+        contract.genesis.testnet = true;
+        let contract_id = contract.contract_id();
+
+        AcceptPolicy::default()
+            .check(&contract, &mut DumbResolver)
+            .unwrap();
+        let policy = AcceptPolicy {
+            mainnet: true,
+            ..default!()
+        };
+        assert!(matches!(
+            policy.check(&contract, &mut DumbResolver),
+            Err(DataError::TestnetContract(id)) if id == contract_id
+        ));
+        let policy = AcceptPolicy {
+            schemata: Some(none!()),
+            ..default!()
+        };
+        assert!(matches!(
+            policy.check(&contract, &mut DumbResolver),
+            Err(DataError::SchemaNotAllowed(_))
+        ));
+        let policy = AcceptPolicy {
+            trusted_issuers: Some(TrustedIssuers {
+                identities: none!(),
+                verifier: verify,
+            }),
+            ..default!()
+        };
+        assert!(matches!(
+            policy.check(&contract, &mut DumbResolver),
+            Err(DataError::UntrustedIssuer(id)) if id == contract_id
+        ));
+        let policy = AcceptPolicy {
+            max_history: Some(0),
+            max_size: Some(0),
+            ..default!()
+        };
+        assert!(matches!(
+            policy.check(&contract, &mut DumbResolver),
+            Err(DataError::ConsignmentTooLarge(_, 0))
+        ));
+    }
+
+    /// Test signature scheme, in which the signature must match the public
+    /// key of the signer.
+    fn verify(signer: &Identity, _: &ContentId, signature: &[u8]) -> bool {
+        signer.pk.as_slice() == signature
+    }
+
+    fn signed(signer: &Identity, signature: &[u8]) -> Contract {
+        let mut contract = Contract::strict_dumb();
+        let cert = Cert {
+            signer: signer.clone(),
+            signature: TinyBlob::try_from(signature.to_vec()).unwrap(),
+        };
+        let sigs = ContentSigs::from(Confined::try_from(bset![cert]).unwrap());
+        contract
+            .signatures
+            .insert(ContentId::Genesis(contract.contract_id()), sigs)
+            .unwrap();
+        contract
+    }
+
+    #[test]
+    fn trusted_issuers() {
+        let issuer = Identity {
+            pk: TinyBlob::try_from(vec![1u8; 32]).unwrap(),
+            ..Identity::strict_dumb()
+        };
+        let policy = AcceptPolicy {
+            trusted_issuers: Some(TrustedIssuers {
+                identities: bset![issuer.clone()],
+                verifier: verify,
+            }),
+            ..default!()
+        };
+
+        let contract = signed(&issuer, &[1u8; 32]);
+        policy.check(&contract, &mut DumbResolver).unwrap();
+
+        let forged = signed(&issuer, &[2u8; 32]);
+        assert!(matches!(
+            policy.check(&forged, &mut DumbResolver),
+            Err(DataError::UntrustedIssuer(id)) if id == forged.contract_id()
+        ));
+    }
+}
@@ -0,0 +1,176 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+
+use invoice::Amount;
+use rgb::{ContractId, XOutputSeal};
+
+use crate::interface::rgb21::OwnedFraction;
+
+/// Strategy for the automatic selection of outputs spent by a transfer (see
+/// [`crate::persistence::Inventory::select_inputs`]).
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum InputSelection {
+    /// Spend outputs holding the smallest amounts first, consolidating the
+    /// dust.
+    #[default]
+    SmallestFirst,
+
+    /// Spend outputs holding the largest amounts first.
+    LargestFirst,
+
+    /// Spend as few outputs as possible: a single output covering the whole
+    /// amount, if there is one, or the outputs holding the largest amounts
+    /// otherwise.
+    MinInputs,
+
+    /// Prefer outputs which don't carry state of other contracts, and thus do
+    /// not require blank transitions; spending the smallest amounts first.
+    AvoidOtherContracts,
+}
+
+/// Quantity of the invoiced state held by the outputs selected with
+/// [`crate::persistence::Inventory::select_inputs`].
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum SelectedState {
+    /// Fungible amount.
+    Amount(Amount),
+    /// Owned fraction of an RGB21 token.
+    Fraction(OwnedFraction),
+    /// Declarative rights or an attachment, which can't be quantified.
+    Unquantified,
+}
+
+/// Outputs selected for spending by
+/// [`crate::persistence::Inventory::select_inputs`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct InputsSelected {
+    /// Outputs which must be spent by the transfer.
+    pub outputs: BTreeSet<XOutputSeal>,
+    /// Invoiced state held by the selected outputs.
+    pub state: SelectedState,
+    /// Other contracts having state assigned to the selected outputs, each of
+    /// which will require a blank state transition.
+    pub blanks: BTreeSet<ContractId>,
+}
+
+/// Output holding the state requested by an invoice.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub(super) struct Candidate {
+    pub output: XOutputSeal,
+    /// Fungible amount or token fraction held by the output; zero for the
+    /// state which can't be quantified.
+    pub value: u64,
+    pub others: BTreeSet<ContractId>,
+}
+
+impl InputSelection {
+    /// Selects candidates holding at least `target` value in total, or a
+    /// single candidate if the state can't be split (`target` is `None`).
+    ///
+    /// Returns the selected candidates together with their total value.
+    pub(super) fn select(
+        self,
+        mut candidates: Vec<Candidate>,
+        target: Option<u64>,
+    ) -> Option<(Vec<Candidate>, u64)> {
+        match self {
+            InputSelection::SmallestFirst => candidates.sort_by_key(|c| c.value),
+            InputSelection::LargestFirst => {
+                candidates.sort_by_key(|c| c.value);
+                candidates.reverse();
+            }
+            InputSelection::MinInputs => {
+                candidates.sort_by_key(|c| c.value);
+                let target = target.unwrap_or_default();
+                match candidates.iter().position(|c| c.value >= target) {
+                    Some(pos) => candidates.swap(0, pos),
+                    None => candidates.reverse(),
+                }
+            }
+            InputSelection::AvoidOtherContracts => {
+                candidates.sort_by_key(|c| (!c.others.is_empty(), c.value))
+            }
+        }
+
+        let mut selected = vec![];
+        let mut total = 0u64;
+        for candidate in candidates {
+            total = total.checked_add(candidate.value)?;
+            selected.push(candidate);
+            if target.map(|t| total >= t).unwrap_or(true) {
+                return Some((selected, total));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::ByteArray;
+    use bp::dbc::Method;
+    use bp::{Outpoint, Txid};
+    use rgb::{OutputSeal, XChain};
+
+    use super::*;
+
+    fn candidate(no: u8, value: u64, others: &[u8]) -> Candidate {
+        let outpoint = Outpoint::new(Txid::from([no; 32]), 0);
+        Candidate {
+            output: XChain::Bitcoin(OutputSeal::new(Method::TapretFirst, outpoint)),
+            value,
+            others: others.iter().map(|id| ContractId::from([*id; 32])).collect(),
+        }
+    }
+
+    fn select(strategy: InputSelection, target: u64) -> Option<(Vec<u8>, Vec<u8>)> {
+        let candidates = vec![candidate(1, 50, &[]), candidate(2, 10, &[7]), candidate(3, 30, &[])];
+        let (selected, _) = strategy.select(candidates, Some(target))?;
+        let outputs = selected
+            .iter()
+            .map(|c| c.output.as_reduced_unsafe().txid.to_byte_array()[0])
+            .collect::<BTreeSet<_>>();
+        let blanks = selected
+            .iter()
+            .flat_map(|c| &c.others)
+            .map(|id| id.to_byte_array()[0])
+            .collect::<BTreeSet<_>>();
+        Some((outputs.into_iter().collect(), blanks.into_iter().collect()))
+    }
+
+    #[test]
+    fn strategies() {
+        assert_eq!(select(InputSelection::SmallestFirst, 35), Some((vec![2, 3], vec![7])));
+        assert_eq!(select(InputSelection::LargestFirst, 35), Some((vec![1], vec![])));
+        assert_eq!(select(InputSelection::MinInputs, 35), Some((vec![1], vec![])));
+        assert_eq!(select(InputSelection::MinInputs, 25), Some((vec![3], vec![])));
+        assert_eq!(select(InputSelection::MinInputs, 70), Some((vec![1, 3], vec![])));
+        assert_eq!(select(InputSelection::AvoidOtherContracts, 35), Some((vec![1, 3], vec![])));
+        assert_eq!(select(InputSelection::SmallestFirst, 100), None);
+    }
+}
@@ -23,23 +23,30 @@ use std::collections::{BTreeMap, BTreeSet};
 use std::convert::Infallible;
 use std::ops::{Deref, DerefMut};
 
-use amplify::confinement::{MediumOrdMap, MediumOrdSet, TinyOrdMap};
+use amplify::confinement;
+use amplify::confinement::{Confined, MediumOrdMap, MediumOrdSet, TinyOrdMap};
 use commit_verify::{mpc, Conceal};
 use rgb::validation::{Status, Validity, Warning};
 use rgb::{
-    validation, AnchoredBundle, Assign, AssignmentType, BundleId, ContractHistory, ContractId,
-    ContractState, ExposedState, Extension, Genesis, GenesisSeal, GraphSeal, OpId, Operation,
-    Opout, SecretSeal, SubSchema, Transition, TransitionBundle, TypedAssigns, WitnessAnchor,
-    WitnessId, XAnchor, XChain, XOutpoint, XOutputSeal,
+    validation, AnchoredBundle, Assign, AssignmentType, AssignmentWitness, BundleId,
+    ContractHistory, ContractId, ContractState, ExposedState, Extension, Genesis, GenesisSeal,
+    GraphSeal, OpId, Operation, Opout, SecretSeal, SubSchema, Transition, TransitionBundle,
+    TypedAssigns, WitnessAnchor, WitnessId, WitnessOrd, XAnchor, XChain, XOutpoint, XOutputSeal,
 };
-use strict_encoding::{StrictDeserialize, StrictSerialize};
+use strict_encoding::{FieldName, StrictDeserialize, StrictSerialize};
 
 use crate::containers::{Cert, Consignment, ContentId, Contract, TerminalSeal, Transfer};
-use crate::interface::{ContractIface, Iface, IfaceId, IfaceImpl, IfacePair, SchemaIfaces};
+use crate::interface::{
+    ContractIface, Iface, IfaceId, IfaceImpl, IfacePair, IssuerRegistry, KnownTransition,
+    SchemaIfaces, StatePoint,
+};
 use crate::persistence::hoard::ConsumeError;
-use crate::persistence::inventory::{DataError, IfaceImplError, InventoryInconsistency};
+use crate::persistence::inventory::{
+    validate_extension, DataError, IfaceImplError, InventoryInconsistency,
+};
 use crate::persistence::{
-    Hoard, Inventory, InventoryDataError, InventoryError, PersistedState, Stash, StashInconsistency,
+    AcceptPolicy, Hoard, Inventory, InventoryDataError, InventoryError, OutgoingTransfer,
+    PersistedState, Stash, StashInconsistency, TransferPreview, TransferStatus,
 };
 use crate::resolvers::ResolveHeight;
 use crate::LIB_NAME_RGB_STD;
@@ -61,6 +68,23 @@ pub struct IndexedBundle(ContractId, BundleId);
 pub struct ContractIndex {
     public_opouts: MediumOrdSet<Opout>,
     outpoint_opouts: MediumOrdMap<XOutputSeal, MediumOrdSet<Opout>>,
+    /// Bundles containing known state transitions of the contract.
+    bundles: MediumOrdSet<BundleId>,
+}
+
+/// Version of the [`Stock`] data layout.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, Default)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD, tags = repr, into_u8, try_from_u8)]
+#[display(lowercase)]
+#[non_exhaustive]
+#[repr(u8)]
+pub enum StockVer {
+    // V0 is the unversioned layout, which can be migrated with `StockV0`.
+    /// Layout adding the witness ordering and per-contract bundle indexes and
+    /// the records of outgoing transfers.
+    #[default]
+    V1 = 1,
 }
 
 /// Stock is an in-memory inventory (stash, index, contract state) useful for
@@ -72,6 +96,7 @@ pub struct ContractIndex {
 #[derive(StrictType, StrictEncode, StrictDecode)]
 #[strict_type(lib = LIB_NAME_RGB_STD)]
 pub struct Stock {
+    version: StockVer,
     // stash
     hoard: Hoard,
     // state
@@ -79,22 +104,38 @@ pub struct Stock {
     // index
     bundle_op_index: MediumOrdMap<OpId, IndexedBundle>,
     anchor_bundle_index: MediumOrdMap<BundleId, WitnessId>,
+    witness_ord_index: MediumOrdMap<WitnessId, WitnessOrd>,
     contract_index: TinyOrdMap<ContractId, ContractIndex>,
     terminal_index: MediumOrdMap<XChain<SecretSeal>, Opout>,
     // secrets
     seal_secrets: MediumOrdSet<XChain<GraphSeal>>,
+    // outgoing transfers, added with the V1 layout
+    outgoing_transfers: MediumOrdMap<WitnessId, OutgoingTransfer>,
 }
 
 impl Default for Stock {
-    fn default() -> Self {
+    fn default() -> Self { Stock::with_hoard(Hoard::preset()) }
+}
+
+impl Stock {
+    /// Constructs stock knowing all interfaces, schemata and interface
+    /// implementations from the registry.
+    pub fn with_registry(registry: &IssuerRegistry) -> Result<Self, confinement::Error> {
+        Hoard::with_registry(registry).map(Stock::with_hoard)
+    }
+
+    fn with_hoard(hoard: Hoard) -> Self {
         Stock {
-            hoard: Hoard::preset(),
+            version: StockVer::V1,
+            hoard,
             history: empty!(),
             bundle_op_index: empty!(),
             anchor_bundle_index: empty!(),
+            witness_ord_index: empty!(),
             contract_index: empty!(),
             terminal_index: empty!(),
             seal_secrets: empty!(),
+            outgoing_transfers: empty!(),
         }
     }
 }
@@ -102,6 +143,80 @@ impl Default for Stock {
 impl StrictSerialize for Stock {}
 impl StrictDeserialize for Stock {}
 
+#[derive(Clone, Debug, Default)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+struct ContractIndexV0 {
+    public_opouts: MediumOrdSet<Opout>,
+    outpoint_opouts: MediumOrdMap<XOutputSeal, MediumOrdSet<Opout>>,
+}
+
+/// Unversioned [`Stock`] data layout, used before [`StockVer::V1`].
+///
+/// Data persisted in this layout must be deserialized as `StockV0` and
+/// converted into [`Stock`]. The conversion rebuilds the indexes which were
+/// absent in the legacy layout; since the mining status of the witness
+/// transactions was not stored, all of them are marked as off-chain until
+/// [`Stock::update_witnesses`] is called. The legacy layout didn't keep
+/// records of outgoing transfers, so the converted stock has none.
+#[derive(Clone, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+pub struct StockV0 {
+    hoard: Hoard,
+    history: TinyOrdMap<ContractId, ContractHistory>,
+    bundle_op_index: MediumOrdMap<OpId, IndexedBundle>,
+    anchor_bundle_index: MediumOrdMap<BundleId, WitnessId>,
+    contract_index: TinyOrdMap<ContractId, ContractIndexV0>,
+    terminal_index: MediumOrdMap<XChain<SecretSeal>, Opout>,
+    seal_secrets: MediumOrdSet<XChain<GraphSeal>>,
+}
+
+impl StrictSerialize for StockV0 {}
+impl StrictDeserialize for StockV0 {}
+
+impl From<StockV0> for Stock {
+    fn from(legacy: StockV0) -> Self {
+        let mut contract_index = legacy
+            .contract_index
+            .into_iter()
+            .map(|(contract_id, index)| {
+                (contract_id, ContractIndex {
+                    public_opouts: index.public_opouts,
+                    outpoint_opouts: index.outpoint_opouts,
+                    bundles: empty!(),
+                })
+            })
+            .collect::<BTreeMap<_, _>>();
+        for IndexedBundle(contract_id, bundle_id) in legacy.bundle_op_index.values() {
+            if let Some(index) = contract_index.get_mut(contract_id) {
+                index
+                    .bundles
+                    .push(*bundle_id)
+                    .expect("legacy bundle index exceeds its own size limit");
+            }
+        }
+        let witness_ord_index = legacy
+            .anchor_bundle_index
+            .values()
+            .map(|witness_id| (*witness_id, WitnessOrd::OffChain))
+            .collect::<BTreeMap<_, _>>();
+
+        Stock {
+            version: StockVer::V1,
+            hoard: legacy.hoard,
+            history: legacy.history,
+            bundle_op_index: legacy.bundle_op_index,
+            anchor_bundle_index: legacy.anchor_bundle_index,
+            witness_ord_index: Confined::from_iter_unsafe(witness_ord_index),
+            contract_index: Confined::from_iter_unsafe(contract_index),
+            terminal_index: legacy.terminal_index,
+            seal_secrets: legacy.seal_secrets,
+            outgoing_transfers: empty!(),
+        }
+    }
+}
+
 impl Deref for Stock {
     type Target = Hoard;
 
@@ -112,49 +227,163 @@ impl DerefMut for Stock {
     fn deref_mut(&mut self) -> &mut Self::Target { &mut self.hoard }
 }
 
+/// Resolver wrapper remembering already resolved witness anchors, such that
+/// each anchor is resolved only once per consignment.
+struct CachedResolver<'r, R: ResolveHeight> {
+    resolver: &'r mut R,
+    cache: BTreeMap<WitnessId, WitnessAnchor>,
+}
+
+impl<'r, R: ResolveHeight> CachedResolver<'r, R> {
+    fn new(resolver: &'r mut R) -> Self {
+        CachedResolver {
+            resolver,
+            cache: empty!(),
+        }
+    }
+}
+
+impl<'r, R: ResolveHeight> ResolveHeight for CachedResolver<'r, R> {
+    type Error = R::Error;
+
+    fn resolve_anchor(&mut self, anchor: &XAnchor) -> Result<WitnessAnchor, Self::Error> {
+        let witness_id = anchor.witness_id();
+        if let Some(witness_anchor) = witness_id.and_then(|id| self.cache.get(&id)) {
+            return Ok(*witness_anchor);
+        }
+        let witness_anchor = self.resolver.resolve_anchor(anchor)?;
+        if let Some(id) = witness_id {
+            self.cache.insert(id, witness_anchor);
+        }
+        Ok(witness_anchor)
+    }
+
+    fn resolve_witness(&mut self, witness_id: WitnessId) -> Result<WitnessOrd, Self::Error> {
+        self.resolver.resolve_witness(witness_id)
+    }
+}
+
 #[allow(clippy::result_large_err)]
 impl Stock {
-    fn consume_consignment<R: ResolveHeight, const TYPE: bool>(
+    /// Updates mining status of all known witness transactions.
+    ///
+    /// Witnesses of the locally created transfers and imported state
+    /// extensions are recorded as off-chain and must be updated once they are
+    /// mined, since otherwise the history operations will not be properly
+    /// ordered. Witnesses without a known anchor are resolved with
+    /// [`ResolveHeight::resolve_witness`]. Outgoing transfers with mined
+    /// witnesses are moved to the status [`TransferStatus::Mined`].
+    pub fn update_witnesses<R: ResolveHeight>(
         &mut self,
-        mut consignment: Consignment<TYPE>,
         resolver: &mut R,
-        force: bool,
+    ) -> Result<(), InventoryError<Infallible>>
+    where
+        R::Error: 'static,
+    {
+        let witness_ids = self.witness_ord_index.keys().copied().collect::<Vec<_>>();
+        for witness_id in witness_ids {
+            let witness_ord = match self.hoard.anchors.get(&witness_id) {
+                None => resolver
+                    .resolve_witness(witness_id)
+                    .map_err(|err| DataError::HeightResolver(Box::new(err)))?,
+                Some(anchor) => {
+                    let Some((_, contract_id)) = anchor.known_bundle_ids().next() else {
+                        continue;
+                    };
+                    let anchor = anchor
+                        .to_merkle_proof(contract_id)
+                        .map_err(|_| ConsumeError::AnchorInconsistent(witness_id))?;
+                    resolver
+                        .resolve_anchor(&anchor)
+                        .map_err(|err| DataError::HeightResolver(Box::new(err)))?
+                        .witness_ord
+                }
+            };
+            self.witness_ord_index.insert(witness_id, witness_ord)?;
+            if let (WitnessOrd::OnChain(_), Some(transfer)) =
+                (witness_ord, self.outgoing_transfers.get_mut(&witness_id))
+            {
+                if transfer.status < TransferStatus::Mined {
+                    transfer.status = TransferStatus::Mined;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn contract_iface(
+        &self,
+        history: ContractHistory,
+        iface_id: IfaceId,
+        transitions: BTreeMap<OpId, KnownTransition>,
+    ) -> Result<ContractIface, InventoryError<Infallible>> {
+        let schema_id = history.schema_id();
+        let schema_ifaces = self
+            .schemata
+            .get(&schema_id)
+            .ok_or(StashInconsistency::SchemaAbsent(schema_id))?;
+        let state = ContractState {
+            schema: schema_ifaces.schema.clone(),
+            history,
+        };
+        let iimpl = schema_ifaces
+            .iimpls
+            .get(&iface_id)
+            .ok_or(StashInconsistency::IfaceImplAbsent(iface_id, schema_id))?
+            .clone();
+        Ok(ContractIface {
+            state,
+            iface: iimpl,
+            transitions,
+        })
+    }
+
+    /// Checks consignment validation status against the acceptance policy,
+    /// returning warnings which must be added to the status of the import.
+    fn check_consignment<R: ResolveHeight, const TYPE: bool>(
+        consignment: &Consignment<TYPE>,
+        resolver: &mut CachedResolver<R>,
+        policy: &AcceptPolicy,
     ) -> Result<validation::Status, InventoryError<Infallible>>
     where
         R::Error: 'static,
     {
+        policy.check_limits(consignment)?;
         let mut status = validation::Status::new();
         match consignment.validation_status() {
             None => return Err(DataError::NotValidated.into()),
             Some(status) if status.validity() == Validity::Invalid => {
                 return Err(DataError::Invalid(status.clone()).into());
             }
-            Some(status) if status.validity() == Validity::UnresolvedTransactions && !force => {
+            Some(status)
+                if status.validity() == Validity::UnresolvedTransactions && !policy.force =>
+            {
                 return Err(DataError::UnresolvedTransactions.into());
             }
-            Some(status) if status.validity() == Validity::UnminedTerminals && !force => {
+            Some(status) if status.validity() == Validity::UnminedTerminals && !policy.force => {
                 return Err(DataError::TerminalsUnmined.into());
             }
-            Some(s) if s.validity() == Validity::UnresolvedTransactions && !force => {
+            Some(s) if s.validity() == Validity::UnresolvedTransactions => {
                 status.add_warning(Warning::Custom(s!(
                     "contract contains unknown transactions and was forcefully imported"
                 )));
             }
-            Some(s) if s.validity() == Validity::UnminedTerminals && !force => {
+            Some(s) if s.validity() == Validity::UnminedTerminals => {
                 status.add_warning(Warning::Custom(s!("contract contains not yet mined final \
                                                        transactions and was forcefully imported")));
             }
             _ => {}
         }
+        policy.check_contract(consignment, resolver)?;
+        Ok(status)
+    }
 
-        let id = consignment.contract_id();
-
-        self.import_schema(consignment.schema.clone())?;
-        for IfacePair { iface, iimpl } in consignment.ifaces.values() {
-            self.import_iface(iface.clone())?;
-            self.import_iface_impl(iimpl.clone())?;
-        }
-
+    /// Reveals terminal seals of the consignment for which the seal secrets
+    /// are known.
+    fn reveal_terminals<const TYPE: bool>(
+        &self,
+        mut consignment: Consignment<TYPE>,
+    ) -> Consignment<TYPE> {
         // clone needed due to borrow checker
         for (bundle_id, terminal) in consignment.terminals.clone() {
             for secret in terminal
@@ -167,10 +396,34 @@ impl Stock {
                 }
             }
         }
+        consignment
+    }
+
+    fn consume_consignment<R: ResolveHeight, const TYPE: bool>(
+        &mut self,
+        consignment: Consignment<TYPE>,
+        resolver: &mut R,
+        policy: &AcceptPolicy,
+    ) -> Result<validation::Status, InventoryError<Infallible>>
+    where
+        R::Error: 'static,
+    {
+        let mut resolver = CachedResolver::new(resolver);
+        let status = Self::check_consignment(&consignment, &mut resolver, policy)?;
+
+        let id = consignment.contract_id();
+
+        self.import_schema(consignment.schema.clone())?;
+        for IfacePair { iface, iimpl } in consignment.ifaces.values() {
+            self.import_iface(iface.clone())?;
+            self.import_iface_impl(iimpl.clone())?;
+        }
+
+        let consignment = self.reveal_terminals(consignment);
 
         // Update existing contract state
         let history = consignment
-            .update_history(self.history.get(&id), resolver)
+            .update_history(self.history.get(&id), &mut resolver)
             .map_err(|err| DataError::HeightResolver(Box::new(err)))?;
         self.history.insert(id, history)?;
 
@@ -187,7 +440,12 @@ impl Stock {
             let witness_id = anchor
                 .witness_id()
                 .ok_or_else(|| ConsumeError::AnchorInconsistent(anchor.witness_id_unchecked()))?;
+            let witness_anchor = resolver
+                .resolve_anchor(anchor)
+                .map_err(|err| DataError::HeightResolver(Box::new(err)))?;
             self.anchor_bundle_index.insert(bundle_id, witness_id)?;
+            self.witness_ord_index
+                .insert(witness_id, witness_anchor.witness_ord)?;
             self.index_bundle(contract_id, bundle, witness_id)?;
         }
 
@@ -196,6 +454,37 @@ impl Stock {
         Ok(status)
     }
 
+    fn known_transitions(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<BTreeMap<OpId, KnownTransition>, InventoryError<Infallible>> {
+        let mut transitions = BTreeMap::new();
+        let index = self
+            .contract_index
+            .get(&contract_id)
+            .ok_or(StashInconsistency::ContractAbsent(contract_id))?;
+        for bundle_id in &index.bundles {
+            let Some(witness_id) = self.anchor_bundle_index.get(bundle_id).copied() else {
+                continue;
+            };
+            let witness_ord = self
+                .witness_ord_index
+                .get(&witness_id)
+                .copied()
+                .unwrap_or(WitnessOrd::OffChain);
+            for (opid, transition) in &self.bundle(*bundle_id)?.known_transitions {
+                let inputs = transition.inputs.iter().map(|input| input.prev_out);
+                transitions.insert(*opid, KnownTransition {
+                    witness_id,
+                    witness_ord,
+                    transition_type: transition.transition_type,
+                    inputs: Confined::from_iter_unsafe(inputs),
+                });
+            }
+        }
+        Ok(transitions)
+    }
+
     fn index_genesis(
         &mut self,
         id: ContractId,
@@ -253,6 +542,11 @@ impl Stock {
         witness_id: WitnessId,
     ) -> Result<(), InventoryError<<Self as Inventory>::Error>> {
         let bundle_id = bundle.bundle_id();
+        self.contract_index
+            .get_mut(&id)
+            .ok_or(StashInconsistency::ContractAbsent(id))?
+            .bundles
+            .push(bundle_id)?;
         for (opid, transition) in &bundle.known_transitions {
             self.bundle_op_index
                 .insert(*opid, IndexedBundle(id, bundle_id))?;
@@ -436,23 +730,94 @@ impl Inventory for Stock {
         &mut self,
         contract: Contract,
         resolver: &mut R,
+        policy: &AcceptPolicy,
     ) -> Result<validation::Status, InventoryError<Self::Error>>
     where
         R::Error: 'static,
     {
-        self.consume_consignment(contract, resolver, false)
+        self.consume_consignment(contract, resolver, policy)
     }
 
     fn accept_transfer<R: ResolveHeight>(
         &mut self,
         transfer: Transfer,
         resolver: &mut R,
-        force: bool,
+        policy: &AcceptPolicy,
     ) -> Result<Status, InventoryError<Self::Error>>
     where
         R::Error: 'static,
     {
-        self.consume_consignment(transfer, resolver, force)
+        self.consume_consignment(transfer, resolver, policy)
+    }
+
+    fn preview_transfer<R: ResolveHeight>(
+        &self,
+        transfer: &Transfer,
+        resolver: &mut R,
+        policy: &AcceptPolicy,
+    ) -> Result<TransferPreview, InventoryError<Self::Error>>
+    where
+        R::Error: 'static,
+    {
+        let contract_id = transfer.contract_id();
+        let new_contract = !self.history.contains_key(&contract_id);
+
+        // Outputs defined by the terminals for which we know seal secrets
+        let mut outputs = BTreeSet::new();
+        for (bundle_id, terminal) in &transfer.terminals {
+            let Some(witness_id) = transfer
+                .bundles
+                .iter()
+                .find(|ab| ab.bundle.bundle_id() == *bundle_id)
+                .and_then(|ab| ab.anchor.witness_id())
+            else {
+                continue;
+            };
+            for secret in terminal
+                .seals
+                .iter()
+                .filter_map(|seal| seal.map_ref(TerminalSeal::secret_seal).transpose())
+            {
+                if let Some(seal) = self.seal_secrets.iter().find(|s| s.conceal() == secret) {
+                    let output = seal
+                        .try_to_output_seal(witness_id)
+                        .map_err(|_| DataError::ChainMismatch)?;
+                    outputs.insert(output);
+                }
+            }
+        }
+
+        // The transfer is validated and applied to a copy of the contract
+        // history only, leaving the stock untouched
+        let mut status = transfer.validation_status().cloned().unwrap_or_default();
+        let mut resolver = CachedResolver::new(resolver);
+        status += Self::check_consignment(transfer, &mut resolver, policy)?;
+        let consignment = self.reveal_terminals(transfer.clone());
+        let history = consignment
+            .update_history(self.history.get(&contract_id), &mut resolver)
+            .map_err(|err| DataError::HeightResolver(Box::new(err)))?;
+
+        let outputs = outputs.into_iter().map(XOutpoint::from).collect();
+        let mut allocations = BTreeMap::<FieldName, Vec<_>>::new();
+        for ((opout, output), state) in history_state(&history, &outputs) {
+            let name = transfer
+                .ifaces
+                .values()
+                .find_map(|pair| pair.iimpl.assignment_name(opout.ty))
+                .cloned()
+                .unwrap_or_else(|| {
+                    FieldName::try_from(format!("type{}", opout.ty))
+                        .expect("type ids are valid field names")
+                });
+            allocations.entry(name).or_default().push((output, state));
+        }
+
+        Ok(TransferPreview {
+            contract_id,
+            new_contract,
+            allocations,
+            status,
+        })
     }
 
     unsafe fn consume_anchor(
@@ -476,6 +841,10 @@ impl Inventory for Stock {
         witness_id: WitnessId,
     ) -> Result<(), InventoryError<<Self as Inventory>::Error>> {
         self.index_bundle(contract_id, &bundle, witness_id)?;
+        if !self.witness_ord_index.contains_key(&witness_id) {
+            self.witness_ord_index
+                .insert(witness_id, WitnessOrd::OffChain)?;
+        }
         let history = self
             .history
             .get_mut(&contract_id)
@@ -488,6 +857,27 @@ impl Inventory for Stock {
         Ok(())
     }
 
+    fn import_extension(
+        &mut self,
+        extension: Extension,
+        witness_id: WitnessId,
+    ) -> Result<(), InventoryError<<Self as Inventory>::Error>> {
+        validate_extension(self, &extension)?;
+        let contract_id = extension.contract_id;
+        self.index_extension(contract_id, &extension)?;
+        if !self.witness_ord_index.contains_key(&witness_id) {
+            self.witness_ord_index
+                .insert(witness_id, WitnessOrd::OffChain)?;
+        }
+        let history = self
+            .history
+            .get_mut(&contract_id)
+            .ok_or(InventoryInconsistency::StateAbsent(contract_id))?;
+        history.add_extension(&extension, WitnessAnchor::from_mempool(witness_id));
+        self.hoard.consume_extension(extension)?;
+        Ok(())
+    }
+
     unsafe fn import_contract_force<R: ResolveHeight>(
         &mut self,
         contract: Contract,
@@ -496,7 +886,7 @@ impl Inventory for Stock {
     where
         R::Error: 'static,
     {
-        self.consume_consignment(contract, resolver, true)
+        self.consume_consignment(contract, resolver, &AcceptPolicy::forced())
     }
 
     fn contract_iface_id(
@@ -509,24 +899,98 @@ impl Inventory for Stock {
             .get(&contract_id)
             .ok_or(InventoryInconsistency::StateAbsent(contract_id))?
             .clone();
-        let schema_id = history.schema_id();
-        let schema_ifaces = self
-            .schemata
-            .get(&schema_id)
-            .ok_or(StashInconsistency::SchemaAbsent(schema_id))?;
-        let state = ContractState {
-            schema: schema_ifaces.schema.clone(),
-            history,
+        let transitions = self.known_transitions(contract_id)?;
+        self.contract_iface(history, iface_id, transitions)
+    }
+
+    fn contract_iface_at(
+        &self,
+        contract_id: ContractId,
+        iface_id: IfaceId,
+        point: StatePoint,
+    ) -> Result<ContractIface, InventoryError<Self::Error>> {
+        let current = self
+            .history
+            .get(&contract_id)
+            .ok_or(InventoryInconsistency::StateAbsent(contract_id))?;
+        let genesis = self
+            .geneses
+            .get(&contract_id)
+            .ok_or(StashInconsistency::ContractAbsent(contract_id))?;
+        let mut history = ContractHistory::with(
+            current.schema_id(),
+            current.root_schema_id(),
+            contract_id,
+            genesis,
+        );
+        let witness_anchor = |witness_id: WitnessId| WitnessAnchor {
+            witness_ord: self
+                .witness_ord_index
+                .get(&witness_id)
+                .copied()
+                .unwrap_or(WitnessOrd::OffChain),
+            witness_id,
         };
-        let iimpl = schema_ifaces
-            .iimpls
-            .get(&iface_id)
-            .ok_or(StashInconsistency::IfaceImplAbsent(iface_id, schema_id))?
-            .clone();
-        Ok(ContractIface {
-            state,
-            iface: iimpl,
-        })
+
+        // Extensions imported on their own are ordered by the witness they were
+        // imported with, which is kept by the current state of their
+        // assignments; the rest - by the first transition spending their state,
+        // the same way as during the consignment consumption.
+        let mut extensions = BTreeMap::<OpId, WitnessAnchor>::new();
+        let imported = current
+            .rights()
+            .iter()
+            .map(|a| (a.opout, a.witness))
+            .chain(current.fungibles().iter().map(|a| (a.opout, a.witness)))
+            .chain(current.data().iter().map(|a| (a.opout, a.witness)))
+            .chain(current.attach().iter().map(|a| (a.opout, a.witness)));
+        for (opout, witness) in imported {
+            let AssignmentWitness::Present(witness_id) = witness else {
+                continue;
+            };
+            if self.extensions.contains_key(&opout.op) {
+                extensions.insert(opout.op, witness_anchor(witness_id));
+            }
+        }
+        let mut transitions = self.known_transitions(contract_id)?;
+        for known in transitions.values() {
+            let anchor = witness_anchor(known.witness_id);
+            for input in &known.inputs {
+                if !self.extensions.contains_key(&input.op) {
+                    continue;
+                }
+                let ord = extensions.entry(input.op).or_insert(anchor);
+                if *ord > anchor {
+                    *ord = anchor;
+                }
+            }
+        }
+
+        transitions.retain(|_, known| {
+            point.includes(WitnessAnchor {
+                witness_ord: known.witness_ord,
+                witness_id: known.witness_id,
+            })
+        });
+        for (opid, known) in &transitions {
+            let anchor = WitnessAnchor {
+                witness_ord: known.witness_ord,
+                witness_id: known.witness_id,
+            };
+            history.add_transition(self.transition(*opid)?, anchor);
+        }
+        for (opid, anchor) in extensions {
+            if !point.includes(anchor) {
+                continue;
+            }
+            let extension = self
+                .extensions
+                .get(&opid)
+                .ok_or(StashInconsistency::OperationAbsent(opid))?;
+            history.add_extension(extension, anchor);
+        }
+
+        self.contract_iface(history, iface_id, transitions)
     }
 
     fn transition(&self, opid: OpId) -> Result<&Transition, InventoryError<Self::Error>> {
@@ -635,58 +1099,387 @@ impl Inventory for Stock {
             .history
             .get(&contract_id)
             .ok_or(StashInconsistency::ContractAbsent(contract_id))?;
+        Ok(history_state(history, &outputs))
+    }
 
-        let mut res = BTreeMap::new();
-
-        for item in history.fungibles() {
-            if outputs.contains::<XOutpoint>(&item.seal.into()) {
-                res.insert(
-                    (item.opout, item.seal),
-                    PersistedState::Amount(
-                        item.state.value.into(),
-                        item.state.blinding,
-                        item.state.tag,
-                    ),
-                );
-            }
+    fn store_seal_secret(
+        &mut self,
+        seal: XChain<GraphSeal>,
+    ) -> Result<(), InventoryError<Self::Error>> {
+        self.seal_secrets.push(seal)?;
+        Ok(())
+    }
+
+    fn seal_secrets(&self) -> Result<BTreeSet<XChain<GraphSeal>>, InventoryError<Self::Error>> {
+        Ok(self.seal_secrets.to_inner())
+    }
+
+    fn store_outgoing_transfer(
+        &mut self,
+        transfer: OutgoingTransfer,
+    ) -> Result<(), InventoryError<Self::Error>> {
+        self.outgoing_transfers
+            .insert(transfer.witness_id, transfer)?;
+        Ok(())
+    }
+
+    fn outgoing_transfers(
+        &self,
+    ) -> Result<BTreeMap<WitnessId, OutgoingTransfer>, InventoryError<Self::Error>> {
+        Ok(self.outgoing_transfers.to_inner())
+    }
+
+    fn outgoing_transfer(
+        &self,
+        witness_id: WitnessId,
+    ) -> Result<Option<OutgoingTransfer>, InventoryError<Self::Error>> {
+        Ok(self.outgoing_transfers.get(&witness_id).cloned())
+    }
+}
+
+/// Collects state of the contract history assigned to the given outputs.
+fn history_state(
+    history: &ContractHistory,
+    outputs: &BTreeSet<XOutpoint>,
+) -> BTreeMap<(Opout, XOutputSeal), PersistedState> {
+    let mut res = BTreeMap::new();
+
+    for item in history.fungibles() {
+        if outputs.contains::<XOutpoint>(&item.seal.into()) {
+            res.insert(
+                (item.opout, item.seal),
+                PersistedState::Amount(
+                    item.state.value.into(),
+                    item.state.blinding,
+                    item.state.tag,
+                ),
+            );
         }
+    }
 
-        for item in history.data() {
-            if outputs.contains::<XOutpoint>(&item.seal.into()) {
-                res.insert(
-                    (item.opout, item.seal),
-                    PersistedState::Data(item.state.value.clone(), item.state.salt),
-                );
-            }
+    for item in history.data() {
+        if outputs.contains::<XOutpoint>(&item.seal.into()) {
+            res.insert(
+                (item.opout, item.seal),
+                PersistedState::Data(item.state.value.clone(), item.state.salt),
+            );
         }
+    }
 
-        for item in history.rights() {
-            if outputs.contains::<XOutpoint>(&item.seal.into()) {
-                res.insert((item.opout, item.seal), PersistedState::Void);
-            }
+    for item in history.rights() {
+        if outputs.contains::<XOutpoint>(&item.seal.into()) {
+            res.insert((item.opout, item.seal), PersistedState::Void);
         }
+    }
 
-        for item in history.attach() {
-            if outputs.contains::<XOutpoint>(&item.seal.into()) {
-                res.insert(
-                    (item.opout, item.seal),
-                    PersistedState::Attachment(item.state.clone().into(), item.state.salt),
-                );
-            }
+    for item in history.attach() {
+        if outputs.contains::<XOutpoint>(&item.seal.into()) {
+            res.insert(
+                (item.opout, item.seal),
+                PersistedState::Attachment(item.state.clone().into(), item.state.salt),
+            );
         }
+    }
 
-        Ok(res)
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use bp::dbc::Method;
+    use bp::seals::txout::ExplicitSeal;
+    use bp::{
+        LockTime, OpCode, Outpoint, ScriptPubkey, SeqNo, SigScript, Tx, TxIn, TxOut, TxVer, Txid,
+        Vout, Witness,
+    };
+    use commit_verify::EmbedCommitVerify;
+    use invoice::Amount;
+    use rgb::validation::{ResolveWitness, WitnessResolverError};
+    use rgb::{WitnessPos, XPubWitness};
+    use strict_encoding::StrictSerialize;
+
+    use super::*;
+    use crate::containers::BuilderSeal;
+    use crate::fixtures::{
+        claim_contract, consume, genesis_seal, output, rgb20_contract, rgb20_transfer, stock_with,
+        witness_outputs,
+    };
+    use crate::interface::{IfaceWrapper, Rgb20};
+
+    struct MinedResolver;
+
+    impl ResolveHeight for MinedResolver {
+        type Error = Infallible;
+
+        fn resolve_anchor(&mut self, anchor: &XAnchor) -> Result<WitnessAnchor, Self::Error> {
+            Ok(WitnessAnchor {
+                witness_ord: WitnessOrd::OnChain(WitnessPos::new(840000, 1713571767).unwrap()),
+                witness_id: anchor.witness_id_unchecked(),
+            })
+        }
     }
 
-    fn store_seal_secret(
-        &mut self,
-        seal: XChain<GraphSeal>,
-    ) -> Result<(), InventoryError<Self::Error>> {
-        self.seal_secrets.push(seal)?;
-        Ok(())
+    /// Resolver of the witness transactions by their ids.
+    struct TxResolver(BTreeMap<WitnessId, Tx>);
+
+    impl ResolveWitness for TxResolver {
+        fn resolve_pub_witness(
+            &self,
+            witness_id: WitnessId,
+        ) -> Result<XPubWitness, WitnessResolverError> {
+            self.0
+                .get(&witness_id)
+                .cloned()
+                .map(XChain::Bitcoin)
+                .ok_or(WitnessResolverError::Unknown(witness_id))
+        }
     }
 
-    fn seal_secrets(&self) -> Result<BTreeSet<XChain<GraphSeal>>, InventoryError<Self::Error>> {
-        Ok(self.seal_secrets.to_inner())
+    /// Resolver of the witnesses mined at the given heights.
+    struct HeightResolver(BTreeMap<WitnessId, u32>);
+
+    impl ResolveHeight for HeightResolver {
+        type Error = Infallible;
+
+        fn resolve_anchor(&mut self, anchor: &XAnchor) -> Result<WitnessAnchor, Self::Error> {
+            let witness_id = anchor.witness_id_unchecked();
+            Ok(WitnessAnchor {
+                witness_ord: self.resolve_witness(witness_id)?,
+                witness_id,
+            })
+        }
+
+        fn resolve_witness(&mut self, witness_id: WitnessId) -> Result<WitnessOrd, Self::Error> {
+            Ok(match self.0.get(&witness_id) {
+                Some(height) => WitnessOrd::OnChain(WitnessPos::new(*height, 1713571767).unwrap()),
+                None => WitnessOrd::OffChain,
+            })
+        }
+    }
+
+    #[test]
+    fn update_witnesses() {
+        let contract = rgb20_contract([(1, 600)]);
+        let contract_id = contract.contract_id();
+        let mut stock = stock_with([contract]);
+        let transition = rgb20_transfer(&stock, contract_id, 1, [(0, 100), (1, 500)]);
+        let opid = transition.id();
+        let witness_id = consume(&mut stock, 3, [transition]);
+
+        let transitions = stock.known_transitions(contract_id).unwrap();
+        assert_eq!(transitions[&opid].witness_ord, WitnessOrd::OffChain);
+
+        stock.update_witnesses(&mut MinedResolver).unwrap();
+        let transitions = stock.known_transitions(contract_id).unwrap();
+        assert_eq!(
+            transitions[&opid].witness_ord,
+            WitnessOrd::OnChain(WitnessPos::new(840000, 1713571767).unwrap())
+        );
+        let transfer = stock.outgoing_transfer(witness_id).unwrap().unwrap();
+        assert_eq!(transfer.status, TransferStatus::Mined);
+    }
+
+    #[test]
+    fn migrate_legacy() {
+        let contract = rgb20_contract([(1, 600)]);
+        let contract_id = contract.contract_id();
+        let mut stock = stock_with([contract]);
+        let transition = rgb20_transfer(&stock, contract_id, 1, [(0, 100), (1, 500)]);
+        consume(&mut stock, 3, [transition]);
+
+        let contract_index = stock.contract_index.iter().map(|(id, index)| {
+            (*id, ContractIndexV0 {
+                public_opouts: index.public_opouts.clone(),
+                outpoint_opouts: index.outpoint_opouts.clone(),
+            })
+        });
+        let legacy = StockV0 {
+            hoard: stock.hoard.clone(),
+            history: stock.history.clone(),
+            bundle_op_index: stock.bundle_op_index.clone(),
+            anchor_bundle_index: stock.anchor_bundle_index.clone(),
+            contract_index: Confined::from_iter_unsafe(contract_index),
+            terminal_index: stock.terminal_index.clone(),
+            seal_secrets: stock.seal_secrets.clone(),
+        };
+        let data = legacy.to_strict_serialized::<{ usize::MAX }>().unwrap();
+        let legacy = StockV0::from_strict_serialized::<{ usize::MAX }>(data).unwrap();
+        let migrated = Stock::from(legacy);
+
+        assert_eq!(migrated.version, StockVer::V1);
+        assert_eq!(
+            migrated.known_transitions(contract_id).unwrap(),
+            stock.known_transitions(contract_id).unwrap()
+        );
+        assert_eq!(migrated.witness_ord_index, stock.witness_ord_index);
+    }
+
+    #[test]
+    fn contract_iface_at() {
+        let contract = rgb20_contract([(1, 600)]);
+        let contract_id = contract.contract_id();
+        let mut stock = stock_with([contract]);
+        let transition = rgb20_transfer(&stock, contract_id, 1, [(0, 100), (1, 500)]);
+        let first = transition.id();
+        let first_witness = consume(&mut stock, 3, [transition]);
+        let transition = rgb20_transfer(&stock, contract_id, 3, [(0, 100)]);
+        let second = transition.id();
+        let second_witness = consume(&mut stock, 5, [transition]);
+
+        let state_at = |stock: &Stock, point| {
+            let iface = stock
+                .contract_iface_at(contract_id, Rgb20::IFACE_ID, point)
+                .unwrap();
+            let opids = iface.transitions.keys().copied().collect::<Vec<_>>();
+            let rgb20 = Rgb20::from(iface);
+            let balances = [3, 5].map(|no| rgb20.balance(witness_outputs(no)).unwrap().value());
+            (opids, balances)
+        };
+
+        // Locally consumed witnesses are unmined until they are updated
+        assert_eq!(state_at(&stock, StatePoint::Height(u32::MAX)), (vec![], [0, 0]));
+
+        let heights = bmap! { first_witness => 840000, second_witness => 840010 };
+        stock.update_witnesses(&mut HeightResolver(heights)).unwrap();
+        assert_eq!(state_at(&stock, StatePoint::Height(839999)), (vec![], [0, 0]));
+        assert_eq!(state_at(&stock, StatePoint::Height(840000)), (vec![first], [600, 0]));
+        let mut both = vec![first, second];
+        both.sort();
+        assert_eq!(state_at(&stock, StatePoint::Height(840010)), (both, [600, 100]));
+    }
+
+    #[test]
+    fn contract_iface_at_extension() {
+        let contract = claim_contract();
+        let (contract_id, genesis_id) = (contract.contract_id(), contract.genesis.id());
+        let iface_id = contract.ifaces.keys().next().copied().unwrap();
+        let mut stock = stock_with([contract]);
+        let extension = stock
+            .extension_builder(contract_id, "TestClaim", "claim")
+            .unwrap()
+            .redeem_valency("claim", genesis_id)
+            .unwrap()
+            .add_rights("claimRight", genesis_seal(1))
+            .unwrap()
+            .complete_extension()
+            .unwrap();
+        let opid = extension.id();
+        let witness_id = XChain::Bitcoin(Txid::from([3; 32]));
+        stock.import_extension(extension, witness_id).unwrap();
+
+        // The extension state is never spent
+        let claimed = |stock: &Stock, point| {
+            let iface = stock.contract_iface_at(contract_id, iface_id, point).unwrap();
+            let rights = iface.state.rights().iter();
+            rights.filter(|right| right.opout.op == opid).count()
+        };
+        assert_eq!(claimed(&stock, StatePoint::Height(u32::MAX)), 0);
+
+        let heights = bmap! { witness_id => 840000 };
+        stock.update_witnesses(&mut HeightResolver(heights)).unwrap();
+        assert_eq!(claimed(&stock, StatePoint::Height(839999)), 0);
+        assert_eq!(claimed(&stock, StatePoint::Height(840000)), 1);
+    }
+
+    #[test]
+    fn preview_transfer() {
+        let contract = rgb20_contract([(1, 600)]);
+        let contract_id = contract.contract_id();
+        let mut sender = stock_with([contract]);
+
+        let mut receiver = Stock::default();
+        let seal = XChain::Bitcoin(GraphSeal::with_blinding(
+            Method::TapretFirst,
+            Txid::from([5; 32]),
+            0,
+            5,
+        ));
+        receiver.store_seal_secret(seal).unwrap();
+
+        let mut builder = sender
+            .transition_builder(contract_id, "RGB20", None::<&str>)
+            .unwrap();
+        for ((opout, _), state) in sender.state_for_outpoints(contract_id, [output(1)]).unwrap() {
+            builder = builder.add_input(opout, state).unwrap();
+        }
+        let change = GraphSeal::with_blinded_vout(Method::TapretFirst, Vout::from_u32(1), 0);
+        let change = BuilderSeal::Revealed(XChain::Bitcoin(change));
+        let transition = builder
+            .add_fungible_state("assetOwner", BuilderSeal::Concealed(seal.conceal()), 100u64)
+            .unwrap()
+            .add_fungible_state("assetOwner", change, 500u64)
+            .unwrap()
+            .complete_transition()
+            .unwrap();
+        let witness_id = consume(&mut sender, 3, [transition]);
+        let transfer = sender.transfer(contract_id, [], [seal.conceal()]).unwrap();
+
+        // Witness transaction spending the genesis output and committing to
+        // the transfer bundle
+        let anchored_bundle = transfer.bundles.iter().next().unwrap();
+        let (_, opret) = anchored_bundle.anchor.as_reduced_unsafe().as_split();
+        let commitment = opret
+            .unwrap()
+            .convolve(contract_id, mpc::Message::from(anchored_bundle.bundle.bundle_id()))
+            .unwrap();
+        let mut script_pubkey = ScriptPubkey::new();
+        script_pubkey.push_opcode(OpCode::Return);
+        let mut tx = Tx {
+            version: TxVer::V2,
+            inputs: Confined::try_from(vec![TxIn {
+                prev_output: Outpoint::new(Txid::from([1; 32]), 0),
+                sig_script: SigScript::new(),
+                sequence: SeqNo::from_consensus_u32(0),
+                witness: Witness::new(),
+            }])
+            .unwrap(),
+            outputs: Confined::try_from(vec![TxOut::new(script_pubkey, 0u64)]).unwrap(),
+            lock_time: LockTime::ZERO,
+        };
+        tx.embed_commit(&commitment).unwrap();
+        let transfer = transfer
+            .validate(&mut TxResolver(bmap! { witness_id => tx }), true)
+            .unwrap();
+
+        let before = receiver.to_strict_serialized::<{ usize::MAX }>().unwrap();
+        let preview = receiver
+            .preview_transfer(&transfer, &mut MinedResolver, &AcceptPolicy::default())
+            .unwrap();
+        assert_eq!(preview.contract_id, contract_id);
+        assert!(preview.new_contract);
+        assert!(preview.status.failures.is_empty());
+        assert_eq!(preview.allocations.len(), 1);
+        let allocations = &preview.allocations[&FieldName::from("assetOwner")];
+        assert_eq!(allocations.len(), 1);
+        let (output, state) = &allocations[0];
+        assert_eq!(*output, XChain::Bitcoin(ExplicitSeal::new(
+            Method::TapretFirst,
+            Outpoint::new(Txid::from([5; 32]), 0)
+        )));
+        assert!(matches!(
+            state,
+            PersistedState::Amount(amount, _, _) if *amount == Amount::from(100u64)
+        ));
+        assert_eq!(receiver.to_strict_serialized::<{ usize::MAX }>().unwrap(), before);
+        assert!(receiver.contract_ids().unwrap().is_empty());
+
+        // Accepting the transfer results in the previewed state
+        receiver
+            .accept_transfer(transfer.clone(), &mut MinedResolver, &AcceptPolicy::default())
+            .unwrap();
+        let state = receiver
+            .state_for_outpoints(contract_id, [*output])
+            .unwrap()
+            .into_iter()
+            .map(|((_, output), state)| (output, state))
+            .collect::<Vec<_>>();
+        assert_eq!(&state, allocations);
+
+        // The sender already knows the contract and doesn't own the transferred state
+        let preview = sender
+            .preview_transfer(&transfer, &mut MinedResolver, &AcceptPolicy::default())
+            .unwrap();
+        assert!(!preview.new_contract);
+        assert!(preview.allocations.is_empty());
     }
 }
@@ -0,0 +1,234 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tracking of outgoing transfers through their lifecycle and previews of
+//! incoming transfers.
+
+use std::collections::BTreeMap;
+
+use amplify::confinement::{self, SmallOrdSet, TinyOrdMap};
+use rgb::{validation, ContractId, SecretSeal, WitnessId, XChain, XOutputSeal};
+use strict_encoding::FieldName;
+
+use crate::persistence::PersistedState;
+use crate::LIB_NAME_RGB_STD;
+
+/// Lifecycle status of an outgoing transfer. Statuses are ordered: a transfer
+/// may only move to a status following its current one.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, Default)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD, tags = repr, into_u8, try_from_u8)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display(lowercase)]
+#[repr(u8)]
+pub enum TransferStatus {
+    /// State transitions are composed and the witness transaction is
+    /// constructed.
+    #[default]
+    Composed = 0,
+
+    /// The fascia with the state transitions was consumed by the inventory.
+    Consumed = 1,
+
+    /// The consignment for the beneficiary was created.
+    Consigned = 2,
+
+    /// The witness transaction was broadcast.
+    Broadcast = 3,
+
+    /// The witness transaction was mined.
+    Mined = 4,
+
+    /// The beneficiary has acknowledged the transfer.
+    Acknowledged = 5,
+}
+
+impl TransferStatus {
+    /// Detects whether the transfer has completed its lifecycle.
+    pub fn is_final(self) -> bool { self == TransferStatus::Acknowledged }
+}
+
+/// Record of an outgoing transfer, containing information required to
+/// regenerate the consignments for the beneficiaries.
+///
+/// A single witness transaction may transfer state of multiple contracts, thus
+/// the beneficiaries are recorded per contract.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct OutgoingTransfer {
+    pub witness_id: WitnessId,
+    pub status: TransferStatus,
+    pub contracts: TinyOrdMap<ContractId, TransferBeneficiaries>,
+}
+
+/// Beneficiaries of an outgoing transfer of a single contract state.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct TransferBeneficiaries {
+    /// Beneficiary outputs defined by the witness transaction.
+    pub outputs: SmallOrdSet<XOutputSeal>,
+    /// Beneficiary seals provided in the concealed form.
+    pub secret_seals: SmallOrdSet<XChain<SecretSeal>>,
+}
+
+impl OutgoingTransfer {
+    /// Constructs record for a newly composed transfer, which doesn't have
+    /// any beneficiaries yet.
+    pub fn new(witness_id: WitnessId) -> Self {
+        OutgoingTransfer {
+            witness_id,
+            status: TransferStatus::Composed,
+            contracts: empty!(),
+        }
+    }
+
+    /// Adds beneficiaries of the contract state transferred by the witness
+    /// transaction, extending the ones already known for the contract.
+    pub fn add_beneficiaries(
+        &mut self,
+        contract_id: ContractId,
+        outputs: impl IntoIterator<Item = XOutputSeal>,
+        secret_seals: impl IntoIterator<Item = XChain<SecretSeal>>,
+    ) -> Result<(), confinement::Error> {
+        let mut beneficiaries = self.contracts.remove(&contract_id)?.unwrap_or_default();
+        beneficiaries.outputs.extend(outputs)?;
+        beneficiaries.secret_seals.extend(secret_seals)?;
+        self.contracts.insert(contract_id, beneficiaries)?;
+        Ok(())
+    }
+}
+
+/// Result of a transfer acceptance dry-run produced by
+/// [`crate::persistence::Inventory::preview_transfer`].
+#[derive(Clone, Debug)]
+pub struct TransferPreview {
+    pub contract_id: ContractId,
+    /// Whether the contract is not yet known to the inventory and will be
+    /// imported with the transfer.
+    pub new_contract: bool,
+    /// State which will be assigned to the outputs controlled by the wallet
+    /// (i.e. the transfer terminals with known seal secrets), grouped by the
+    /// name of the interface assignment field.
+    pub allocations: BTreeMap<FieldName, Vec<(XOutputSeal, PersistedState)>>,
+    /// Transfer validation status, including warnings produced by the
+    /// acceptance.
+    pub status: validation::Status,
+}
+
+#[cfg(test)]
+mod test {
+    use bp::dbc::Method;
+    use bp::seals::txout::ExplicitSeal;
+    use bp::{Outpoint, Txid, Vout};
+    use rgb::{GraphSeal, SecretSeal};
+
+    use super::*;
+    use crate::containers::BuilderSeal;
+    use crate::fixtures::{consume, output, rgb20_contract, stock_with};
+    use crate::persistence::inventory::DataError;
+    use crate::persistence::{Inventory, InventoryError, Stock};
+
+    #[test]
+    fn lifecycle() {
+        let witness_id = XChain::Bitcoin(Txid::from([1u8; 32]));
+        let seal = XChain::Bitcoin(SecretSeal::from([3u8; 32]));
+
+        let mut stock = Stock::default();
+        let mut transfer = OutgoingTransfer::new(witness_id);
+        for contract_id in [ContractId::from([2u8; 32]), ContractId::from([4u8; 32])] {
+            transfer.add_beneficiaries(contract_id, [], [seal]).unwrap();
+        }
+        stock.store_outgoing_transfer(transfer).unwrap();
+        assert_eq!(stock.pending_transfers().unwrap().len(), 1);
+
+        stock
+            .update_transfer_status(witness_id, TransferStatus::Broadcast)
+            .unwrap();
+        assert!(matches!(
+            stock.update_transfer_status(witness_id, TransferStatus::Consumed),
+            Err(InventoryError::DataError(DataError::TransferStatus(
+                _,
+                TransferStatus::Broadcast,
+                TransferStatus::Consumed
+            )))
+        ));
+        stock
+            .update_transfer_status(witness_id, TransferStatus::Acknowledged)
+            .unwrap();
+        assert!(stock.pending_transfers().unwrap().is_empty());
+        let transfer = stock.outgoing_transfer(witness_id).unwrap().unwrap();
+        assert_eq!(transfer.status, TransferStatus::Acknowledged);
+        assert_eq!(transfer.contracts.len(), 2);
+    }
+
+    #[test]
+    fn consume_fascia() {
+        let contract = rgb20_contract([(1, 600)]);
+        let contract_id = contract.contract_id();
+        let mut stock = stock_with([contract]);
+
+        let secret_seal = XChain::Bitcoin(SecretSeal::from([3u8; 32]));
+        let change = GraphSeal::with_blinded_vout(Method::TapretFirst, Vout::from_u32(1), 0);
+        let change = BuilderSeal::Revealed(XChain::Bitcoin(change));
+        let mut builder = stock
+            .transition_builder(contract_id, "RGB20", None::<&str>)
+            .unwrap();
+        for ((opout, _), state) in stock.state_for_outpoints(contract_id, [output(1)]).unwrap() {
+            builder = builder.add_input(opout, state).unwrap();
+        }
+        let transition = builder
+            .add_fungible_state("assetOwner", BuilderSeal::Concealed(secret_seal), 100u64)
+            .unwrap()
+            .add_fungible_state("assetOwner", change, 500u64)
+            .unwrap()
+            .complete_transition()
+            .unwrap();
+        let witness_id = consume(&mut stock, 3, [transition]);
+
+        let transfer = stock.outgoing_transfer(witness_id).unwrap().unwrap();
+        assert_eq!(transfer.status, TransferStatus::Consumed);
+        assert_eq!(transfer.contracts.len(), 1);
+        let beneficiaries = &transfer.contracts[&contract_id];
+        let change = ExplicitSeal::new(Method::TapretFirst, Outpoint::new(Txid::from([3; 32]), 1));
+        assert_eq!(beneficiaries.outputs.iter().copied().collect::<Vec<_>>(), vec![
+            XChain::Bitcoin(change)
+        ]);
+        assert_eq!(beneficiaries.secret_seals.iter().copied().collect::<Vec<_>>(), vec![
+            secret_seal
+        ]);
+    }
+}
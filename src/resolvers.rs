@@ -19,10 +19,20 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use rgb::{WitnessAnchor, XAnchor};
+use rgb::{WitnessAnchor, WitnessId, WitnessOrd, XAnchor};
 
 pub trait ResolveHeight {
     type Error: std::error::Error;
 
     fn resolve_anchor(&mut self, anchor: &XAnchor) -> Result<WitnessAnchor, Self::Error>;
+
+    /// Resolves mining status of a witness transaction for which no anchor is
+    /// known, like the ones state extensions are imported with.
+    ///
+    /// Defaults to [`WitnessOrd::OffChain`], meaning that the resolver is not
+    /// able to locate the transaction.
+    fn resolve_witness(&mut self, witness_id: WitnessId) -> Result<WitnessOrd, Self::Error> {
+        let _ = witness_id;
+        Ok(WitnessOrd::OffChain)
+    }
 }
@@ -25,17 +25,22 @@ mod stl;
 mod error;
 mod mime;
 mod chain;
+mod records;
 
 pub use chain::ProofOfReserves;
 use error::Error;
 pub use invoice::LIB_NAME_RGB_CONTRACT;
 pub use mime::{MediaRegName, MediaType};
+pub use records::{
+    DomainName, DomainRecord, IdentityKey, KeyAlgo, LogEntry, LIB_NAME_RGB_RECORDS,
+};
 pub use specs::{
     AssetSpec, AssetTerms, Attachment, BurnMeta, Details, IssueMeta, Name, RicardianContract,
     Ticker,
 };
 pub use stl::{
     aluvm_stl, bp_core_stl, bp_tx_stl, commit_verify_stl, rgb_contract_stl, rgb_core_stl,
-    rgb_std_stl, StandardTypes, LIB_ID_RGB, LIB_ID_RGB_CONTRACT, LIB_ID_RGB_STD,
+    rgb_records_stl, rgb_std_stl, StandardTypes, LIB_ID_RGB, LIB_ID_RGB_CONTRACT,
+    LIB_ID_RGB_RECORDS, LIB_ID_RGB_STD,
 };
 pub const LIB_NAME_RGB_STD: &str = "RGBStd";
@@ -0,0 +1,183 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2023-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2023-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![allow(unused_braces)] // caused by rustc unable to understand strict_dumb
+
+use std::fmt::{self, Debug, Formatter};
+use std::str::FromStr;
+
+use amplify::ascii::AsciiString;
+use amplify::confinement::{Confined, NonEmptyString, TinyBlob, U8};
+use strict_encoding::{InvalidIdent, StrictDeserialize, StrictDumb, StrictSerialize};
+
+use super::{Details, Name};
+
+pub const LIB_NAME_RGB_RECORDS: &str = "RGBRecords";
+
+/// Algorithm of a public key controlled by a digital identity.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_RECORDS, tags = repr, into_u8, try_from_u8)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display(lowercase)]
+#[repr(u8)]
+pub enum KeyAlgo {
+    #[strict_type(dumb)]
+    Bip340 = 0,
+    Ecdsa = 1,
+    Ed25519 = 2,
+}
+
+/// Public key of a digital identity (RGB22).
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_RECORDS)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct IdentityKey {
+    pub algo: KeyAlgo,
+    pub key: TinyBlob,
+}
+impl StrictSerialize for IdentityKey {}
+impl StrictDeserialize for IdentityKey {}
+
+impl IdentityKey {
+    pub fn new(algo: KeyAlgo, key: TinyBlob) -> Self { IdentityKey { algo, key } }
+}
+
+/// Entry of an audit log (RGB23), committing to some externally kept data.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_RECORDS)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct LogEntry {
+    pub digest: [u8; 32],
+    pub details: Option<Details>,
+}
+impl StrictSerialize for LogEntry {}
+impl StrictDeserialize for LogEntry {}
+
+impl LogEntry {
+    pub fn new(digest: [u8; 32], details: Option<Details>) -> Self { LogEntry { digest, details } }
+}
+
+/// Domain name registered with a naming contract (RGB24).
+///
+/// Domain names consist of dot-separated labels made of lowercase latin
+/// letters, digits and hyphens.
+#[derive(Wrapper, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, From)]
+#[wrapper(Deref, Display)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_RECORDS)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", transparent)
+)]
+pub struct DomainName(NonEmptyString<U8>);
+impl StrictSerialize for DomainName {}
+impl StrictDeserialize for DomainName {}
+
+impl AsRef<str> for DomainName {
+    #[inline]
+    fn as_ref(&self) -> &str { self.0.as_str() }
+}
+
+impl StrictDumb for DomainName {
+    fn strict_dumb() -> Self { Self(Confined::try_from(s!("dumb.domain")).unwrap()) }
+}
+
+impl FromStr for DomainName {
+    type Err = InvalidIdent;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ascii = AsciiString::from_ascii(s.as_bytes())?;
+        if ascii.is_empty() {
+            return Err(InvalidIdent::Empty);
+        }
+        if let Some(ch) = ascii.as_slice().iter().copied().find(|ch| {
+            !(ch.is_ascii_lowercase() || ch.is_ascii_digit() || *ch == '-' || *ch == '.')
+        }) {
+            return Err(InvalidIdent::InvalidChar(ascii, ch));
+        }
+        if s.split('.').any(str::is_empty) {
+            return Err(InvalidIdent::Empty);
+        }
+        let s = Confined::try_from_iter(s.chars())?;
+        Ok(Self(s))
+    }
+}
+
+impl TryFrom<String> for DomainName {
+    type Error = InvalidIdent;
+
+    fn try_from(name: String) -> Result<Self, InvalidIdent> { Self::from_str(&name) }
+}
+
+impl Debug for DomainName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DomainName").field(&self.as_str()).finish()
+    }
+}
+
+/// Record associated with a domain name registered with a naming contract
+/// (RGB24).
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_RECORDS)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct DomainRecord {
+    pub domain: DomainName,
+    pub key: Name,
+    pub value: Details,
+}
+impl StrictSerialize for DomainRecord {}
+impl StrictDeserialize for DomainRecord {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn domain_name() {
+        assert!(DomainName::from_str("example.rgb").is_ok());
+        assert!(DomainName::from_str("my-site.example.rgb").is_ok());
+        assert!(DomainName::from_str("").is_err());
+        assert!(DomainName::from_str("Example.rgb").is_err());
+        assert!(DomainName::from_str("example..rgb").is_err());
+        assert!(DomainName::from_str("exa mple.rgb").is_err());
+    }
+}
@@ -29,7 +29,10 @@ use strict_types::stl::{std_stl, strict_types_stl};
 use strict_types::typesys::SystemBuilder;
 use strict_types::{CompileError, LibBuilder, SemId, SymbolicSys, TypeLib, TypeSystem};
 
-use super::{AssetSpec, AssetTerms, BurnMeta, Error, IssueMeta, MediaType, LIB_NAME_RGB_CONTRACT};
+use super::{
+    AssetSpec, AssetTerms, BurnMeta, DomainName, DomainRecord, Error, IdentityKey, IssueMeta,
+    LogEntry, MediaType, LIB_NAME_RGB_CONTRACT, LIB_NAME_RGB_RECORDS,
+};
 use crate::containers::{Contract, Transfer};
 use crate::persistence::Stock;
 use crate::stl::ProofOfReserves;
@@ -40,9 +43,14 @@ use crate::LIB_NAME_RGB_STD;
 pub const LIB_ID_RGB_CONTRACT: &str =
     "urn:ubideco:stl:pvPyTM1N1rG9HEAvvEgqKonnMdakHreYrvGWfFff8q6#atlanta-adrian-sonata";
 
+/// Strict types id for the library providing data types for RGB22, RGB23 and
+/// RGB24 interfaces.
+pub const LIB_ID_RGB_RECORDS: &str =
+    "urn:ubideco:stl:7cw5jSm9Z3Mp6J4q63jcEnmduD6WxoLx44cayFtV29Bn#siren-mirage-rachel";
+
 /// Strict types id for the library representing of RGB StdLib data types.
 pub const LIB_ID_RGB_STD: &str =
-    "urn:ubideco:stl:K4PwUS8f7zqaDaKNVF1Cr23grxJwjoZrZZ6sYbrXRL3#market-senior-product";
+    "urn:ubideco:stl:GPZShbZaKRE3WywtFm1QFqgmcgPHVXNYbxeKps7LCiHo#salon-copy-monitor";
 
 fn _rgb_std_stl() -> Result<TypeLib, CompileError> {
     LibBuilder::new(libname!(LIB_NAME_RGB_STD), tiny_bset! {
@@ -75,6 +83,19 @@ fn _rgb_contract_stl() -> Result<TypeLib, CompileError> {
     .compile()
 }
 
+#[allow(clippy::result_large_err)]
+fn _rgb_records_stl() -> Result<TypeLib, CompileError> {
+    LibBuilder::new(libname!(LIB_NAME_RGB_RECORDS), tiny_bset! {
+        std_stl().to_dependency(),
+        rgb_contract_stl().to_dependency()
+    })
+    .transpile::<IdentityKey>()
+    .transpile::<LogEntry>()
+    .transpile::<DomainName>()
+    .transpile::<DomainRecord>()
+    .compile()
+}
+
 /// Generates strict type library representation of RGB StdLib data types.
 pub fn rgb_std_stl() -> TypeLib { _rgb_std_stl().expect("invalid strict type RGBStd library") }
 
@@ -84,6 +105,12 @@ pub fn rgb_contract_stl() -> TypeLib {
     _rgb_contract_stl().expect("invalid strict type RGBContract library")
 }
 
+/// Generates strict type library providing data types for RGB22, RGB23 and
+/// RGB24 interfaces.
+pub fn rgb_records_stl() -> TypeLib {
+    _rgb_records_stl().expect("invalid strict type RGBRecords library")
+}
+
 #[derive(Debug)]
 pub struct StandardTypes(SymbolicSys);
 
@@ -131,6 +158,12 @@ mod test {
         assert_eq!(lib.id().to_string(), LIB_ID_RGB_CONTRACT);
     }
 
+    #[test]
+    fn records_lib_id() {
+        let lib = rgb_records_stl();
+        assert_eq!(lib.id().to_string(), LIB_ID_RGB_RECORDS);
+    }
+
     #[test]
     fn std_lib_id() {
         let lib = rgb_std_stl();
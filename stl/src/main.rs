@@ -28,10 +28,10 @@ use std::io::Write;
 
 use commit_verify::CommitmentLayout;
 use rgbstd::containers::Transfer;
-use rgbstd::interface::{IfaceClass, Rgb20, Rgb21, Rgb25};
+use rgbstd::interface::{IfaceClass, Rgb20, Rgb21, Rgb22, Rgb23, Rgb24, Rgb25};
 use rgbstd::stl::{
     aluvm_stl, bp_core_stl, bp_tx_stl, commit_verify_stl, rgb_contract_stl, rgb_core_stl,
-    rgb_std_stl,
+    rgb_records_stl, rgb_std_stl,
 };
 use strict_types::stl::{std_stl, strict_types_stl};
 use strict_types::{parse_args, StlFormat, SystemBuilder};
@@ -79,6 +79,28 @@ fn main() {
   Description: Types for RGB21 interface
   Author: Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
   Copyright (C) 2023-2024-2024 LNP/BP Standards Association. All rights reserved.
+  License: Apache-2.0",
+            ),
+        )
+        .expect("unable to write to the file");
+
+    let rgb_records = rgb_records_stl();
+    rgb_records
+        .serialize(StlFormat::Binary, Some(&dir), "0.1.0", None)
+        .expect("unable to write to the file");
+    rgb_records
+        .serialize(StlFormat::Armored, Some(&dir), "0.1.0", None)
+        .expect("unable to write to the file");
+    rgb_records
+        .serialize(
+            StlFormat::Source,
+            Some(&dir),
+            "0.1.0",
+            Some(
+                "
+  Description: Types for RGB22, RGB23 and RGB24 interfaces
+  Author: Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+  Copyright (C) 2023-2024-2024 LNP/BP Standards Association. All rights reserved.
   License: Apache-2.0",
             ),
         )
@@ -138,6 +160,8 @@ fn main() {
     let ifsys = SystemBuilder::new()
         .import(rgb21)
         .unwrap()
+        .import(rgb_records)
+        .unwrap()
         .import(rgb_contract_stl())
         .unwrap()
         .import(bp_tx_stl())
@@ -153,6 +177,15 @@ fn main() {
     let rgb21 = Rgb21::iface();
     fs::write(format!("{dir}/RGB21.con"), format!("{}", rgb21.display(none!(), &ifsys))).unwrap();
 
+    let rgb22 = Rgb22::iface();
+    fs::write(format!("{dir}/RGB22.con"), format!("{}", rgb22.display(none!(), &ifsys))).unwrap();
+
+    let rgb23 = Rgb23::iface();
+    fs::write(format!("{dir}/RGB23.con"), format!("{}", rgb23.display(none!(), &ifsys))).unwrap();
+
+    let rgb24 = Rgb24::iface();
+    fs::write(format!("{dir}/RGB24.con"), format!("{}", rgb24.display(none!(), &ifsys))).unwrap();
+
     let rgb25 = Rgb25::iface();
     fs::write(format!("{dir}/RGB25.con"), format!("{}", rgb25.display(none!(), &ifsys))).unwrap();
 